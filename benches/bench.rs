@@ -54,6 +54,33 @@ mod numbers {
   }
 }
 
+mod bytes {
+  use capacity_builder::BytesBuilder;
+
+  #[divan::bench]
+  fn bytes_builder_tiny_appends() -> usize {
+    BytesBuilder::<Vec<u8>>::build(|builder| {
+      for i in 0..1000u32 {
+        builder.append(i as u8);
+        builder.append_le(i);
+      }
+    })
+    .unwrap()
+    .len()
+  }
+
+  #[divan::bench]
+  fn vec_new() -> usize {
+    let mut bytes = Vec::new();
+    for i in 0..1000u32 {
+      bytes.push(i as u8);
+      bytes.extend_from_slice(&i.to_le_bytes());
+    }
+    bytes.shrink_to_fit();
+    bytes.len()
+  }
+}
+
 mod small_string_types {
   use capacity_builder::StringType;
 
@@ -0,0 +1,191 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::LitStr;
+use syn::Member;
+
+use crate::string_appendable::is_reference;
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+  let name = &input.ident;
+  let Data::Struct(data) = &input.data else {
+    return Err(syn::Error::new(
+      input.span(),
+      "BytesAppendable can only be derived for structs",
+    ));
+  };
+  let container = Options::parse(&input.attrs)?;
+  if let Some(len_prefix) = &container.len_prefix {
+    return Err(syn::Error::new(
+      len_prefix.span(),
+      "len_prefix can only be specified on fields",
+    ));
+  }
+  let body = appends_for_fields(&data.fields, container.endianness)?;
+
+  let mut generics = input.generics.clone();
+  generics
+    .params
+    .insert(0, syn::parse_quote!('capacity_builder_a));
+  {
+    let where_clause = generics.make_where_clause();
+    for param in input.generics.type_params() {
+      let ident = &param.ident;
+      where_clause
+        .predicates
+        .push(syn::parse_quote!(#ident: 'capacity_builder_a));
+    }
+  }
+  let (impl_generics, _, where_clause) = generics.split_for_impl();
+  let (_, ty_generics, _) = input.generics.split_for_impl();
+
+  Ok(quote! {
+    impl #impl_generics capacity_builder::BytesAppendable<'capacity_builder_a>
+      for &'capacity_builder_a #name #ty_generics #where_clause
+    {
+      fn append_to_builder<TBytes: capacity_builder::BytesType>(
+        self,
+        builder: &mut capacity_builder::BytesBuilder<'capacity_builder_a, TBytes>,
+      ) {
+        #body
+      }
+    }
+  })
+}
+
+fn appends_for_fields(
+  fields: &Fields,
+  default_endianness: Option<Endianness>,
+) -> syn::Result<TokenStream> {
+  let mut appends = Vec::new();
+  for (i, field) in fields.iter().enumerate() {
+    let options = Options::parse(&field.attrs)?;
+    if options.skip {
+      continue;
+    }
+    let member = match &field.ident {
+      Some(ident) => Member::Named(ident.clone()),
+      None => Member::Unnamed(i.into()),
+    };
+    // the container's endianness only applies to integer fields
+    let endianness = options.endianness.or_else(|| {
+      if is_integer(&field.ty) {
+        default_endianness
+      } else {
+        None
+      }
+    });
+    let append = match endianness {
+      Some(endianness) => {
+        let value = if is_reference(&field.ty) {
+          quote!(*self.#member)
+        } else {
+          quote!(self.#member)
+        };
+        match endianness {
+          Endianness::Big => quote!(builder.append_be(#value);),
+          Endianness::Little => quote!(builder.append_le(#value);),
+        }
+      }
+      None => {
+        if is_reference(&field.ty) {
+          quote!(builder.append(&*self.#member);)
+        } else {
+          quote!(builder.append(&self.#member);)
+        }
+      }
+    };
+    match &options.len_prefix {
+      Some(len_prefix) => {
+        let len_prefix = parse_len_prefix(len_prefix)?;
+        appends.push(quote! {
+          builder.append_len_prefixed(#len_prefix, |builder| {
+            #append
+          });
+        });
+      }
+      None => appends.push(append),
+    }
+  }
+  Ok(quote!(#(#appends)*))
+}
+
+fn is_integer(ty: &syn::Type) -> bool {
+  const INTEGERS: [&str; 12] = [
+    "u8", "u16", "u32", "u64", "u128", "usize", "i8", "i16", "i32", "i64",
+    "i128", "isize",
+  ];
+  match ty {
+    syn::Type::Path(path) => path
+      .path
+      .get_ident()
+      .map(|ident| INTEGERS.iter().any(|i| ident == i))
+      .unwrap_or(false),
+    syn::Type::Group(group) => is_integer(&group.elem),
+    syn::Type::Paren(paren) => is_integer(&paren.elem),
+    _ => false,
+  }
+}
+
+fn parse_len_prefix(value: &LitStr) -> syn::Result<TokenStream> {
+  let len_prefix = quote!(capacity_builder::frame::LenPrefix);
+  let big = quote!(capacity_builder::frame::Endianness::Big);
+  let little = quote!(capacity_builder::frame::Endianness::Little);
+  Ok(match value.value().as_str() {
+    "u8" => quote!(#len_prefix::U8),
+    "u16_be" => quote!(#len_prefix::U16(#big)),
+    "u16_le" => quote!(#len_prefix::U16(#little)),
+    "u32_be" => quote!(#len_prefix::U32(#big)),
+    "u32_le" => quote!(#len_prefix::U32(#little)),
+    "u64_be" => quote!(#len_prefix::U64(#big)),
+    "u64_le" => quote!(#len_prefix::U64(#little)),
+    _ => {
+      return Err(syn::Error::new(
+        value.span(),
+        "expected one of: u8, u16_be, u16_le, u32_be, u32_le, u64_be, u64_le",
+      ))
+    }
+  })
+}
+
+#[derive(Clone, Copy)]
+enum Endianness {
+  Big,
+  Little,
+}
+
+struct Options {
+  endianness: Option<Endianness>,
+  len_prefix: Option<LitStr>,
+  skip: bool,
+}
+
+impl Options {
+  fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+    let mut options = Options {
+      endianness: None,
+      len_prefix: None,
+      skip: false,
+    };
+    for attr in attrs.iter().filter(|a| a.path().is_ident("bytes")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("be") {
+          options.endianness = Some(Endianness::Big);
+        } else if meta.path.is_ident("le") {
+          options.endianness = Some(Endianness::Little);
+        } else if meta.path.is_ident("len_prefix") {
+          options.len_prefix = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("skip") {
+          options.skip = true;
+        } else {
+          return Err(meta.error("unknown bytes attribute"));
+        }
+        Ok(())
+      })?;
+    }
+    Ok(options)
+  }
+}
@@ -0,0 +1,250 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use quote::ToTokens;
+use syn::Data;
+use syn::DeriveInput;
+use syn::LitStr;
+
+use crate::string_appendable::appends_for_fields;
+use crate::string_appendable::field_types;
+use crate::string_appendable::is_reference;
+use crate::string_appendable::mentions_any;
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+  let name = &input.ident;
+  let options = Options::parse(&input.attrs)?;
+  let mut generics = input.generics.clone();
+  {
+    let (_, ty_generics, _) = input.generics.split_for_impl();
+    generics
+      .make_where_clause()
+      .predicates
+      .push(syn::parse_quote!(
+        for<'capacity_builder_a> &'capacity_builder_a #name #ty_generics:
+          capacity_builder::StringAppendable<'capacity_builder_a>
+      ));
+  }
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+  let inherent = if options.no_inherent {
+    quote!()
+  } else {
+    quote! {
+      impl #impl_generics #name #ty_generics #where_clause {
+        pub fn to_string(&self) -> String {
+          capacity_builder::StringBuilder::<String>::build(|builder| {
+            builder.append(self)
+          }).unwrap()
+        }
+
+        pub fn to_custom_string<TString: capacity_builder::StringType>(&self) -> TString {
+          capacity_builder::StringBuilder::<TString>::build(|builder| {
+            builder.append(self)
+          }).unwrap()
+        }
+      }
+    }
+  };
+
+  let into_string = if options.into_string {
+    let mut ref_generics = generics.clone();
+    ref_generics
+      .params
+      .insert(0, syn::parse_quote!('capacity_builder_b));
+    let (ref_impl_generics, _, _) = ref_generics.split_for_impl();
+    quote! {
+      impl #ref_impl_generics From<&'capacity_builder_b #name #ty_generics> for String #where_clause {
+        fn from(value: &'capacity_builder_b #name #ty_generics) -> Self {
+          capacity_builder::StringBuilder::<String>::build(|builder| {
+            builder.append(value)
+          }).unwrap()
+        }
+      }
+
+      impl #impl_generics From<#name #ty_generics> for String #where_clause {
+        fn from(value: #name #ty_generics) -> Self {
+          capacity_builder::StringBuilder::<String>::build(|builder| {
+            builder.append(&value)
+          }).unwrap()
+        }
+      }
+    }
+  } else {
+    quote!()
+  };
+
+  let serialize = if options.serialize {
+    serialize_impl(name, &impl_generics, &ty_generics, where_clause)?
+  } else {
+    quote!()
+  };
+
+  let fmt_fn = if options.edits {
+    quote!(fmt_with_edits)
+  } else {
+    quote!(fmt)
+  };
+
+  let debug = match &options.debug {
+    Some(format) => debug_impl(&input, &generics, format.as_ref(), &fmt_fn)?,
+    None => quote!(),
+  };
+
+  Ok(quote! {
+    #inherent
+    #into_string
+    #serialize
+    #debug
+
+    impl #impl_generics std::fmt::Display for #name #ty_generics #where_clause {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        capacity_builder::StringBuilder::<String>::#fmt_fn(f, |builder| {
+          builder.append(self)
+        })
+      }
+    }
+  })
+}
+
+fn debug_impl(
+  input: &DeriveInput,
+  generics: &syn::Generics,
+  format: Option<&LitStr>,
+  fmt_fn: &TokenStream,
+) -> syn::Result<TokenStream> {
+  let name = &input.ident;
+  let mut generics = generics.clone();
+  let body = match format {
+    Some(format) => {
+      let Data::Struct(data) = &input.data else {
+        return Err(syn::Error::new(
+          format.span(),
+          "a debug format is only supported on structs",
+        ));
+      };
+      // require generic fields to be appendable
+      let type_params = input
+        .generics
+        .type_params()
+        .map(|param| param.ident.to_string())
+        .collect::<Vec<_>>();
+      let where_clause = generics.make_where_clause();
+      for ty in field_types(&input.data) {
+        if !is_reference(ty) && mentions_any(ty.to_token_stream(), &type_params)
+        {
+          where_clause.predicates.push(syn::parse_quote!(
+            for<'capacity_builder_a> &'capacity_builder_a #ty:
+              capacity_builder::StringAppendable<'capacity_builder_a>
+          ));
+        }
+      }
+      appends_for_fields(&data.fields, Some(format), |member, ty| {
+        if is_reference(ty) {
+          quote!(&*self.#member)
+        } else {
+          quote!(&self.#member)
+        }
+      })?
+    }
+    None => quote!(builder.append(self);),
+  };
+  let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+  Ok(quote! {
+    impl #impl_generics std::fmt::Debug for #name #ty_generics #where_clause {
+      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        capacity_builder::StringBuilder::<String>::#fmt_fn(f, |builder| {
+          #body
+        })
+      }
+    }
+  })
+}
+
+#[cfg(feature = "serde")]
+fn serialize_impl(
+  name: &syn::Ident,
+  impl_generics: &syn::ImplGenerics,
+  ty_generics: &syn::TypeGenerics,
+  where_clause: Option<&syn::WhereClause>,
+) -> syn::Result<TokenStream> {
+  Ok(quote! {
+    impl #impl_generics capacity_builder::__serde::Serialize for #name #ty_generics #where_clause {
+      fn serialize<S: capacity_builder::__serde::Serializer>(
+        &self,
+        serializer: S,
+      ) -> Result<S::Ok, S::Error> {
+        let text = capacity_builder::StringBuilder::<String>::build(|builder| {
+          builder.append(self)
+        }).map_err(<S::Error as capacity_builder::__serde::ser::Error>::custom)?;
+        serializer.serialize_str(&text)
+      }
+    }
+  })
+}
+
+#[cfg(not(feature = "serde"))]
+fn serialize_impl(
+  name: &syn::Ident,
+  _impl_generics: &syn::ImplGenerics,
+  _ty_generics: &syn::TypeGenerics,
+  _where_clause: Option<&syn::WhereClause>,
+) -> syn::Result<TokenStream> {
+  Err(syn::Error::new(
+    name.span(),
+    "the `serde` feature of capacity_builder must be enabled to use `serialize`",
+  ))
+}
+
+struct Options {
+  /// Don't generate the inherent `to_string` and `to_custom_string`
+  /// methods, which shadow `ToString::to_string`.
+  no_inherent: bool,
+  /// Implement `From<T>` and `From<&T>` for `String`.
+  into_string: bool,
+  /// Implement `serde::Serialize` serializing as a string.
+  serialize: bool,
+  /// Implement `Debug` with the same output as `Display` or, when
+  /// provided, using the format string.
+  debug: Option<Option<LitStr>>,
+  /// Format with `StringBuilder::fmt_with_edits`, which supports
+  /// truncating and inserting text.
+  edits: bool,
+}
+
+impl Options {
+  fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+    let mut options = Options {
+      no_inherent: false,
+      into_string: false,
+      serialize: false,
+      debug: None,
+      edits: false,
+    };
+    for attr in attrs
+      .iter()
+      .filter(|a| a.path().is_ident("capacity_display"))
+    {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("no_inherent") {
+          options.no_inherent = true;
+        } else if meta.path.is_ident("into_string") {
+          options.into_string = true;
+        } else if meta.path.is_ident("serialize") {
+          options.serialize = true;
+        } else if meta.path.is_ident("edits") {
+          options.edits = true;
+        } else if meta.path.is_ident("debug") {
+          options.debug = Some(if meta.input.peek(syn::Token![=]) {
+            Some(meta.value()?.parse()?)
+          } else {
+            None
+          });
+        } else {
+          return Err(meta.error("unknown capacity_display attribute"));
+        }
+        Ok(())
+      })?;
+    }
+    Ok(options)
+  }
+}
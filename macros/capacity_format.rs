@@ -0,0 +1,171 @@
+use proc_macro2::TokenStream;
+use quote::format_ident;
+use quote::quote;
+use syn::parse::Parse;
+use syn::parse::ParseStream;
+use syn::Expr;
+use syn::Ident;
+use syn::LitStr;
+use syn::Token;
+
+pub struct FormatInput {
+  format: LitStr,
+  positional: Vec<Expr>,
+  named: Vec<(Ident, Expr)>,
+}
+
+impl Parse for FormatInput {
+  fn parse(input: ParseStream) -> syn::Result<Self> {
+    let format = input.parse()?;
+    let mut positional = Vec::new();
+    let mut named = Vec::new();
+    while !input.is_empty() {
+      input.parse::<Token![,]>()?;
+      if input.is_empty() {
+        break;
+      }
+      if input.peek(Ident) && input.peek2(Token![=]) && !input.peek2(Token![==])
+      {
+        let name = input.parse()?;
+        input.parse::<Token![=]>()?;
+        named.push((name, input.parse()?));
+      } else if !named.is_empty() {
+        return Err(input.error("positional arguments must come before named"));
+      } else {
+        positional.push(input.parse()?);
+      }
+    }
+    Ok(Self {
+      format,
+      positional,
+      named,
+    })
+  }
+}
+
+enum Piece {
+  Literal(String),
+  Arg { index: usize, spec: Option<String> },
+}
+
+pub fn expand(input: FormatInput) -> syn::Result<TokenStream> {
+  let FormatInput {
+    format,
+    positional,
+    mut named,
+  } = input;
+  let error = |message: String| syn::Error::new(format.span(), message);
+
+  // parse the format string
+  let value = format.value();
+  let mut pieces = Vec::new();
+  let mut literal = String::new();
+  let mut next_index = 0;
+  let mut chars = value.chars().peekable();
+  let mut used = vec![false; positional.len()];
+  while let Some(c) = chars.next() {
+    match c {
+      '{' if chars.peek() == Some(&'{') => {
+        chars.next();
+        literal.push('{');
+      }
+      '}' if chars.peek() == Some(&'}') => {
+        chars.next();
+        literal.push('}');
+      }
+      '{' => {
+        let mut inner = String::new();
+        loop {
+          match chars.next() {
+            Some('}') => break,
+            Some(c) => inner.push(c),
+            None => return Err(error("unclosed `{` in format string".into())),
+          }
+        }
+        let (target, spec) = match inner.split_once(':') {
+          Some((target, spec)) => (target.trim(), Some(spec.to_string())),
+          None => (inner.trim(), None),
+        };
+        let index = if target.is_empty() {
+          next_index += 1;
+          next_index - 1
+        } else if let Ok(index) = target.parse::<usize>() {
+          index
+        } else {
+          let ident = syn::parse_str::<Ident>(target)
+            .map_err(|_| error(format!("invalid argument `{}`", target)))?;
+          match named.iter().position(|(name, _)| *name == ident) {
+            Some(position) => positional.len() + position,
+            None => {
+              // implicitly captured identifier
+              named.push((ident.clone(), syn::parse_quote!(#ident)));
+              positional.len() + named.len() - 1
+            }
+          }
+        };
+        if index < positional.len() {
+          used[index] = true;
+        } else if target.is_empty() || target.parse::<usize>().is_ok() {
+          return Err(error(format!(
+            "invalid reference to positional argument {} ({} provided)",
+            index,
+            positional.len()
+          )));
+        }
+        if !literal.is_empty() {
+          pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+        }
+        pieces.push(Piece::Arg { index, spec });
+      }
+      '}' => return Err(error("unmatched `}` in format string".into())),
+      c => literal.push(c),
+    }
+  }
+  if !literal.is_empty() {
+    pieces.push(Piece::Literal(literal));
+  }
+  if let Some(index) = used.iter().position(|used| !used) {
+    return Err(error(format!("argument {} is never used", index)));
+  }
+
+  let exprs = positional
+    .iter()
+    .chain(named.iter().map(|(_, expr)| expr))
+    .collect::<Vec<_>>();
+  let bindings = (0..exprs.len())
+    .map(|i| format_ident!("__arg{}", i))
+    .collect::<Vec<_>>();
+  let appends = pieces.iter().map(|piece| match piece {
+    Piece::Literal(text) => quote!(builder.append(#text);),
+    Piece::Arg { index, spec: None } => {
+      let binding = &bindings[*index];
+      quote!(capacity_builder::macro_support::Wrap(#binding).__append(builder);)
+    }
+    Piece::Arg {
+      index,
+      spec: Some(spec),
+    } => {
+      let binding = &bindings[*index];
+      let format = LitStr::new(&format!("{{:{}}}", spec), format.span());
+      quote! {
+        capacity_builder::macro_support::Wrap(
+          &capacity_builder::macro_support::FmtFn(|f| write!(f, #format, #binding))
+        ).__append(builder);
+      }
+    }
+  });
+
+  Ok(quote! {
+    {
+      #[allow(unused_imports)]
+      use capacity_builder::macro_support::ViaAppendable as _;
+      #[allow(unused_imports)]
+      use capacity_builder::macro_support::ViaDisplay as _;
+      capacity_builder::StringBuilder::<String>::build(|builder| {
+        #(let #bindings = #exprs;)*
+        #(#appends)*
+      })
+      .unwrap()
+    }
+  })
+}
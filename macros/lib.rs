@@ -1,37 +1,62 @@
 use proc_macro::TokenStream;
-use quote::quote;
 use syn::parse_macro_input;
 use syn::DeriveInput;
 
-#[proc_macro_derive(CapacityDisplay)]
+mod bytes_appendable;
+mod capacity_display;
+mod capacity_format;
+mod precompute;
+mod string_appendable;
+
+#[proc_macro_derive(CapacityDisplay, attributes(capacity_display))]
 pub fn capacity_display_derive(input: TokenStream) -> TokenStream {
   let input = parse_macro_input!(input as DeriveInput);
-  let name = &input.ident;
+  capacity_display::derive(input)
+    .unwrap_or_else(|err| err.to_compile_error())
+    .into()
+}
 
-  let expanded = quote! {
-    impl #name {
-      pub fn to_string(&self) -> String {
-        capacity_builder::StringBuilder::<String>::build(|builder| {
-          builder.append(self)
-        }).unwrap()
-      }
+#[proc_macro_derive(StringAppendable, attributes(append))]
+pub fn string_appendable_derive(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  string_appendable::derive(input)
+    .unwrap_or_else(|err| err.to_compile_error())
+    .into()
+}
 
-      pub fn to_custom_string<TString: capacity_builder::StringType>(&self) -> TString {
-        capacity_builder::StringBuilder::<TString>::build(|builder| {
-          builder.append(self)
-        }).unwrap()
-      }
-    }
+#[proc_macro_derive(BytesAppendable, attributes(bytes))]
+pub fn bytes_appendable_derive(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as DeriveInput);
+  bytes_appendable::derive(input)
+    .unwrap_or_else(|err| err.to_compile_error())
+    .into()
+}
 
-    impl std::fmt::Display for #name {
-      fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        capacity_builder::StringBuilder::<String>::fmt(f, |builder| {
-          builder.append(self)
-        })
-      }
-    }
-  };
+/// Formats a string like `format!`, but with the exact capacity.
+///
+/// Arguments for `{}` are appended like `StringBuilder::append` when they're
+/// appendable and fall back to their `Display` implementation otherwise. Note
+/// that the arguments are evaluated on each pass.
+#[proc_macro]
+pub fn capacity_format(input: TokenStream) -> TokenStream {
+  let input = parse_macro_input!(input as capacity_format::FormatInput);
+  capacity_format::expand(input)
+    .unwrap_or_else(|err| err.to_compile_error())
+    .into()
+}
 
-  // Return the modified implementation
-  TokenStream::from(expanded)
+/// Folds the lengths of the literal appends at the top level of a build
+/// function into one constant added to the capacity, skipping those
+/// appends on the capacity pass.
+///
+/// The function must take the builder as its first parameter and can't
+/// return early. Since the literals are counted up front, the length of the
+/// builder on the capacity pass includes them, so don't use it with code
+/// that depends on the current length (ex. checkpoints or padding).
+#[proc_macro_attribute]
+pub fn precompute(_attr: TokenStream, item: TokenStream) -> TokenStream {
+  let item = parse_macro_input!(item as syn::ItemFn);
+  precompute::expand(item)
+    .unwrap_or_else(|err| err.to_compile_error())
+    .into()
 }
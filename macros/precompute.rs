@@ -0,0 +1,110 @@
+use proc_macro2::TokenStream;
+use proc_macro2::TokenTree;
+use quote::quote;
+use syn::Expr;
+use syn::FnArg;
+use syn::ItemFn;
+use syn::Lit;
+use syn::Pat;
+use syn::ReturnType;
+use syn::Stmt;
+
+pub fn expand(mut item: ItemFn) -> syn::Result<TokenStream> {
+  let builder = match item.sig.inputs.first() {
+    Some(FnArg::Typed(arg)) => match &*arg.pat {
+      Pat::Ident(pat) => pat.ident.clone(),
+      _ => {
+        return Err(syn::Error::new_spanned(
+          &arg.pat,
+          "expected the builder parameter to be an identifier",
+        ))
+      }
+    },
+    _ => {
+      return Err(syn::Error::new_spanned(
+        &item.sig,
+        "expected a function taking the builder as its first parameter",
+      ))
+    }
+  };
+  // the literals are counted up front, so every one of them must be
+  // appended once the function starts
+  if let ReturnType::Type(..) = &item.sig.output {
+    return Err(syn::Error::new_spanned(
+      &item.sig.output,
+      "#[precompute] functions can't return a value",
+    ));
+  }
+  if let Some(span) = find_return(quote!(#item).into_iter()) {
+    return Err(syn::Error::new(
+      span,
+      "#[precompute] functions can't return early",
+    ));
+  }
+
+  let mut size = 0;
+  for stmt in &mut item.block.stmts {
+    let Stmt::Expr(Expr::MethodCall(call), Some(_)) = stmt else {
+      continue;
+    };
+    let is_builder = matches!(
+      &*call.receiver,
+      Expr::Path(path) if path.path.is_ident(&builder)
+    );
+    if !is_builder || call.method != "append" || call.args.len() != 1 {
+      continue;
+    }
+    let Expr::Lit(lit) = &call.args[0] else {
+      continue;
+    };
+    let literal = match &lit.lit {
+      Lit::Str(value) => {
+        size += value.value().len();
+        quote!(capacity_builder::macro_support::Literal::Str(#value))
+      }
+      Lit::Char(value) => {
+        size += value.value().len_utf8();
+        quote!(capacity_builder::macro_support::Literal::Char(#value))
+      }
+      _ => continue,
+    };
+    *stmt = syn::parse_quote! {
+      capacity_builder::macro_support::Precompute::__append_precomputed(
+        #builder,
+        #literal,
+      );
+    };
+  }
+  if size > 0 {
+    item.block.stmts.insert(
+      0,
+      syn::parse_quote! {
+        capacity_builder::macro_support::Precompute::__add_precomputed(
+          #builder,
+          #size,
+        );
+      },
+    );
+  }
+  Ok(quote!(#item))
+}
+
+/// Finds a `return`, including in closures to keep it simple.
+fn find_return(
+  tokens: impl Iterator<Item = TokenTree>,
+) -> Option<proc_macro2::Span> {
+  for token in tokens {
+    match token {
+      TokenTree::Ident(ident) if ident == "return" => {
+        return Some(ident.span())
+      }
+      TokenTree::Group(group) => {
+        if let Some(span) = find_return(group.stream().into_iter()) {
+          return Some(span);
+        }
+      }
+      _ => {}
+    }
+  }
+  None
+}
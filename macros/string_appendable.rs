@@ -0,0 +1,367 @@
+use proc_macro2::TokenStream;
+use quote::quote;
+use quote::ToTokens;
+use syn::spanned::Spanned;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Fields;
+use syn::LitStr;
+use syn::Member;
+
+pub fn derive(input: DeriveInput) -> syn::Result<TokenStream> {
+  let name = &input.ident;
+  let options = ContainerOptions::parse(&input.attrs)?;
+  let body = match &input.data {
+    Data::Struct(data) => appends_for_fields(
+      &data.fields,
+      options.format.as_ref(),
+      |member, ty| {
+        if is_reference(ty) {
+          quote!(&*self.#member)
+        } else {
+          quote!(&self.#member)
+        }
+      },
+    )?,
+    Data::Enum(data) => {
+      if let Some(format) = &options.format {
+        return Err(syn::Error::new(
+          format.span(),
+          "specify the format on each variant instead of the enum",
+        ));
+      }
+      let mut arms = Vec::with_capacity(data.variants.len());
+      for variant in &data.variants {
+        arms.push(variant_arm(name, variant)?);
+      }
+      quote! {
+        match self {
+          #(#arms)*
+        }
+      }
+    }
+    Data::Union(_) => {
+      return Err(syn::Error::new(
+        input.span(),
+        "StringAppendable can't be derived for unions",
+      ))
+    }
+  };
+
+  let mut generics = input.generics.clone();
+  generics
+    .params
+    .insert(0, syn::parse_quote!('capacity_builder_a));
+  {
+    let where_clause = generics.make_where_clause();
+    for param in input.generics.type_params() {
+      let ident = &param.ident;
+      where_clause
+        .predicates
+        .push(syn::parse_quote!(#ident: 'capacity_builder_a));
+    }
+    // require generic fields to be appendable
+    let type_params = input
+      .generics
+      .type_params()
+      .map(|param| param.ident.to_string())
+      .collect::<Vec<_>>();
+    for ty in field_types(&input.data) {
+      if !is_reference(ty) && mentions_any(ty.to_token_stream(), &type_params) {
+        where_clause.predicates.push(syn::parse_quote!(
+          &'capacity_builder_a #ty:
+            capacity_builder::StringAppendable<'capacity_builder_a>
+        ));
+      }
+    }
+  }
+  let (impl_generics, _, where_clause) = generics.split_for_impl();
+  let (_, ty_generics, _) = input.generics.split_for_impl();
+
+  Ok(quote! {
+    impl #impl_generics capacity_builder::StringAppendable<'capacity_builder_a>
+      for &'capacity_builder_a #name #ty_generics #where_clause
+    {
+      fn append_to_builder<TString: capacity_builder::StringType>(
+        self,
+        builder: &mut capacity_builder::StringBuilder<'capacity_builder_a, TString>,
+      ) {
+        #body
+      }
+    }
+  })
+}
+
+fn variant_arm(
+  enum_name: &syn::Ident,
+  variant: &syn::Variant,
+) -> syn::Result<TokenStream> {
+  let options = ContainerOptions::parse(&variant.attrs)?;
+  let ident = &variant.ident;
+  let binding = |member: &Member| match member {
+    Member::Named(ident) => ident.clone(),
+    Member::Unnamed(index) => quote::format_ident!("__field{}", index.index),
+  };
+  let members = variant
+    .fields
+    .iter()
+    .enumerate()
+    .map(|(i, field)| match &field.ident {
+      Some(ident) => Member::Named(ident.clone()),
+      None => Member::Unnamed(i.into()),
+    })
+    .collect::<Vec<_>>();
+  let bindings = members.iter().map(binding).collect::<Vec<_>>();
+  let pattern = match &variant.fields {
+    Fields::Named(_) => quote!(#enum_name::#ident { #(#bindings),* }),
+    Fields::Unnamed(_) => quote!(#enum_name::#ident(#(#bindings),*)),
+    Fields::Unit => quote!(#enum_name::#ident),
+  };
+  let body = if variant.fields.is_empty() && options.format.is_none() {
+    let name = ident.to_string();
+    quote!(builder.append(#name);)
+  } else {
+    appends_for_fields(
+      &variant.fields,
+      options.format.as_ref(),
+      |member, ty| {
+        let binding = binding(member);
+        if is_reference(ty) {
+          quote!(*#binding)
+        } else {
+          quote!(#binding)
+        }
+      },
+    )?
+  };
+  Ok(quote! {
+    #[allow(unused_variables)]
+    #pattern => {
+      #body
+    }
+  })
+}
+
+/// Generates the appends for the provided fields either in declaration
+/// order or using the format string.
+pub fn appends_for_fields(
+  fields: &Fields,
+  format: Option<&LitStr>,
+  field_expr: impl Fn(&Member, &syn::Type) -> TokenStream,
+) -> syn::Result<TokenStream> {
+  let members = fields
+    .iter()
+    .enumerate()
+    .map(|(i, field)| match &field.ident {
+      Some(ident) => Member::Named(ident.clone()),
+      None => Member::Unnamed(i.into()),
+    })
+    .collect::<Vec<_>>();
+  let mut appends = Vec::new();
+  match format {
+    Some(format) => {
+      for segment in parse_format(format)? {
+        match segment {
+          Segment::Literal(text) => {
+            appends.push(quote!(builder.append(#text);));
+          }
+          Segment::Field(member) => {
+            let Some(index) = members.iter().position(|m| *m == member) else {
+              return Err(syn::Error::new(
+                format.span(),
+                format!(
+                  "unknown field `{}` in format string",
+                  member_display(&member)
+                ),
+              ));
+            };
+            let field = fields.iter().nth(index).unwrap();
+            let expr = field_expr(&member, &field.ty);
+            appends.push(quote!(builder.append(#expr);));
+          }
+        }
+      }
+    }
+    None => {
+      for (field, member) in fields.iter().zip(&members) {
+        let options = FieldOptions::parse(&field.attrs)?;
+        if options.skip {
+          continue;
+        }
+        if let Some(prefix) = &options.prefix {
+          appends.push(quote!(builder.append(#prefix);));
+        }
+        let expr = field_expr(member, &field.ty);
+        appends.push(quote!(builder.append(#expr);));
+        if let Some(suffix) = &options.suffix {
+          appends.push(quote!(builder.append(#suffix);));
+        }
+      }
+    }
+  }
+  Ok(quote!(#(#appends)*))
+}
+
+pub fn field_types(data: &Data) -> Vec<&syn::Type> {
+  match data {
+    Data::Struct(data) => data.fields.iter().map(|f| &f.ty).collect(),
+    Data::Enum(data) => data
+      .variants
+      .iter()
+      .flat_map(|v| v.fields.iter().map(|f| &f.ty))
+      .collect(),
+    Data::Union(_) => Vec::new(),
+  }
+}
+
+pub fn mentions_any(tokens: TokenStream, idents: &[String]) -> bool {
+  tokens.into_iter().any(|token| match token {
+    proc_macro2::TokenTree::Ident(ident) => idents.iter().any(|i| ident == i),
+    proc_macro2::TokenTree::Group(group) => {
+      mentions_any(group.stream(), idents)
+    }
+    _ => false,
+  })
+}
+
+/// Reference fields are appended by reborrowing the reference
+/// itself rather than taking a reference to the reference.
+pub fn is_reference(ty: &syn::Type) -> bool {
+  match ty {
+    syn::Type::Reference(_) => true,
+    syn::Type::Group(group) => is_reference(&group.elem),
+    syn::Type::Paren(paren) => is_reference(&paren.elem),
+    _ => false,
+  }
+}
+
+fn member_display(member: &Member) -> String {
+  match member {
+    Member::Named(ident) => ident.to_string(),
+    Member::Unnamed(index) => index.index.to_string(),
+  }
+}
+
+pub struct ContainerOptions {
+  pub format: Option<LitStr>,
+}
+
+impl ContainerOptions {
+  pub fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+    let mut format = None;
+    for attr in attrs.iter().filter(|a| a.path().is_ident("append")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("format") {
+          format = Some(meta.value()?.parse()?);
+          Ok(())
+        } else {
+          Err(meta.error("unknown append attribute"))
+        }
+      })?;
+    }
+    Ok(Self { format })
+  }
+}
+
+struct FieldOptions {
+  prefix: Option<LitStr>,
+  suffix: Option<LitStr>,
+  skip: bool,
+}
+
+impl FieldOptions {
+  fn parse(attrs: &[syn::Attribute]) -> syn::Result<Self> {
+    let mut options = FieldOptions {
+      prefix: None,
+      suffix: None,
+      skip: false,
+    };
+    for attr in attrs.iter().filter(|a| a.path().is_ident("append")) {
+      attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("prefix") {
+          options.prefix = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("suffix") {
+          options.suffix = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("skip") {
+          options.skip = true;
+        } else {
+          return Err(meta.error("unknown append attribute"));
+        }
+        Ok(())
+      })?;
+    }
+    Ok(options)
+  }
+}
+
+enum Segment {
+  Literal(String),
+  Field(Member),
+}
+
+/// Parses a format string like `"{major}.{minor}"` where `{{` and `}}`
+/// are escaped braces.
+fn parse_format(format: &LitStr) -> syn::Result<Vec<Segment>> {
+  let value = format.value();
+  let mut segments = Vec::new();
+  let mut literal = String::new();
+  let mut chars = value.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '{' if chars.peek() == Some(&'{') => {
+        chars.next();
+        literal.push('{');
+      }
+      '}' if chars.peek() == Some(&'}') => {
+        chars.next();
+        literal.push('}');
+      }
+      '{' => {
+        let mut name = String::new();
+        loop {
+          match chars.next() {
+            Some('}') => break,
+            Some(c) => name.push(c),
+            None => {
+              return Err(syn::Error::new(
+                format.span(),
+                "unclosed `{` in format string",
+              ))
+            }
+          }
+        }
+        let name = name.trim();
+        let member = match name.parse::<u32>() {
+          Ok(index) => Member::Unnamed(syn::Index {
+            index,
+            span: format.span(),
+          }),
+          Err(_) => match syn::parse_str::<syn::Ident>(name) {
+            Ok(ident) => Member::Named(ident),
+            Err(_) => {
+              return Err(syn::Error::new(
+                format.span(),
+                format!("invalid field `{}` in format string", name),
+              ))
+            }
+          },
+        };
+        if !literal.is_empty() {
+          segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+        segments.push(Segment::Field(member));
+      }
+      '}' => {
+        return Err(syn::Error::new(
+          format.span(),
+          "unmatched `}` in format string",
+        ))
+      }
+      c => literal.push(c),
+    }
+  }
+  if !literal.is_empty() {
+    segments.push(Segment::Literal(literal));
+  }
+  Ok(segments)
+}
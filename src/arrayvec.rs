@@ -0,0 +1,7 @@
+use arrayvec::ArrayString;
+use arrayvec::ArrayVec;
+
+crate::fixed_capacity::impl_fixed_capacity_outputs! {
+  string: ArrayString { push: try_push, push_str: try_push_str },
+  vec: ArrayVec { push: try_push, extend_from_slice: try_extend_from_slice },
+}
@@ -0,0 +1,40 @@
+use std::sync::atomic::Ordering;
+
+use crate::StringAppendable;
+use crate::StringBuilder;
+use crate::StringType;
+
+macro_rules! impl_appendable_for_atomic {
+  ($($t:ident),*) => {
+    $(
+      /// Loads the value once with `Ordering::Relaxed`, keeping the
+      /// formatted text between passes so a concurrent update can't change
+      /// the capacity.
+      impl<'a> StringAppendable<'a> for &'a std::sync::atomic::$t {
+        #[inline(always)]
+        fn append_to_builder<TString: StringType>(
+          self,
+          builder: &mut StringBuilder<'a, TString>,
+        ) {
+          builder.append_cached(|text| {
+            let mut buffer = crate::int_buffer::Buffer::new();
+            text.push_str(buffer.format(self.load(Ordering::Relaxed)));
+          });
+        }
+      }
+    )*
+  };
+}
+
+impl_appendable_for_atomic!(
+  AtomicI8,
+  AtomicI16,
+  AtomicI32,
+  AtomicIsize,
+  AtomicU8,
+  AtomicU16,
+  AtomicU32,
+  AtomicUsize
+);
+#[cfg(target_has_atomic = "64")]
+impl_appendable_for_atomic!(AtomicI64, AtomicU64);
@@ -0,0 +1,475 @@
+//! A compact binary serializer that writes to a `BytesBuilder`.
+//!
+//! The layout is the same as `bincode`'s default: little-endian fixed-width
+//! integers, `u64` lengths, `u32` variant indexes, and a `u8` tag for
+//! options. The value is serialized on both passes, so serializing to a
+//! `Vec<u8>` allocates once with the exact capacity.
+
+use std::cell::Cell;
+
+use serde::ser;
+use serde::Serialize;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+use crate::Error;
+
+/// Serializes the value to bytes.
+pub fn to_vec<T: Serialize + ?Sized>(value: &T) -> Result<Vec<u8>, Error> {
+  let error = Cell::new(None);
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    if let Err(err) = value.serialize(&mut Serializer::new(builder)) {
+      error.set(Some(err));
+    }
+  })?;
+  match error.into_inner() {
+    Some(err) => Err(err),
+    None => Ok(bytes),
+  }
+}
+
+/// Serializes values in a compact binary layout to a builder.
+pub struct Serializer<'b, 'a, TBytes: BytesType> {
+  builder: &'b mut BytesBuilder<'a, TBytes>,
+}
+
+impl<'b, 'a, TBytes: BytesType> Serializer<'b, 'a, TBytes> {
+  pub fn new(builder: &'b mut BytesBuilder<'a, TBytes>) -> Self {
+    Self { builder }
+  }
+
+  /// Writes bytes that only live for the duration of the call.
+  #[inline(always)]
+  fn write(&mut self, bytes: &[u8]) {
+    match &mut self.builder.bytes {
+      Some(b) => b.extend_from_slice(bytes),
+      None => self.builder.capacity += bytes.len(),
+    }
+  }
+
+  #[inline(always)]
+  fn write_len(&mut self, len: usize) {
+    self.builder.append_le(len as u64);
+  }
+}
+
+impl<'c, 'b, 'a, TBytes: BytesType> ser::Serializer
+  for &'c mut Serializer<'b, 'a, TBytes>
+{
+  type Ok = ();
+  type Error = Error;
+  type SerializeSeq = Compound<'c, 'b, 'a, TBytes>;
+  type SerializeTuple = Compound<'c, 'b, 'a, TBytes>;
+  type SerializeTupleStruct = Compound<'c, 'b, 'a, TBytes>;
+  type SerializeTupleVariant = Compound<'c, 'b, 'a, TBytes>;
+  type SerializeMap = Compound<'c, 'b, 'a, TBytes>;
+  type SerializeStruct = Compound<'c, 'b, 'a, TBytes>;
+  type SerializeStructVariant = Compound<'c, 'b, 'a, TBytes>;
+
+  fn serialize_bool(self, v: bool) -> Result<(), Error> {
+    self.builder.append(v as u8);
+    Ok(())
+  }
+
+  fn serialize_i8(self, v: i8) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_i16(self, v: i16) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_i32(self, v: i32) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_i64(self, v: i64) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_i128(self, v: i128) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_u8(self, v: u8) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_u16(self, v: u16) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_u32(self, v: u32) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_u64(self, v: u64) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_u128(self, v: u128) -> Result<(), Error> {
+    self.builder.append_le(v);
+    Ok(())
+  }
+
+  fn serialize_f32(self, v: f32) -> Result<(), Error> {
+    self.write(&v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_f64(self, v: f64) -> Result<(), Error> {
+    self.write(&v.to_le_bytes());
+    Ok(())
+  }
+
+  fn serialize_char(self, v: char) -> Result<(), Error> {
+    self.write(v.encode_utf8(&mut [0; 4]).as_bytes());
+    Ok(())
+  }
+
+  fn serialize_str(self, v: &str) -> Result<(), Error> {
+    self.serialize_bytes(v.as_bytes())
+  }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+    self.write_len(v.len());
+    self.write(v);
+    Ok(())
+  }
+
+  fn serialize_none(self) -> Result<(), Error> {
+    self.builder.append(0u8);
+    Ok(())
+  }
+
+  fn serialize_some<T: Serialize + ?Sized>(
+    self,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.builder.append(1u8);
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<(), Error> {
+    Ok(())
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+    Ok(())
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+  ) -> Result<(), Error> {
+    self.builder.append_le(variant_index);
+    Ok(())
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.builder.append_le(variant_index);
+    value.serialize(self)
+  }
+
+  fn serialize_seq(
+    self,
+    len: Option<usize>,
+  ) -> Result<Self::SerializeSeq, Error> {
+    let len = len.ok_or_else(length_required)?;
+    self.write_len(len);
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleStruct, Error> {
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleVariant, Error> {
+    self.builder.append_le(variant_index);
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_map(
+    self,
+    len: Option<usize>,
+  ) -> Result<Self::SerializeMap, Error> {
+    let len = len.ok_or_else(length_required)?;
+    self.write_len(len);
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_struct(
+    self,
+    _name: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeStruct, Error> {
+    Ok(Compound { ser: self })
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeStructVariant, Error> {
+    self.builder.append_le(variant_index);
+    Ok(Compound { ser: self })
+  }
+}
+
+fn length_required() -> Error {
+  Error::Serialize("sequences and maps must have a known length".to_string())
+}
+
+/// Serializes the elements of a sequence or the entries of a map.
+pub struct Compound<'c, 'b, 'a, TBytes: BytesType> {
+  ser: &'c mut Serializer<'b, 'a, TBytes>,
+}
+
+impl<TBytes: BytesType> ser::SerializeSeq for Compound<'_, '_, '_, TBytes> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    Ok(())
+  }
+}
+
+impl<TBytes: BytesType> ser::SerializeTuple for Compound<'_, '_, '_, TBytes> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    Ok(())
+  }
+}
+
+impl<TBytes: BytesType> ser::SerializeTupleStruct
+  for Compound<'_, '_, '_, TBytes>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    Ok(())
+  }
+}
+
+impl<TBytes: BytesType> ser::SerializeTupleVariant
+  for Compound<'_, '_, '_, TBytes>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    Ok(())
+  }
+}
+
+impl<TBytes: BytesType> ser::SerializeMap for Compound<'_, '_, '_, TBytes> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_key<T: Serialize + ?Sized>(
+    &mut self,
+    key: &T,
+  ) -> Result<(), Error> {
+    key.serialize(&mut *self.ser)
+  }
+
+  fn serialize_value<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    Ok(())
+  }
+}
+
+impl<TBytes: BytesType> ser::SerializeStruct for Compound<'_, '_, '_, TBytes> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    _key: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    Ok(())
+  }
+}
+
+impl<TBytes: BytesType> ser::SerializeStructVariant
+  for Compound<'_, '_, '_, TBytes>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    _key: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::BTreeMap;
+
+  use serde::Serialize;
+
+  #[derive(Serialize)]
+  enum Shape {
+    Empty,
+    Circle(f64),
+    Point(i32, i32),
+    Rect { width: u32, height: u32 },
+  }
+
+  #[derive(Serialize)]
+  struct Drawing<'a> {
+    name: &'a str,
+    shapes: Vec<Shape>,
+    tags: BTreeMap<u32, Option<char>>,
+    #[serde(with = "serde_bytes_like")]
+    bytes: &'a [u8],
+    point: (u8, i128),
+    unit: (),
+  }
+
+  mod serde_bytes_like {
+    pub fn serialize<S: serde::Serializer>(
+      bytes: &[u8],
+      serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+      serializer.serialize_bytes(bytes)
+    }
+  }
+
+  #[test]
+  fn matches_bincode() {
+    let drawing = Drawing {
+      name: "Hello 😀",
+      shapes: vec![
+        Shape::Empty,
+        Shape::Circle(1.5),
+        Shape::Point(-1, 2),
+        Shape::Rect {
+          width: 3,
+          height: 4,
+        },
+      ],
+      tags: BTreeMap::from([(1, Some('é')), (2, None)]),
+      bytes: &[1, 2, 3],
+      point: (5, -6),
+      unit: (),
+    };
+    let bytes = super::to_vec(&drawing).unwrap();
+    assert_eq!(bytes, bincode::serialize(&drawing).unwrap());
+    assert_eq!(bytes.capacity(), bytes.len());
+  }
+
+  #[test]
+  fn errors_for_unknown_lengths() {
+    struct Unknown;
+
+    impl Serialize for Unknown {
+      fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+      ) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeSeq;
+        let seq = serializer.serialize_seq(None)?;
+        seq.end()
+      }
+    }
+
+    let err = super::to_vec(&Unknown).unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "sequences and maps must have a known length"
+    );
+  }
+}
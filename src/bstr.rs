@@ -0,0 +1,101 @@
+use bstr::BStr;
+use bstr::BString;
+use bstr::ByteSlice;
+
+use crate::BytesAppendable;
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::StringAppendable;
+use crate::StringBuilder;
+use crate::StringType;
+
+impl BytesType for BString {
+  type MutType = Vec<u8>;
+
+  #[inline(always)]
+  fn with_capacity(
+    size: usize,
+  ) -> Result<Self::MutType, std::collections::TryReserveError> {
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(size)?;
+    Ok(bytes)
+  }
+
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    BString::from(inner)
+  }
+}
+
+impl<'a> BytesAppendable<'a> for &'a BStr {
+  #[inline(always)]
+  fn append_to_builder<TBytes: BytesType>(
+    self,
+    builder: &mut BytesBuilder<'a, TBytes>,
+  ) {
+    builder.append(self.as_bytes());
+  }
+}
+
+impl<'a> BytesAppendable<'a> for &'a BString {
+  #[inline(always)]
+  fn append_to_builder<TBytes: BytesType>(
+    self,
+    builder: &mut BytesBuilder<'a, TBytes>,
+  ) {
+    builder.append(self.as_bytes());
+  }
+}
+
+/// Appends the same text as the `Display` implementation, which replaces
+/// invalid UTF-8 with the replacement character.
+impl<'a> StringAppendable<'a> for &'a BStr {
+  fn append_to_builder<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    for chunk in self.utf8_chunks() {
+      builder.append(chunk.valid());
+      if !chunk.invalid().is_empty() {
+        builder.append('\u{FFFD}');
+      }
+    }
+  }
+}
+
+impl<'a> StringAppendable<'a> for &'a BString {
+  #[inline(always)]
+  fn append_to_builder<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    builder.append(self.as_bstr());
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use bstr::BStr;
+  use bstr::BString;
+
+  use crate::BytesBuilder;
+  use crate::StringBuilder;
+
+  #[test]
+  fn builds() {
+    let name = BString::from(&b"caf\xFF\xFEe"[..]);
+    let text = StringBuilder::<String>::build(|builder| {
+      builder.append("Hello ");
+      builder.append(&name);
+    })
+    .unwrap();
+    assert_eq!(text, format!("Hello {}", name));
+
+    let bytes = BytesBuilder::<BString>::build(|builder| {
+      builder.append(BStr::new("Hello "));
+      builder.append(&name);
+    })
+    .unwrap();
+    assert_eq!(bytes, BString::from(&b"Hello caf\xFF\xFEe"[..]));
+  }
+}
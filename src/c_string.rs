@@ -0,0 +1,37 @@
+use std::collections::TryReserveError;
+use std::ffi::CString;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::Error;
+
+impl BytesType for CString {
+  type MutType = Vec<u8>;
+
+  #[inline(always)]
+  fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+    let mut bytes = Vec::new();
+    // reserve room for the trailing nul
+    bytes.try_reserve_exact(size.saturating_add(1))?;
+    Ok(bytes)
+  }
+
+  /// Panics when the bytes contain a nul. Use
+  /// `BytesBuilder::build_c_string` to get an error instead.
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    CString::new(inner).expect("built bytes contained an interior nul")
+  }
+}
+
+impl<'a> BytesBuilder<'a, CString> {
+  /// Builds a `CString`, erroring when the bytes contain a nul.
+  ///
+  /// The trailing nul is appended automatically.
+  pub fn build_c_string(
+    build: impl Fn(&mut BytesBuilder<'a, CString>),
+  ) -> Result<CString, Error> {
+    let result = Self::build_mut(build)?;
+    CString::new(result.bytes).map_err(Error::InteriorNul)
+  }
+}
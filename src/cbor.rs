@@ -0,0 +1,96 @@
+use crate::BytesBuilder;
+use crate::BytesType;
+
+impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
+  /// Appends a CBOR unsigned integer (major type 0).
+  #[inline(always)]
+  pub fn append_cbor_uint(&mut self, value: u64) {
+    self.append_cbor_header(0, value);
+  }
+
+  /// Appends a CBOR integer using major type 0 for non-negative
+  /// values and major type 1 for negative values.
+  #[inline(always)]
+  pub fn append_cbor_int(&mut self, value: i64) {
+    if value < 0 {
+      // -1 - n, which can't overflow when done on the bits
+      self.append_cbor_header(1, !(value as u64));
+    } else {
+      self.append_cbor_header(0, value as u64);
+    }
+  }
+
+  /// Appends a CBOR byte string (major type 2).
+  pub fn append_cbor_bytes(&mut self, value: &'a [u8]) {
+    self.append_cbor_header(2, value.len() as u64);
+    self.append(value);
+  }
+
+  /// Appends a CBOR text string (major type 3).
+  pub fn append_cbor_text(&mut self, value: &'a str) {
+    self.append_cbor_header(3, value.len() as u64);
+    self.append(value);
+  }
+
+  /// Appends a CBOR array header (major type 4) for the number
+  /// of items that follow.
+  #[inline(always)]
+  pub fn append_cbor_array_header(&mut self, len: usize) {
+    self.append_cbor_header(4, len as u64);
+  }
+
+  /// Appends a CBOR map header (major type 5) for the number
+  /// of key/value pairs that follow.
+  #[inline(always)]
+  pub fn append_cbor_map_header(&mut self, len: usize) {
+    self.append_cbor_header(5, len as u64);
+  }
+
+  /// Appends a CBOR tag (major type 6) for the item that follows.
+  #[inline(always)]
+  pub fn append_cbor_tag(&mut self, tag: u64) {
+    self.append_cbor_header(6, tag);
+  }
+
+  /// Appends a CBOR boolean.
+  #[inline(always)]
+  pub fn append_cbor_bool(&mut self, value: bool) {
+    self.append(if value { 0xf5u8 } else { 0xf4u8 });
+  }
+
+  /// Appends a CBOR null.
+  #[inline(always)]
+  pub fn append_cbor_null(&mut self) {
+    self.append(0xf6u8);
+  }
+
+  /// Appends a CBOR double precision float.
+  #[inline(always)]
+  pub fn append_cbor_f64(&mut self, value: f64) {
+    self.append(0xfbu8);
+    self.append(value.to_be_bytes());
+  }
+
+  fn append_cbor_header(&mut self, major_type: u8, value: u64) {
+    let major_type = major_type << 5;
+    match value {
+      0..=23 => self.append(major_type | value as u8),
+      24..=0xff => {
+        self.append(major_type | 24);
+        self.append(value as u8);
+      }
+      0x100..=0xffff => {
+        self.append(major_type | 25);
+        self.append_be(value as u16);
+      }
+      0x1_0000..=0xffff_ffff => {
+        self.append(major_type | 26);
+        self.append_be(value as u32);
+      }
+      _ => {
+        self.append(major_type | 27);
+        self.append_be(value);
+      }
+    }
+  }
+}
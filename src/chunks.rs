@@ -0,0 +1,138 @@
+use std::collections::TryReserveError;
+
+use crate::BytesType;
+use crate::BytesTypeMut;
+
+/// Bytes split into chunks of at most `N` bytes where each
+/// chunk is allocated with its exact size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Chunked<const N: usize>(Vec<Vec<u8>>);
+
+impl<const N: usize> Chunked<N> {
+  pub fn chunks(&self) -> &[Vec<u8>] {
+    &self.0
+  }
+
+  pub fn into_chunks(self) -> Vec<Vec<u8>> {
+    self.0
+  }
+}
+
+impl<const N: usize> BytesType for Chunked<N> {
+  type MutType = ChunkedMut<N>;
+
+  fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+    const { assert!(N > 0, "chunk size must be greater than zero") };
+    let mut chunks = Vec::new();
+    let chunk_count = size.div_ceil(N);
+    chunks.try_reserve_exact(chunk_count)?;
+    for i in 0..chunk_count {
+      let mut chunk = Vec::new();
+      chunk.try_reserve_exact(std::cmp::min(N, size - i * N))?;
+      chunks.push(chunk);
+    }
+    Ok(ChunkedMut { chunks, len: 0 })
+  }
+
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    Chunked(inner.chunks)
+  }
+}
+
+pub struct ChunkedMut<const N: usize> {
+  chunks: Vec<Vec<u8>>,
+  len: usize,
+}
+
+impl<const N: usize> ChunkedMut<N> {
+  #[inline(always)]
+  fn current_chunk(&mut self) -> &mut Vec<u8> {
+    let index = self.len / N;
+    if index == self.chunks.len() {
+      // only happens when the capacity was incorrect
+      self.chunks.push(Vec::with_capacity(N));
+    }
+    &mut self.chunks[index]
+  }
+}
+
+impl<const N: usize> BytesTypeMut for ChunkedMut<N> {
+  #[inline(always)]
+  fn push(&mut self, c: u8) {
+    self.current_chunk().push(c);
+    self.len += 1;
+  }
+
+  fn extend_from_slice(&mut self, mut bytes: &[u8]) {
+    while !bytes.is_empty() {
+      let remaining = N - self.len % N;
+      let (current, rest) =
+        bytes.split_at(std::cmp::min(remaining, bytes.len()));
+      self.current_chunk().extend_from_slice(current);
+      self.len += current.len();
+      bytes = rest;
+    }
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    self.len
+  }
+
+  fn slices_from(&self, start: usize, mut f: impl FnMut(&[u8])) {
+    let first_index = start / N;
+    for (i, chunk) in self.chunks.iter().enumerate().skip(first_index) {
+      if i == first_index {
+        f(&chunk[start % N..]);
+      } else {
+        f(chunk);
+      }
+    }
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use super::Chunked;
+  use crate::crc::Crc32Kind;
+  use crate::BytesBuilder;
+
+  #[test]
+  fn exact_chunk_capacities() {
+    let chunks = BytesBuilder::<Chunked<3>>::build(|builder| {
+      builder.append("ab");
+      builder.append(b'c');
+      builder.append("defg");
+    })
+    .unwrap()
+    .into_chunks();
+    assert_eq!(
+      chunks,
+      vec![b"abc".to_vec(), b"def".to_vec(), b"g".to_vec()]
+    );
+    for chunk in &chunks {
+      assert_eq!(chunk.capacity(), chunk.len());
+    }
+  }
+
+  #[test]
+  fn empty() {
+    let chunks = BytesBuilder::<Chunked<3>>::build(|_| {}).unwrap();
+    assert!(chunks.chunks().is_empty());
+  }
+
+  #[test]
+  fn crc_across_chunks() {
+    let chunks = BytesBuilder::<Chunked<2>>::build(|builder| {
+      builder.append("a");
+      builder.append_crc32_be_of(Crc32Kind::Ieee, |builder| {
+        builder.append("123456789");
+      });
+    })
+    .unwrap()
+    .into_chunks();
+    let bytes = chunks.concat();
+    assert_eq!(&bytes[10..], &0xCBF4_3926u32.to_be_bytes());
+  }
+}
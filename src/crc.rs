@@ -0,0 +1,113 @@
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+
+const CRC32_TABLE: [u32; 256] = crc32_table(0xEDB8_8320);
+const CRC32C_TABLE: [u32; 256] = crc32_table(0x82F6_3B78);
+
+const fn crc32_table(polynomial: u32) -> [u32; 256] {
+  let mut table = [0; 256];
+  let mut i = 0;
+  while i < 256 {
+    let mut value = i as u32;
+    let mut bit = 0;
+    while bit < 8 {
+      value = if value & 1 == 1 {
+        (value >> 1) ^ polynomial
+      } else {
+        value >> 1
+      };
+      bit += 1;
+    }
+    table[i] = value;
+    i += 1;
+  }
+  table
+}
+
+fn update(table: &[u32; 256], mut crc: u32, bytes: &[u8]) -> u32 {
+  for byte in bytes {
+    crc = table[((crc ^ *byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+  }
+  crc
+}
+
+fn checksum(table: &[u32; 256], bytes: &[u8]) -> u32 {
+  !update(table, !0, bytes)
+}
+
+/// Computes the CRC-32 (IEEE) checksum used by zip, gzip, and png.
+pub fn crc32(bytes: &[u8]) -> u32 {
+  checksum(&CRC32_TABLE, bytes)
+}
+
+/// Computes the CRC-32C (Castagnoli) checksum.
+pub fn crc32c(bytes: &[u8]) -> u32 {
+  checksum(&CRC32C_TABLE, bytes)
+}
+
+/// The checksum algorithm to use for a CRC appended by a builder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Crc32Kind {
+  /// CRC-32 (IEEE) as used by zip, gzip, and png.
+  Ieee,
+  /// CRC-32C (Castagnoli) as used by iSCSI and many storage formats.
+  Castagnoli,
+}
+
+impl Crc32Kind {
+  pub fn checksum(&self, bytes: &[u8]) -> u32 {
+    checksum(self.table(), bytes)
+  }
+
+  fn table(&self) -> &'static [u32; 256] {
+    match self {
+      Crc32Kind::Ieee => &CRC32_TABLE,
+      Crc32Kind::Castagnoli => &CRC32C_TABLE,
+    }
+  }
+}
+
+impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
+  /// Appends the bytes in the provided closure followed by the
+  /// CRC-32 of those bytes in big-endian byte order (ex. png).
+  pub fn append_crc32_be_of(
+    &mut self,
+    kind: Crc32Kind,
+    build: impl FnOnce(&mut BytesBuilder<'a, TBytes>),
+  ) {
+    let crc = self.crc32_of(kind, build);
+    self.append_be(crc);
+  }
+
+  /// Appends the bytes in the provided closure followed by the
+  /// CRC-32 of those bytes in little-endian byte order (ex. zip).
+  pub fn append_crc32_le_of(
+    &mut self,
+    kind: Crc32Kind,
+    build: impl FnOnce(&mut BytesBuilder<'a, TBytes>),
+  ) {
+    let crc = self.crc32_of(kind, build);
+    self.append_le(crc);
+  }
+
+  /// Appends the bytes in the closure and returns the CRC-32 of them,
+  /// which will be zero on the first pass.
+  pub(crate) fn crc32_of(
+    &mut self,
+    kind: Crc32Kind,
+    build: impl FnOnce(&mut BytesBuilder<'a, TBytes>),
+  ) -> u32 {
+    let start = self.len();
+    build(self);
+    match &self.bytes {
+      Some(bytes) => {
+        let table = kind.table();
+        let mut crc = !0;
+        bytes.slices_from(start, |slice| crc = update(table, crc, slice));
+        !crc
+      }
+      None => 0,
+    }
+  }
+}
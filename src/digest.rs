@@ -0,0 +1,84 @@
+use std::collections::TryReserveError;
+
+use digest::Digest;
+use digest::Output;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+use crate::Error;
+
+/// Output type that computes a digest of the bytes while they're written.
+///
+/// See `BytesBuilder::build_with_digest`.
+pub struct DigestBytes<TBytes: BytesType, D: Digest> {
+  pub bytes: TBytes,
+  pub digest: Output<D>,
+}
+
+impl<TBytes: BytesType, D: Digest> BytesType for DigestBytes<TBytes, D> {
+  type MutType = DigestBytesMut<TBytes::MutType, D>;
+
+  const MAX_CAPACITY: usize = TBytes::MAX_CAPACITY;
+
+  #[inline(always)]
+  fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+    Ok(DigestBytesMut {
+      bytes: TBytes::with_capacity(size)?,
+      hasher: D::new(),
+    })
+  }
+
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    DigestBytes {
+      bytes: TBytes::from_mut(inner.bytes),
+      digest: inner.hasher.finalize(),
+    }
+  }
+}
+
+/// Feeds the bytes to the hasher as they're appended to the inner bytes.
+pub struct DigestBytesMut<TBytesMut: BytesTypeMut, D: Digest> {
+  bytes: TBytesMut,
+  hasher: D,
+}
+
+impl<TBytesMut: BytesTypeMut, D: Digest> BytesTypeMut
+  for DigestBytesMut<TBytesMut, D>
+{
+  #[inline(always)]
+  fn push(&mut self, c: u8) {
+    self.bytes.push(c);
+    self.hasher.update([c]);
+  }
+
+  #[inline(always)]
+  fn extend_from_slice(&mut self, bytes: &[u8]) {
+    self.bytes.extend_from_slice(bytes);
+    self.hasher.update(bytes);
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    self.bytes.len()
+  }
+
+  #[inline(always)]
+  fn slices_from(&self, start: usize, f: impl FnMut(&[u8])) {
+    self.bytes.slices_from(start, f)
+  }
+}
+
+impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
+  /// Builds the bytes and computes a digest of them.
+  ///
+  /// The bytes are fed to the hasher as they're written on the second
+  /// pass, so the output isn't read again afterwards.
+  pub fn build_with_digest<D: Digest + 'a>(
+    build: impl Fn(&mut BytesBuilder<'a, DigestBytes<TBytes, D>>),
+  ) -> Result<(TBytes, Output<D>), Error> {
+    let output = BytesBuilder::<DigestBytes<TBytes, D>>::build(build)?;
+    Ok((output.bytes, output.digest))
+  }
+}
@@ -0,0 +1,144 @@
+use std::time::Duration;
+use std::time::SystemTime;
+
+use crate::StringAppendableValue;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+/// The smallest unit shown by `StringBuilder::append_duration_human`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DurationUnit {
+  Hours,
+  Minutes,
+  Seconds,
+  Milliseconds,
+}
+
+impl DurationUnit {
+  const ALL: [DurationUnit; 4] = [
+    DurationUnit::Hours,
+    DurationUnit::Minutes,
+    DurationUnit::Seconds,
+    DurationUnit::Milliseconds,
+  ];
+
+  fn millis(self) -> u128 {
+    match self {
+      DurationUnit::Hours => 3_600_000,
+      DurationUnit::Minutes => 60_000,
+      DurationUnit::Seconds => 1_000,
+      DurationUnit::Milliseconds => 1,
+    }
+  }
+
+  fn suffix(self) -> &'static str {
+    match self {
+      DurationUnit::Hours => "h",
+      DurationUnit::Minutes => "m",
+      DurationUnit::Seconds => "s",
+      DurationUnit::Milliseconds => "ms",
+    }
+  }
+}
+
+impl<TString: StringType> StringBuilder<'_, TString> {
+  /// Appends a duration like `2h 3m 5s` or `450ms`, skipping units that
+  /// are zero and truncating below the provided granularity.
+  ///
+  /// A duration shorter than the granularity is appended as zero of that
+  /// unit (ex. `0s`).
+  pub fn append_duration_human(
+    &mut self,
+    duration: Duration,
+    granularity: DurationUnit,
+  ) {
+    let mut remaining = duration.as_millis();
+    let mut is_empty = true;
+    for unit in DurationUnit::ALL {
+      let value = remaining / unit.millis();
+      remaining %= unit.millis();
+      if value > 0 {
+        if !is_empty {
+          self.append(' ');
+        }
+        self.append(value);
+        self.append(unit.suffix());
+        is_empty = false;
+      }
+      if unit == granularity {
+        break;
+      }
+    }
+    if is_empty {
+      self.append('0');
+      self.append(granularity.suffix());
+    }
+  }
+}
+
+/// The precision of a unix timestamp appended by
+/// `StringBuilder::append_timestamp`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampPrecision {
+  Seconds,
+  Milliseconds,
+  Microseconds,
+  Nanoseconds,
+}
+
+impl TimestampPrecision {
+  fn nanos(self) -> i128 {
+    match self {
+      TimestampPrecision::Seconds => 1_000_000_000,
+      TimestampPrecision::Milliseconds => 1_000_000,
+      TimestampPrecision::Microseconds => 1_000,
+      TimestampPrecision::Nanoseconds => 1,
+    }
+  }
+}
+
+/// Gets the time since the unix epoch in the provided precision, rounding
+/// down. Times before the epoch are negative.
+fn unix_timestamp(time: SystemTime, precision: TimestampPrecision) -> i128 {
+  match time.duration_since(SystemTime::UNIX_EPOCH) {
+    Ok(duration) => duration.as_nanos() as i128 / precision.nanos(),
+    Err(err) => {
+      let nanos = err.duration().as_nanos() as i128;
+      -((nanos + precision.nanos() - 1) / precision.nanos())
+    }
+  }
+}
+
+impl<TString: StringType> StringBuilder<'_, TString> {
+  /// Appends the unix timestamp of the time in the provided precision.
+  #[inline(always)]
+  pub fn append_timestamp(
+    &mut self,
+    time: SystemTime,
+    precision: TimestampPrecision,
+  ) {
+    self.append(unix_timestamp(time, precision));
+  }
+}
+
+/// Appends the unix timestamp in seconds.
+impl StringAppendableValue for SystemTime {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    unix_timestamp(*self, TimestampPrecision::Seconds).byte_len()
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    unix_timestamp(*self, TimestampPrecision::Seconds).push_to(text)
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    unix_timestamp(*self, TimestampPrecision::Seconds).write_to_formatter(fmt)
+  }
+}
@@ -35,6 +35,13 @@ impl StringTypeMut for EcoString {
   fn len(&self) -> usize {
     self.len()
   }
+
+  #[inline(always)]
+  fn truncate(&mut self, len: usize) {
+    while self.len() > len {
+      self.pop();
+    }
+  }
 }
 
 impl<'a> StringAppendable<'a> for &'a EcoString {
@@ -0,0 +1,118 @@
+use std::collections::TryReserveError;
+
+use crate::mismatch::CapacityMismatch;
+
+/// An error building the output.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+  /// Allocating the output failed.
+  Allocation {
+    /// The requested capacity.
+    size: usize,
+    source: TryReserveError,
+  },
+  /// The requested capacity exceeds `isize::MAX` bytes.
+  CapacityOverflow {
+    /// The requested capacity.
+    size: usize,
+  },
+  /// The requested capacity exceeds the maximum of a fixed-capacity output.
+  CapacityExceeded {
+    /// The requested capacity.
+    size: usize,
+    /// The maximum capacity of the output.
+    max: usize,
+  },
+  /// The built length didn't equal the calculated capacity.
+  CapacityMismatch(CapacityMismatch),
+  /// Reading or writing failed.
+  Io(std::io::Error),
+  /// The bytes of a `CString` contained a nul.
+  InteriorNul(std::ffi::NulError),
+  /// Serializing a value failed.
+  #[cfg(feature = "serde")]
+  Serialize(String),
+}
+
+impl Error {
+  /// Creates the error for failing to reserve the capacity.
+  pub(crate) fn reserve(size: usize) -> impl FnOnce(TryReserveError) -> Error {
+    move |source| {
+      if size > isize::MAX as usize {
+        Error::CapacityOverflow { size }
+      } else {
+        Error::Allocation { size, source }
+      }
+    }
+  }
+}
+
+impl std::fmt::Display for Error {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    match self {
+      Error::Allocation { size, source } => {
+        write!(f, "failed allocating {} bytes: {}", size, source)
+      }
+      Error::CapacityOverflow { size } => {
+        write!(f, "capacity of {} bytes overflowed", size)
+      }
+      Error::CapacityExceeded { size, max } => write!(
+        f,
+        "capacity of {} bytes exceeds the maximum of {} bytes",
+        size, max
+      ),
+      Error::CapacityMismatch(mismatch) => write!(
+        f,
+        "built {} bytes, but the capacity was {} bytes",
+        mismatch.len, mismatch.capacity
+      ),
+      Error::Io(err) => err.fmt(f),
+      Error::InteriorNul(err) => err.fmt(f),
+      #[cfg(feature = "serde")]
+      Error::Serialize(msg) => f.write_str(msg),
+    }
+  }
+}
+
+impl std::error::Error for Error {
+  fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+    match self {
+      Error::Allocation { source, .. } => Some(source),
+      Error::Io(err) => Some(err),
+      Error::InteriorNul(err) => Some(err),
+      Error::CapacityOverflow { .. }
+      | Error::CapacityExceeded { .. }
+      | Error::CapacityMismatch(_) => None,
+      #[cfg(feature = "serde")]
+      Error::Serialize(_) => None,
+    }
+  }
+}
+
+impl From<std::io::Error> for Error {
+  fn from(err: std::io::Error) -> Self {
+    Error::Io(err)
+  }
+}
+
+impl From<Error> for std::io::Error {
+  fn from(err: Error) -> Self {
+    match err {
+      Error::Io(err) => err,
+      Error::Allocation { .. }
+      | Error::CapacityOverflow { .. }
+      | Error::CapacityExceeded { .. } => {
+        std::io::Error::new(std::io::ErrorKind::OutOfMemory, err)
+      }
+      Error::InteriorNul(_) => {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+      }
+      #[cfg(feature = "serde")]
+      Error::Serialize(_) => {
+        std::io::Error::new(std::io::ErrorKind::InvalidData, err)
+      }
+      Error::CapacityMismatch(_) => std::io::Error::other(err),
+    }
+  }
+}
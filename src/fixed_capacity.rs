@@ -0,0 +1,115 @@
+/// Implements the string and bytes output traits for the fixed capacity
+/// string and vec of a crate, provided the names of their fallible
+/// appending methods.
+macro_rules! impl_fixed_capacity_outputs {
+  (
+    string: $string:ident { push: $push:ident, push_str: $push_str:ident },
+    vec: $vec:ident { push: $vec_push:ident, extend_from_slice: $extend:ident },
+  ) => {
+    const EXCEEDED: &str =
+      "the built length exceeded the capacity of the fixed capacity output";
+
+    impl<const N: usize> $crate::StringType for $string<N> {
+      type MutType = $string<N>;
+
+      const MAX_CAPACITY: usize = N;
+
+      #[inline(always)]
+      fn with_capacity(
+        _size: usize,
+      ) -> Result<Self::MutType, std::collections::TryReserveError> {
+        Ok($string::new())
+      }
+
+      #[inline(always)]
+      fn from_mut(inner: Self::MutType) -> Self {
+        inner
+      }
+    }
+
+    impl<const N: usize> $crate::StringTypeMut for $string<N> {
+      #[inline(always)]
+      fn push(&mut self, c: char) {
+        $string::$push(self, c).expect(EXCEEDED);
+      }
+
+      #[inline(always)]
+      fn push_str(&mut self, str: &str) {
+        $string::$push_str(self, str).expect(EXCEEDED);
+      }
+
+      #[inline(always)]
+      fn len(&self) -> usize {
+        self.as_str().len()
+      }
+
+      #[inline(always)]
+      fn truncate(&mut self, len: usize) {
+        $string::truncate(self, len);
+      }
+    }
+
+    impl<'a, const N: usize> $crate::StringAppendable<'a> for &'a $string<N> {
+      #[inline(always)]
+      fn append_to_builder<TString: $crate::StringType>(
+        self,
+        builder: &mut $crate::StringBuilder<'a, TString>,
+      ) {
+        builder.append(self.as_str());
+      }
+    }
+
+    impl<const N: usize> $crate::BytesType for $vec<u8, N> {
+      type MutType = $vec<u8, N>;
+
+      const MAX_CAPACITY: usize = N;
+
+      #[inline(always)]
+      fn with_capacity(
+        _size: usize,
+      ) -> Result<Self::MutType, std::collections::TryReserveError> {
+        Ok($vec::new())
+      }
+
+      #[inline(always)]
+      fn from_mut(inner: Self::MutType) -> Self {
+        inner
+      }
+    }
+
+    impl<const N: usize> $crate::BytesTypeMut for $vec<u8, N> {
+      #[inline(always)]
+      fn push(&mut self, c: u8) {
+        $vec::$vec_push(self, c).expect(EXCEEDED);
+      }
+
+      #[inline(always)]
+      fn extend_from_slice(&mut self, bytes: &[u8]) {
+        $vec::$extend(self, bytes).expect(EXCEEDED);
+      }
+
+      #[inline(always)]
+      fn len(&self) -> usize {
+        self.as_slice().len()
+      }
+
+      fn slices_from(&self, start: usize, mut f: impl FnMut(&[u8])) {
+        f(&self.as_slice()[start..]);
+      }
+    }
+
+    impl<const N: usize> $crate::BytesAppendableValue for &$vec<u8, N> {
+      #[inline(always)]
+      fn byte_len(&self) -> usize {
+        self.as_slice().len()
+      }
+
+      #[inline(always)]
+      fn push_to<TBytes: $crate::BytesTypeMut>(&self, bytes: &mut TBytes) {
+        bytes.extend_from_slice(self.as_slice());
+      }
+    }
+  };
+}
+
+pub(crate) use impl_fixed_capacity_outputs;
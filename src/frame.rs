@@ -0,0 +1,194 @@
+use crate::crc::Crc32Kind;
+use crate::BytesBuilder;
+use crate::BytesType;
+
+/// Byte order of a multi-byte integer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Endianness {
+  Big,
+  Little,
+}
+
+/// The integer type used for a length prefix.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LenPrefix {
+  U8,
+  U16(Endianness),
+  U32(Endianness),
+  U64(Endianness),
+}
+
+impl LenPrefix {
+  /// The number of bytes the prefix takes up.
+  pub fn size(&self) -> usize {
+    match self {
+      LenPrefix::U8 => 1,
+      LenPrefix::U16(_) => 2,
+      LenPrefix::U32(_) => 4,
+      LenPrefix::U64(_) => 8,
+    }
+  }
+
+  /// The maximum length the prefix can represent.
+  pub fn max_len(&self) -> u64 {
+    match self {
+      LenPrefix::U8 => u8::MAX as u64,
+      LenPrefix::U16(_) => u16::MAX as u64,
+      LenPrefix::U32(_) => u32::MAX as u64,
+      LenPrefix::U64(_) => u64::MAX,
+    }
+  }
+}
+
+/// The layout of a frame appended by `BytesBuilder::append_framed`.
+///
+/// Defaults to a big-endian `u32` length prefix and a big-endian
+/// CRC-32 (IEEE) of the payload.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameLayout {
+  pub len_prefix: LenPrefix,
+  /// The checksum of the payload to append after it, if any.
+  pub checksum: Option<(Crc32Kind, Endianness)>,
+}
+
+impl Default for FrameLayout {
+  fn default() -> Self {
+    Self {
+      len_prefix: LenPrefix::U32(Endianness::Big),
+      checksum: Some((Crc32Kind::Ieee, Endianness::Big)),
+    }
+  }
+}
+
+impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
+  /// Appends the bytes in the closure prefixed with their length.
+  ///
+  /// Panics if the length exceeds what the prefix can represent.
+  pub fn append_len_prefixed(
+    &mut self,
+    len_prefix: LenPrefix,
+    build: impl FnOnce(&mut BytesBuilder<'a, TBytes>),
+  ) {
+    self.append_section_with_len_prefix(
+      Some(len_prefix.size()),
+      |builder, len| builder.append_len_prefix(len_prefix, len),
+      build,
+    );
+  }
+
+  /// Appends the bytes in the closure wrapped in a frame of a length
+  /// prefix and optionally a trailing checksum of the bytes.
+  ///
+  /// Panics if the length exceeds what the prefix can represent.
+  pub fn append_framed(
+    &mut self,
+    layout: FrameLayout,
+    build: impl FnOnce(&mut BytesBuilder<'a, TBytes>),
+  ) {
+    match layout.checksum {
+      Some((kind, endianness)) => {
+        let mut crc = 0;
+        self.append_len_prefixed(layout.len_prefix, |builder| {
+          crc = builder.crc32_of(kind, build);
+        });
+        match endianness {
+          Endianness::Big => self.append_be(crc),
+          Endianness::Little => self.append_le(crc),
+        }
+      }
+      None => self.append_len_prefixed(layout.len_prefix, build),
+    }
+  }
+
+  /// Appends the UTF-8 bytes of the string prefixed with its byte length.
+  ///
+  /// Panics if the length exceeds what the prefix can represent.
+  #[inline(always)]
+  pub fn append_str_with_len_prefix(
+    &mut self,
+    len_prefix: LenPrefix,
+    value: &'a str,
+  ) {
+    self.append_len_prefix(len_prefix, value.len());
+    self.append(value);
+  }
+
+  /// Appends the string prefixed with its byte length as a `u8`.
+  ///
+  /// Panics if the length exceeds `u8::MAX`.
+  #[inline(always)]
+  pub fn append_str_u8_len(&mut self, value: &'a str) {
+    self.append_str_with_len_prefix(LenPrefix::U8, value);
+  }
+
+  /// Appends the string prefixed with its byte length as a big-endian `u16`.
+  ///
+  /// Panics if the length exceeds `u16::MAX`.
+  #[inline(always)]
+  pub fn append_str_u16_be_len(&mut self, value: &'a str) {
+    self.append_str_with_len_prefix(LenPrefix::U16(Endianness::Big), value);
+  }
+
+  /// Appends the string prefixed with its byte length as a little-endian `u16`.
+  ///
+  /// Panics if the length exceeds `u16::MAX`.
+  #[inline(always)]
+  pub fn append_str_u16_le_len(&mut self, value: &'a str) {
+    self.append_str_with_len_prefix(LenPrefix::U16(Endianness::Little), value);
+  }
+
+  /// Appends the string prefixed with its byte length as a big-endian `u32`.
+  ///
+  /// Panics if the length exceeds `u32::MAX`.
+  #[inline(always)]
+  pub fn append_str_u32_be_len(&mut self, value: &'a str) {
+    self.append_str_with_len_prefix(LenPrefix::U32(Endianness::Big), value);
+  }
+
+  /// Appends the string prefixed with its byte length as a little-endian `u32`.
+  ///
+  /// Panics if the length exceeds `u32::MAX`.
+  #[inline(always)]
+  pub fn append_str_u32_le_len(&mut self, value: &'a str) {
+    self.append_str_with_len_prefix(LenPrefix::U32(Endianness::Little), value);
+  }
+
+  /// Appends the string prefixed with its byte length as a big-endian `u64`.
+  ///
+  /// Panics if the length exceeds `u64::MAX`.
+  #[inline(always)]
+  pub fn append_str_u64_be_len(&mut self, value: &'a str) {
+    self.append_str_with_len_prefix(LenPrefix::U64(Endianness::Big), value);
+  }
+
+  /// Appends the string prefixed with its byte length as a little-endian `u64`.
+  ///
+  /// Panics if the length exceeds `u64::MAX`.
+  #[inline(always)]
+  pub fn append_str_u64_le_len(&mut self, value: &'a str) {
+    self.append_str_with_len_prefix(LenPrefix::U64(Endianness::Little), value);
+  }
+
+  pub(crate) fn append_len_prefix(
+    &mut self,
+    len_prefix: LenPrefix,
+    len: usize,
+  ) {
+    let len = len as u64;
+    assert!(
+      len <= len_prefix.max_len(),
+      "length {} exceeded the maximum of the {:?} length prefix",
+      len,
+      len_prefix
+    );
+    match len_prefix {
+      LenPrefix::U8 => self.append(len as u8),
+      LenPrefix::U16(Endianness::Big) => self.append_be(len as u16),
+      LenPrefix::U16(Endianness::Little) => self.append_le(len as u16),
+      LenPrefix::U32(Endianness::Big) => self.append_be(len as u32),
+      LenPrefix::U32(Endianness::Little) => self.append_le(len as u32),
+      LenPrefix::U64(Endianness::Big) => self.append_be(len),
+      LenPrefix::U64(Endianness::Little) => self.append_le(len),
+    }
+  }
+}
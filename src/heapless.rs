@@ -0,0 +1,7 @@
+use heapless::String;
+use heapless::Vec;
+
+crate::fixed_capacity::impl_fixed_capacity_outputs! {
+  string: String { push: push, push_str: push_str },
+  vec: Vec { push: push, extend_from_slice: extend_from_slice },
+}
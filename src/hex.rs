@@ -0,0 +1,222 @@
+use std::fmt;
+
+use crate::BytesAppendableValue;
+use crate::BytesTypeMut;
+use crate::StringAppendableValue;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+/// Formats bytes as hex, ex. `HexDisplay(&[0xab, 0x01])` is `ab01`.
+///
+/// Appending it to a `StringBuilder` or `BytesBuilder` appends the
+/// lowercase hex text, and it implements `Display` (lowercase),
+/// `LowerHex`, and `UpperHex` for use in format strings.
+#[derive(Debug, Clone, Copy)]
+pub struct HexDisplay<'a>(pub &'a [u8]);
+
+/// The hex text of the bytes.
+struct Hex<'a> {
+  bytes: &'a [u8],
+  upper: bool,
+}
+
+impl Hex<'_> {
+  fn write(&self, mut write: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+    let digits = if self.upper {
+      b"0123456789ABCDEF"
+    } else {
+      b"0123456789abcdef"
+    };
+    let mut buffer = [0; 64];
+    for chunk in self.bytes.chunks(buffer.len() / 2) {
+      for (i, byte) in chunk.iter().enumerate() {
+        buffer[i * 2] = digits[(byte >> 4) as usize];
+        buffer[i * 2 + 1] = digits[(byte & 0xf) as usize];
+      }
+      // SAFETY: only ascii hex digits were written
+      write(unsafe {
+        std::str::from_utf8_unchecked(&buffer[..chunk.len() * 2])
+      })?;
+    }
+    Ok(())
+  }
+}
+
+impl StringAppendableValue for Hex<'_> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.bytes.len() * 2
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let _ = self.write(|value| {
+      text.push_str(value);
+      Ok(())
+    });
+  }
+
+  fn write_to_formatter(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.write(|value| fmt.write_str(value))
+  }
+}
+
+impl HexDisplay<'_> {
+  fn hex(&self, upper: bool) -> Hex<'_> {
+    Hex {
+      bytes: self.0,
+      upper,
+    }
+  }
+}
+
+impl StringAppendableValue for HexDisplay<'_> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.hex(false).byte_len()
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    self.hex(false).push_to(text);
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.hex(false).write_to_formatter(fmt)
+  }
+}
+
+impl BytesAppendableValue for HexDisplay<'_> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.hex(false).byte_len()
+  }
+
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    let _ = self.hex(false).write(|value| {
+      bytes.extend_from_slice(value.as_bytes());
+      Ok(())
+    });
+  }
+}
+
+impl fmt::Display for HexDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::LowerHex::fmt(self, f)
+  }
+}
+
+impl fmt::LowerHex for HexDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    StringBuilder::<String>::fmt(f, |builder| {
+      builder.append_value(self.hex(false))
+    })
+  }
+}
+
+impl fmt::UpperHex for HexDisplay<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    StringBuilder::<String>::fmt(f, |builder| {
+      builder.append_value(self.hex(true))
+    })
+  }
+}
+
+/// The number of bytes on each line of a hexdump.
+const HEXDUMP_WIDTH: usize = 16;
+
+/// A hexdump in the format of `hexdump -C`.
+struct Hexdump<'a>(&'a [u8]);
+
+impl Hexdump<'_> {
+  /// The length of an offset, which is at least eight hex digits.
+  fn offset_len(offset: usize) -> usize {
+    let digits = match offset.checked_ilog2() {
+      Some(log) => log as usize / 4 + 1,
+      None => 1,
+    };
+    digits.max(8)
+  }
+
+  fn write(&self, mut write: impl FnMut(&str) -> fmt::Result) -> fmt::Result {
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+    // the longest offset plus the hex columns and ascii gutter
+    let mut line = [b' '; 16 + 52 + HEXDUMP_WIDTH + 3];
+    let write_offset = |line: &mut [u8], offset: usize| {
+      let len = Self::offset_len(offset);
+      let mut value = offset;
+      for digit in line[..len].iter_mut().rev() {
+        *digit = DIGITS[value & 0xf];
+        value >>= 4;
+      }
+      len
+    };
+    for (index, chunk) in self.0.chunks(HEXDUMP_WIDTH).enumerate() {
+      let offset_len = write_offset(&mut line, index * HEXDUMP_WIDTH);
+      let hex = &mut line[offset_len..offset_len + 52];
+      hex.fill(b' ');
+      for (i, byte) in chunk.iter().enumerate() {
+        // two leading spaces, then an extra space between the two groups
+        let start = 2 + i * 3 + if i >= 8 { 1 } else { 0 };
+        hex[start] = DIGITS[(byte >> 4) as usize];
+        hex[start + 1] = DIGITS[(byte & 0xf) as usize];
+      }
+      let gutter = &mut line[offset_len + 52..];
+      gutter[0] = b'|';
+      for (i, byte) in chunk.iter().enumerate() {
+        gutter[i + 1] = if byte.is_ascii_graphic() || *byte == b' ' {
+          *byte
+        } else {
+          b'.'
+        };
+      }
+      gutter[chunk.len() + 1] = b'|';
+      gutter[chunk.len() + 2] = b'\n';
+      let len = offset_len + 52 + chunk.len() + 3;
+      // SAFETY: only ascii was written
+      write(unsafe { std::str::from_utf8_unchecked(&line[..len]) })?;
+    }
+    if !self.0.is_empty() {
+      let len = write_offset(&mut line, self.0.len());
+      line[len] = b'\n';
+      // SAFETY: only ascii was written
+      write(unsafe { std::str::from_utf8_unchecked(&line[..len + 1]) })?;
+    }
+    Ok(())
+  }
+}
+
+impl StringAppendableValue for Hexdump<'_> {
+  fn byte_len(&self) -> usize {
+    if self.0.is_empty() {
+      return 0;
+    }
+    let lines = (0..self.0.len())
+      .step_by(HEXDUMP_WIDTH)
+      .fold(0, |len, offset| len + Self::offset_len(offset) + 52 + 3);
+    lines + self.0.len() + Self::offset_len(self.0.len()) + 1
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let _ = self.write(|value| {
+      text.push_str(value);
+      Ok(())
+    });
+  }
+
+  fn write_to_formatter(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    self.write(|value| fmt.write_str(value))
+  }
+}
+
+impl<TString: StringType> StringBuilder<'_, TString> {
+  /// Appends a hexdump of the bytes in the canonical format of
+  /// `hexdump -C`, with the offset, sixteen bytes as hex, and the bytes as
+  /// ascii on each line followed by a line with the total length.
+  ///
+  /// Unlike `hexdump`, repeated lines aren't collapsed.
+  pub fn append_hexdump(&mut self, bytes: &[u8]) {
+    self.append_value(Hexdump(bytes));
+  }
+}
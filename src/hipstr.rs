@@ -35,6 +35,11 @@ impl StringTypeMut for HipStr<'static> {
   fn len(&self) -> usize {
     self.len()
   }
+
+  #[inline(always)]
+  fn truncate(&mut self, len: usize) {
+    self.truncate(len);
+  }
 }
 
 impl<'a> StringAppendable<'a> for &'a HipStr<'_> {
@@ -0,0 +1,102 @@
+//! Formats integers as decimal text, using `itoa` when enabled.
+
+#[cfg(feature = "itoa")]
+pub(crate) use itoa::Buffer;
+#[cfg(not(feature = "itoa"))]
+pub(crate) use FallbackBuffer as Buffer;
+
+/// The length of the longest integer, `i128::MIN`.
+#[cfg(any(not(feature = "itoa"), test))]
+const MAX_LEN: usize = 40;
+
+/// A minimal formatter used when the `itoa` feature is disabled.
+#[cfg(any(not(feature = "itoa"), test))]
+pub(crate) struct FallbackBuffer {
+  bytes: [u8; MAX_LEN],
+}
+
+#[cfg(any(not(feature = "itoa"), test))]
+impl FallbackBuffer {
+  #[inline(always)]
+  pub fn new() -> Self {
+    Self {
+      bytes: [0; MAX_LEN],
+    }
+  }
+
+  #[inline(always)]
+  pub fn format(&mut self, value: impl FallbackInteger) -> &str {
+    let start = value.write(&mut self.bytes);
+    // SAFETY: only ascii digits and the sign were written
+    unsafe { std::str::from_utf8_unchecked(&self.bytes[start..]) }
+  }
+}
+
+#[cfg(any(not(feature = "itoa"), test))]
+pub(crate) trait FallbackInteger {
+  /// Writes the digits to the end of the buffer, returning the start.
+  fn write(self, bytes: &mut [u8; MAX_LEN]) -> usize;
+}
+
+#[cfg(any(not(feature = "itoa"), test))]
+macro_rules! impl_fallback_for_unsigned {
+  ($($t:ty),*) => {
+    $(
+      impl FallbackInteger for $t {
+        fn write(self, bytes: &mut [u8; MAX_LEN]) -> usize {
+          let mut value = self;
+          let mut start = MAX_LEN;
+          loop {
+            start -= 1;
+            bytes[start] = b'0' + (value % 10) as u8;
+            value /= 10;
+            if value == 0 {
+              return start;
+            }
+          }
+        }
+      }
+    )*
+  };
+}
+
+#[cfg(any(not(feature = "itoa"), test))]
+macro_rules! impl_fallback_for_signed {
+  ($($t:ty),*) => {
+    $(
+      impl FallbackInteger for $t {
+        fn write(self, bytes: &mut [u8; MAX_LEN]) -> usize {
+          let mut start = self.unsigned_abs().write(bytes);
+          if self < 0 {
+            start -= 1;
+            bytes[start] = b'-';
+          }
+          start
+        }
+      }
+    )*
+  };
+}
+
+#[cfg(any(not(feature = "itoa"), test))]
+impl_fallback_for_unsigned!(u8, u16, u32, u64, u128, usize);
+#[cfg(any(not(feature = "itoa"), test))]
+impl_fallback_for_signed!(i8, i16, i32, i64, i128, isize);
+
+#[cfg(test)]
+mod test {
+  use super::FallbackBuffer;
+
+  #[test]
+  fn fallback_matches_display() {
+    let mut buffer = FallbackBuffer::new();
+    assert_eq!(buffer.format(0u8), "0");
+    assert_eq!(buffer.format(u8::MAX), "255");
+    assert_eq!(buffer.format(-1i32), "-1");
+    assert_eq!(buffer.format(1_000_000u64), "1000000");
+    assert_eq!(buffer.format(i64::MIN), i64::MIN.to_string());
+    assert_eq!(buffer.format(i128::MIN), i128::MIN.to_string());
+    assert_eq!(buffer.format(u128::MAX), u128::MAX.to_string());
+    assert_eq!(buffer.format(isize::MIN), isize::MIN.to_string());
+  }
+}
@@ -0,0 +1,134 @@
+use std::io::Read;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+
+impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
+  /// Builds the bytes surfacing any error that occurred reading
+  /// from a reader (ex. `append_reader_exact`).
+  ///
+  /// An allocation failure is surfaced as `ErrorKind::OutOfMemory`.
+  pub fn build_io(
+    build: impl Fn(&mut BytesBuilder<'a, TBytes>),
+  ) -> std::io::Result<TBytes> {
+    let result = Self::build_mut(build)?;
+    match result.io_error {
+      Some(err) => Err(err),
+      None => Ok(TBytes::from_mut(result.bytes)),
+    }
+  }
+
+  /// Appends exactly `len` bytes read from the reader.
+  ///
+  /// The reader is only read on the second pass. If reading fails or the
+  /// reader ends early, the error is stored and surfaced from `build_io`
+  /// (or as `Error::Io` from `build`),
+  /// the remaining bytes are zero-filled to keep the capacity exact, and
+  /// subsequent reads are skipped.
+  pub fn append_reader_exact(&mut self, reader: impl Read, len: usize) {
+    let Some(bytes) = &mut self.bytes else {
+      self.capacity += len;
+      return;
+    };
+    let mut remaining = len;
+    if self.streaming {
+      if self.io_error.is_none() {
+        self.io_error = Some(std::io::Error::new(
+          std::io::ErrorKind::Unsupported,
+          "readers can't be appended when streaming with stream",
+        ));
+      }
+    } else if self.io_error.is_none() {
+      if let Err(err) = read_exact_into(*bytes, reader, &mut remaining) {
+        self.io_error = Some(err);
+      }
+    }
+    for _ in 0..remaining {
+      bytes.push(0);
+    }
+  }
+
+  /// Appends the contents of a file.
+  ///
+  /// The size is computed on the first pass from the file's metadata and the
+  /// file is streamed into the output on the second pass. Errors, including
+  /// the file size changing between the passes, are surfaced from
+  /// `build_io` (or as `Error::Io` from `build`).
+  pub fn append_file(&mut self, path: impl AsRef<std::path::Path>) {
+    let path = path.as_ref();
+    if self.bytes.is_none() {
+      let len = self.file_len(path);
+      self.section_lens.push(len);
+      self.capacity += len;
+      return;
+    }
+
+    let len = match self.section_lens.get(self.section_index) {
+      Some(len) => {
+        self.section_index += 1;
+        *len
+      }
+      // there was no first pass (ex. `hash_into`)
+      None => self.file_len(path),
+    };
+    let bytes = self.bytes.as_mut().unwrap();
+    let mut remaining = len;
+    if self.io_error.is_none() {
+      let result = std::fs::File::open(path).and_then(|mut file| {
+        read_exact_into(*bytes, &mut file, &mut remaining)?;
+        if file.read(&mut [0])? != 0 {
+          return Err(std::io::Error::other("file grew"));
+        }
+        Ok(())
+      });
+      if let Err(err) = result {
+        self.io_error = Some(std::io::Error::new(
+          err.kind(),
+          format!("failed reading {}: {}", path.display(), err),
+        ));
+      }
+    }
+    for _ in 0..remaining {
+      bytes.push(0);
+    }
+  }
+
+  fn file_len(&mut self, path: &std::path::Path) -> usize {
+    match std::fs::metadata(path) {
+      Ok(metadata) => metadata.len() as usize,
+      Err(err) => {
+        if self.io_error.is_none() {
+          self.io_error = Some(err);
+        }
+        0
+      }
+    }
+  }
+}
+
+fn read_exact_into(
+  bytes: &mut impl BytesTypeMut,
+  mut reader: impl Read,
+  remaining: &mut usize,
+) -> std::io::Result<()> {
+  let mut buffer = [0; 8 * 1024];
+  while *remaining > 0 {
+    let max = std::cmp::min(buffer.len(), *remaining);
+    match reader.read(&mut buffer[..max]) {
+      Ok(0) => {
+        return Err(std::io::Error::new(
+          std::io::ErrorKind::UnexpectedEof,
+          format!("reader ended {} bytes early", remaining),
+        ))
+      }
+      Ok(read) => {
+        bytes.extend_from_slice(&buffer[..read]);
+        *remaining -= read;
+      }
+      Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+      Err(err) => return Err(err),
+    }
+  }
+  Ok(())
+}
@@ -0,0 +1,785 @@
+//! A JSON serializer that writes to a `StringBuilder`.
+//!
+//! The value is serialized on both passes, so serializing to a string
+//! allocates once with the exact capacity.
+
+use std::cell::Cell;
+
+use serde::ser;
+use serde::ser::Impossible;
+use serde::Serialize;
+
+use crate::macro_support::DisplayValue;
+use crate::Error;
+use crate::StringAppendableValue;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+/// Serializes the value to a compact JSON string.
+pub fn to_string<T: Serialize + ?Sized>(value: &T) -> Result<String, Error> {
+  let error = Cell::new(None);
+  let text = StringBuilder::<String>::build(|builder| {
+    if let Err(err) = value.serialize(&mut Serializer::new(builder)) {
+      error.set(Some(err));
+    }
+  })?;
+  match error.into_inner() {
+    Some(err) => Err(err),
+    None => Ok(text),
+  }
+}
+
+/// Serializes values as compact JSON to a builder.
+pub struct Serializer<'b, 'a, TString: StringType> {
+  builder: &'b mut StringBuilder<'a, TString>,
+}
+
+impl<'b, 'a, TString: StringType> Serializer<'b, 'a, TString> {
+  pub fn new(builder: &'b mut StringBuilder<'a, TString>) -> Self {
+    Self { builder }
+  }
+}
+
+impl ser::Error for Error {
+  fn custom<T: std::fmt::Display>(msg: T) -> Self {
+    Error::Serialize(msg.to_string())
+  }
+}
+
+impl<'c, 'b, 'a, TString: StringType> ser::Serializer
+  for &'c mut Serializer<'b, 'a, TString>
+{
+  type Ok = ();
+  type Error = Error;
+  type SerializeSeq = Compound<'c, 'b, 'a, TString>;
+  type SerializeTuple = Compound<'c, 'b, 'a, TString>;
+  type SerializeTupleStruct = Compound<'c, 'b, 'a, TString>;
+  type SerializeTupleVariant = Compound<'c, 'b, 'a, TString>;
+  type SerializeMap = Compound<'c, 'b, 'a, TString>;
+  type SerializeStruct = Compound<'c, 'b, 'a, TString>;
+  type SerializeStructVariant = Compound<'c, 'b, 'a, TString>;
+
+  fn serialize_bool(self, v: bool) -> Result<(), Error> {
+    self.builder.append(if v { "true" } else { "false" });
+    Ok(())
+  }
+
+  fn serialize_i8(self, v: i8) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_i16(self, v: i16) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_i32(self, v: i32) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_i64(self, v: i64) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_i128(self, v: i128) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_u8(self, v: u8) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_u16(self, v: u16) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_u32(self, v: u32) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_u64(self, v: u64) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_u128(self, v: u128) -> Result<(), Error> {
+    self.builder.append(v);
+    Ok(())
+  }
+
+  fn serialize_f32(self, v: f32) -> Result<(), Error> {
+    if v.is_finite() {
+      self
+        .builder
+        .append_value(DisplayValue(&format_args!("{:?}", v)));
+    } else {
+      self.builder.append("null");
+    }
+    Ok(())
+  }
+
+  fn serialize_f64(self, v: f64) -> Result<(), Error> {
+    if v.is_finite() {
+      self
+        .builder
+        .append_value(DisplayValue(&format_args!("{:?}", v)));
+    } else {
+      self.builder.append("null");
+    }
+    Ok(())
+  }
+
+  fn serialize_char(self, v: char) -> Result<(), Error> {
+    self.serialize_str(v.encode_utf8(&mut [0; 4]))
+  }
+
+  fn serialize_str(self, v: &str) -> Result<(), Error> {
+    self.builder.append_value(JsonStr(v));
+    Ok(())
+  }
+
+  fn serialize_bytes(self, v: &[u8]) -> Result<(), Error> {
+    let mut seq = ser::Serializer::serialize_seq(self, Some(v.len()))?;
+    for byte in v {
+      ser::SerializeSeq::serialize_element(&mut seq, byte)?;
+    }
+    ser::SerializeSeq::end(seq)
+  }
+
+  fn serialize_none(self) -> Result<(), Error> {
+    self.serialize_unit()
+  }
+
+  fn serialize_some<T: Serialize + ?Sized>(
+    self,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(self)
+  }
+
+  fn serialize_unit(self) -> Result<(), Error> {
+    self.builder.append("null");
+    Ok(())
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+    self.serialize_unit()
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+  ) -> Result<(), Error> {
+    self.serialize_str(variant)
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.builder.append('{');
+    self.builder.append_value(JsonStr(variant));
+    self.builder.append(':');
+    value.serialize(&mut *self)?;
+    self.builder.append('}');
+    Ok(())
+  }
+
+  fn serialize_seq(
+    self,
+    _len: Option<usize>,
+  ) -> Result<Self::SerializeSeq, Error> {
+    self.builder.append('[');
+    Ok(Compound::new(self, "]"))
+  }
+
+  fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, Error> {
+    self.serialize_seq(Some(len))
+  }
+
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    len: usize,
+  ) -> Result<Self::SerializeTupleStruct, Error> {
+    self.serialize_seq(Some(len))
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleVariant, Error> {
+    self.builder.append('{');
+    self.builder.append_value(JsonStr(variant));
+    self.builder.append(":[");
+    Ok(Compound::new(self, "]}"))
+  }
+
+  fn serialize_map(
+    self,
+    _len: Option<usize>,
+  ) -> Result<Self::SerializeMap, Error> {
+    self.builder.append('{');
+    Ok(Compound::new(self, "}"))
+  }
+
+  fn serialize_struct(
+    self,
+    _name: &'static str,
+    len: usize,
+  ) -> Result<Self::SerializeStruct, Error> {
+    self.serialize_map(Some(len))
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeStructVariant, Error> {
+    self.builder.append('{');
+    self.builder.append_value(JsonStr(variant));
+    self.builder.append(":{");
+    Ok(Compound::new(self, "}}"))
+  }
+}
+
+/// Serializes the elements of a sequence or the entries of a map.
+pub struct Compound<'c, 'b, 'a, TString: StringType> {
+  ser: &'c mut Serializer<'b, 'a, TString>,
+  first: bool,
+  close: &'static str,
+}
+
+impl<'c, 'b, 'a, TString: StringType> Compound<'c, 'b, 'a, TString> {
+  fn new(
+    ser: &'c mut Serializer<'b, 'a, TString>,
+    close: &'static str,
+  ) -> Self {
+    Self {
+      ser,
+      first: true,
+      close,
+    }
+  }
+
+  fn separator(&mut self) {
+    if self.first {
+      self.first = false;
+    } else {
+      self.ser.builder.append(',');
+    }
+  }
+
+  fn element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+    self.separator();
+    value.serialize(&mut *self.ser)
+  }
+
+  fn field<T: Serialize + ?Sized>(
+    &mut self,
+    key: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.separator();
+    self.ser.builder.append_value(JsonStr(key));
+    self.ser.builder.append(':');
+    value.serialize(&mut *self.ser)
+  }
+
+  fn close(self) -> Result<(), Error> {
+    self.ser.builder.append(self.close);
+    Ok(())
+  }
+}
+
+impl<TString: StringType> ser::SerializeSeq for Compound<'_, '_, '_, TString> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.element(value)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    self.close()
+  }
+}
+
+impl<TString: StringType> ser::SerializeTuple
+  for Compound<'_, '_, '_, TString>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_element<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.element(value)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    self.close()
+  }
+}
+
+impl<TString: StringType> ser::SerializeTupleStruct
+  for Compound<'_, '_, '_, TString>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.element(value)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    self.close()
+  }
+}
+
+impl<TString: StringType> ser::SerializeTupleVariant
+  for Compound<'_, '_, '_, TString>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.element(value)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    self.close()
+  }
+}
+
+impl<TString: StringType> ser::SerializeMap for Compound<'_, '_, '_, TString> {
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_key<T: Serialize + ?Sized>(
+    &mut self,
+    key: &T,
+  ) -> Result<(), Error> {
+    self.separator();
+    key.serialize(MapKeySerializer {
+      builder: &mut *self.ser.builder,
+    })?;
+    self.ser.builder.append(':');
+    Ok(())
+  }
+
+  fn serialize_value<T: Serialize + ?Sized>(
+    &mut self,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(&mut *self.ser)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    self.close()
+  }
+}
+
+impl<TString: StringType> ser::SerializeStruct
+  for Compound<'_, '_, '_, TString>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    key: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.field(key, value)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    self.close()
+  }
+}
+
+impl<TString: StringType> ser::SerializeStructVariant
+  for Compound<'_, '_, '_, TString>
+{
+  type Ok = ();
+  type Error = Error;
+
+  fn serialize_field<T: Serialize + ?Sized>(
+    &mut self,
+    key: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    self.field(key, value)
+  }
+
+  fn end(self) -> Result<(), Error> {
+    self.close()
+  }
+}
+
+/// Serializes map keys, which must be strings in JSON. Like `serde_json`,
+/// integer, bool, and char keys are quoted.
+struct MapKeySerializer<'b, 'a, TString: StringType> {
+  builder: &'b mut StringBuilder<'a, TString>,
+}
+
+fn key_must_be_a_string() -> Error {
+  Error::Serialize("key must be a string".to_string())
+}
+
+impl<TString: StringType> MapKeySerializer<'_, '_, TString> {
+  fn quoted(self, value: impl StringAppendableValue) -> Result<(), Error> {
+    self.builder.append('"');
+    self.builder.append_value(value);
+    self.builder.append('"');
+    Ok(())
+  }
+}
+
+impl<TString: StringType> ser::Serializer
+  for MapKeySerializer<'_, '_, TString>
+{
+  type Ok = ();
+  type Error = Error;
+  type SerializeSeq = Impossible<(), Error>;
+  type SerializeTuple = Impossible<(), Error>;
+  type SerializeTupleStruct = Impossible<(), Error>;
+  type SerializeTupleVariant = Impossible<(), Error>;
+  type SerializeMap = Impossible<(), Error>;
+  type SerializeStruct = Impossible<(), Error>;
+  type SerializeStructVariant = Impossible<(), Error>;
+
+  fn serialize_bool(self, v: bool) -> Result<(), Error> {
+    self.quoted(if v { "true" } else { "false" })
+  }
+
+  fn serialize_i8(self, v: i8) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_i16(self, v: i16) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_i32(self, v: i32) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_i64(self, v: i64) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_i128(self, v: i128) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_u8(self, v: u8) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_u16(self, v: u16) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_u32(self, v: u32) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_u64(self, v: u64) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_u128(self, v: u128) -> Result<(), Error> {
+    self.quoted(v)
+  }
+
+  fn serialize_f32(self, _v: f32) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_f64(self, _v: f64) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_char(self, v: char) -> Result<(), Error> {
+    self.serialize_str(v.encode_utf8(&mut [0; 4]))
+  }
+
+  fn serialize_str(self, v: &str) -> Result<(), Error> {
+    self.builder.append_value(JsonStr(v));
+    Ok(())
+  }
+
+  fn serialize_bytes(self, _v: &[u8]) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_none(self) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_some<T: Serialize + ?Sized>(
+    self,
+    _value: &T,
+  ) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_unit(self) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_unit_struct(self, _name: &'static str) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_unit_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    variant: &'static str,
+  ) -> Result<(), Error> {
+    self.serialize_str(variant)
+  }
+
+  fn serialize_newtype_struct<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    value: &T,
+  ) -> Result<(), Error> {
+    value.serialize(self)
+  }
+
+  fn serialize_newtype_variant<T: Serialize + ?Sized>(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    _value: &T,
+  ) -> Result<(), Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_seq(
+    self,
+    _len: Option<usize>,
+  ) -> Result<Self::SerializeSeq, Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_tuple_struct(
+    self,
+    _name: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleStruct, Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_tuple_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeTupleVariant, Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_map(
+    self,
+    _len: Option<usize>,
+  ) -> Result<Self::SerializeMap, Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_struct(
+    self,
+    _name: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeStruct, Error> {
+    Err(key_must_be_a_string())
+  }
+
+  fn serialize_struct_variant(
+    self,
+    _name: &'static str,
+    _variant_index: u32,
+    _variant: &'static str,
+    _len: usize,
+  ) -> Result<Self::SerializeStructVariant, Error> {
+    Err(key_must_be_a_string())
+  }
+}
+
+/// A quoted and escaped JSON string.
+struct JsonStr<'b>(&'b str);
+
+impl JsonStr<'_> {
+  fn write(
+    &self,
+    mut write: impl FnMut(&str) -> std::fmt::Result,
+  ) -> std::fmt::Result {
+    write("\"")?;
+    let bytes = self.0.as_bytes();
+    let mut start = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+      let escaped = match byte {
+        b'"' => "\\\"",
+        b'\\' => "\\\\",
+        b'\n' => "\\n",
+        b'\r' => "\\r",
+        b'\t' => "\\t",
+        0x08 => "\\b",
+        0x0C => "\\f",
+        0x00..=0x1F => "",
+        _ => continue,
+      };
+      if start < i {
+        write(&self.0[start..i])?;
+      }
+      if escaped.is_empty() {
+        const HEX: &[u8; 16] = b"0123456789abcdef";
+        let unicode = [
+          b'\\',
+          b'u',
+          b'0',
+          b'0',
+          HEX[(byte >> 4) as usize],
+          HEX[(byte & 0xF) as usize],
+        ];
+        write(std::str::from_utf8(&unicode).unwrap())?;
+      } else {
+        write(escaped)?;
+      }
+      start = i + 1;
+    }
+    if start < bytes.len() {
+      write(&self.0[start..])?;
+    }
+    write("\"")
+  }
+}
+
+impl StringAppendableValue for JsonStr<'_> {
+  fn byte_len(&self) -> usize {
+    let escapes = self
+      .0
+      .bytes()
+      .map(|byte| match byte {
+        b'"' | b'\\' | b'\n' | b'\r' | b'\t' | 0x08 | 0x0C => 1,
+        0x00..=0x1F => 5,
+        _ => 0,
+      })
+      .sum::<usize>();
+    self.0.len() + escapes + 2
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let _ = self.write(|value| {
+      text.push_str(value);
+      Ok(())
+    });
+  }
+
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    self.write(|value| fmt.write_str(value))
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use std::collections::BTreeMap;
+
+  use serde::Serialize;
+
+  #[derive(Serialize)]
+  enum Shape {
+    Empty,
+    Circle(f64),
+    Point(i32, i32),
+    Rect { width: u32, height: u32 },
+  }
+
+  #[derive(Serialize)]
+  struct Drawing<'a> {
+    name: &'a str,
+    shapes: Vec<Shape>,
+    tags: BTreeMap<u32, Option<char>>,
+    bytes: &'a [u8],
+    unit: (),
+  }
+
+  #[test]
+  fn matches_serde_json() {
+    let drawing = Drawing {
+      name: "quote \" slash \\ newline \n control \u{1} emoji 😀",
+      shapes: vec![
+        Shape::Empty,
+        Shape::Circle(1.5),
+        Shape::Circle(f64::NAN),
+        Shape::Point(-1, 2),
+        Shape::Rect {
+          width: 3,
+          height: 4,
+        },
+      ],
+      tags: BTreeMap::from([(1, Some('a')), (2, None)]),
+      bytes: &[],
+      unit: (),
+    };
+    let text = super::to_string(&drawing).unwrap();
+    assert_eq!(text, serde_json::to_string(&drawing).unwrap());
+    assert_eq!(text.capacity(), text.len());
+  }
+
+  #[test]
+  fn errors_for_non_string_keys() {
+    let map = BTreeMap::from([((1, 2), 3)]);
+    let err = super::to_string(&map).unwrap_err();
+    assert_eq!(err.to_string(), "key must be a string");
+  }
+}
@@ -1,29 +1,77 @@
 use std::borrow::Cow;
 use std::collections::TryReserveError;
+use std::collections::VecDeque;
 use std::fmt::Write;
 
+#[cfg(feature = "arrayvec")]
+pub mod arrayvec;
+mod atomic;
+#[cfg(feature = "serde")]
+pub mod binary;
+#[cfg(feature = "bstr")]
+pub mod bstr;
+mod c_string;
+pub mod cbor;
+pub mod chunks;
+pub mod crc;
+#[cfg(feature = "digest")]
+pub mod digest;
+mod duration;
 #[cfg(feature = "ecow")]
 pub mod ecow;
+mod error;
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+mod fixed_capacity;
+pub mod frame;
+#[cfg(feature = "heapless")]
+pub mod heapless;
+mod hex;
 #[cfg(feature = "hipstr")]
 pub mod hipstr;
-
+mod int_buffer;
+mod io;
+#[cfg(feature = "serde")]
+pub mod json;
+#[doc(hidden)]
+pub mod macro_support;
+pub mod mismatch;
+pub mod msgpack;
+#[cfg(feature = "num-bigint")]
+pub mod num_bigint;
+mod number;
+mod os_string;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod path;
+pub mod protobuf;
+pub mod reader;
+#[cfg(feature = "rust_decimal")]
+pub mod rust_decimal;
+#[cfg(feature = "rayon")]
+pub mod sections;
+pub mod segments;
+pub mod sink;
+pub mod slice;
+mod small_string;
+pub mod template;
+mod transform;
+mod utf16;
+
+#[cfg(feature = "serde")]
+#[doc(hidden)]
+pub use serde as __serde;
+
+pub use capacity_builder_macros::capacity_format;
+pub use capacity_builder_macros::precompute;
+pub use capacity_builder_macros::BytesAppendable;
 pub use capacity_builder_macros::CapacityDisplay;
-
-macro_rules! count_digits {
-  ($value:expr) => {{
-    let mut value = $value;
-    if value == 0 {
-      1
-    } else {
-      let mut count = 0;
-      while value > 0 {
-        value /= 10;
-        count += 1;
-      }
-      count
-    }
-  }};
-}
+pub use capacity_builder_macros::StringAppendable;
+pub use duration::DurationUnit;
+pub use duration::TimestampPrecision;
+pub use error::Error;
+pub use hex::HexDisplay;
+pub use path::PathBuilder;
+pub use small_string::SmallString;
 
 macro_rules! impl_appendable_for_int {
   ($($t:ty),*) => {
@@ -44,24 +92,49 @@ macro_rules! impl_appendable_for_int {
 
       impl StringAppendableValue for $t {
         fn byte_len(&self) -> usize {
-          count_digits!(*self)
+          macro_support::ConstLen(*self).byte_len()
         }
 
         fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
           // no need to reuse buffers as per the documentation
           // and as found in my benchmarks
-          let mut buffer = itoa::Buffer::new();
+          let mut buffer = int_buffer::Buffer::new();
           let s = buffer.format(*self);
           text.push_str(s);
         }
 
         #[inline(always)]
         fn write_to_formatter(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-          let mut buffer = itoa::Buffer::new();
+          let mut buffer = int_buffer::Buffer::new();
           let s = buffer.format(*self);
           fmt.write_str(s)
         }
       }
+
+      impl Integer for $t {
+        #[inline(always)]
+        #[allow(unused_comparisons, clippy::absurd_extreme_comparisons)]
+        fn is_negative(&self) -> bool {
+          *self < 0
+        }
+      }
+
+      impl StringAppendableValue for &$t {
+        #[inline(always)]
+        fn byte_len(&self) -> usize {
+          StringAppendableValue::byte_len(*self)
+        }
+
+        #[inline(always)]
+        fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+          StringAppendableValue::push_to(*self, text)
+        }
+
+        #[inline(always)]
+        fn write_to_formatter(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+          StringAppendableValue::write_to_formatter(*self, fmt)
+        }
+      }
     )*
   };
 }
@@ -75,19 +148,23 @@ pub trait StringAppendable<'a> {
 
 impl<'a, T> StringAppendable<'a> for T
 where
-  T: StringAppendableValue,
+  T: StringAppendableValue + 'a,
 {
   fn append_to_builder<TString: StringType>(
     self,
     builder: &mut StringBuilder<'a, TString>,
   ) {
-    builder.append_value(self);
+    builder.append_borrowable_value(self);
   }
 }
 
 pub trait StringType: Sized {
   type MutType: StringTypeMut;
 
+  /// The most bytes the output can hold. Building errors with
+  /// `Error::CapacityExceeded` when the capacity is larger.
+  const MAX_CAPACITY: usize = usize::MAX;
+
   fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError>;
   fn from_mut(inner: Self::MutType) -> Self;
 }
@@ -97,6 +174,50 @@ pub trait StringTypeMut {
   fn push(&mut self, c: char);
   fn push_str(&mut self, str: &str);
   fn len(&self) -> usize;
+
+  /// Shortens the text to the provided byte length.
+  ///
+  /// Panics by default for outputs that can't remove text.
+  fn truncate(&mut self, len: usize) {
+    let _ = len;
+    panic!("truncating is not supported by this string type");
+  }
+
+  /// Moves the text from `start` to the end so that it begins at `index`,
+  /// shifting the text in between to after it.
+  ///
+  /// Panics by default for outputs that can't move text.
+  fn move_to(&mut self, start: usize, index: usize) {
+    let _ = (start, index);
+    panic!("moving text is not supported by this string type");
+  }
+}
+
+impl<T: StringTypeMut + ?Sized> StringTypeMut for &mut T {
+  #[inline(always)]
+  fn push(&mut self, c: char) {
+    (**self).push(c)
+  }
+
+  #[inline(always)]
+  fn push_str(&mut self, str: &str) {
+    (**self).push_str(str)
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    (**self).len()
+  }
+
+  #[inline(always)]
+  fn truncate(&mut self, len: usize) {
+    (**self).truncate(len)
+  }
+
+  #[inline(always)]
+  fn move_to(&mut self, start: usize, index: usize) {
+    (**self).move_to(start, index)
+  }
 }
 
 impl StringType for String {
@@ -118,11 +239,27 @@ impl StringType for String {
 impl StringTypeMut for String {
   #[inline(always)]
   fn push(&mut self, c: char) {
+    #[cfg(feature = "unsafe-fast")]
+    if c.is_ascii() {
+      // SAFETY: an ascii byte is valid utf-8
+      return unsafe { self.as_mut_vec() }.push(c as u8);
+    }
     String::push(self, c)
   }
 
   #[inline(always)]
   fn push_str(&mut self, str: &str) {
+    #[cfg(feature = "unsafe-fast")]
+    {
+      // SAFETY: the bytes are valid utf-8 as they're from a str, and the
+      // vec only writes to the spare capacity reserved by the first pass
+      // (growing when a length was miscalculated)
+      BytesTypeMut::extend_from_slice(
+        unsafe { self.as_mut_vec() },
+        str.as_bytes(),
+      );
+    }
+    #[cfg(not(feature = "unsafe-fast"))]
     String::push_str(self, str)
   }
 
@@ -130,6 +267,20 @@ impl StringTypeMut for String {
   fn len(&self) -> usize {
     String::len(self)
   }
+
+  #[inline(always)]
+  fn truncate(&mut self, len: usize) {
+    String::truncate(self, len)
+  }
+
+  fn move_to(&mut self, start: usize, index: usize) {
+    assert!(self.is_char_boundary(start) && self.is_char_boundary(index));
+    assert!(index <= start);
+    let moved_len = self.len() - start;
+    // SAFETY: both rotated parts are valid utf-8 as they start and end
+    // on char boundaries
+    unsafe { self.as_mut_vec()[index..].rotate_right(moved_len) };
+  }
 }
 
 impl StringType for Box<str> {
@@ -148,6 +299,22 @@ impl StringType for Box<str> {
   }
 }
 
+impl StringType for Cow<'_, str> {
+  type MutType = String;
+
+  #[inline(always)]
+  fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+    let mut text = String::new();
+    text.try_reserve_exact(size)?;
+    Ok(text)
+  }
+
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    Cow::Owned(inner)
+  }
+}
+
 impl<'a> StringAppendable<'a> for &'a Box<str> {
   #[inline(always)]
   fn append_to_builder<TString: StringType>(
@@ -165,11 +332,25 @@ pub trait StringAppendableValue {
     &self,
     fmt: &mut std::fmt::Formatter<'_>,
   ) -> std::fmt::Result;
+
+  /// Gets the value as a string borrowed for `'b` when possible, which
+  /// allows `StringBuilder::build_cow` to avoid allocating.
+  #[inline(always)]
+  fn into_borrowed_str<'b>(self) -> Result<&'b str, Self>
+  where
+    Self: Sized + 'b,
+  {
+    Err(self)
+  }
 }
 
 pub trait BytesType: Sized {
   type MutType: BytesTypeMut;
 
+  /// The most bytes the output can hold. Building errors with
+  /// `Error::CapacityExceeded` when the capacity is larger.
+  const MAX_CAPACITY: usize = usize::MAX;
+
   fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError>;
   fn from_mut(inner: Self::MutType) -> Self;
 }
@@ -179,6 +360,16 @@ pub trait BytesTypeMut: Sized {
   fn push(&mut self, c: u8);
   fn extend_from_slice(&mut self, bytes: &[u8]);
   fn len(&self) -> usize;
+  /// Provides the bytes from `start` to the end in one or
+  /// more contiguous slices (ex. for checksums).
+  ///
+  /// Panics by default for outputs that don't keep the bytes.
+  fn slices_from(&self, start: usize, f: impl FnMut(&[u8])) {
+    let _ = (start, f);
+    panic!(
+      "reading previously appended bytes is not supported by this bytes type"
+    );
+  }
 }
 
 impl BytesType for Vec<u8> {
@@ -213,6 +404,22 @@ impl BytesType for Box<[u8]> {
   }
 }
 
+impl BytesType for Cow<'_, [u8]> {
+  type MutType = Vec<u8>;
+
+  #[inline(always)]
+  fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(size)?;
+    Ok(bytes)
+  }
+
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    Cow::Owned(inner)
+  }
+}
+
 impl<'a> BytesAppendable<'a> for &'a Box<[u8]> {
   fn append_to_builder<TBytes: BytesType>(
     self,
@@ -225,18 +432,47 @@ impl<'a> BytesAppendable<'a> for &'a Box<[u8]> {
 impl BytesTypeMut for Vec<u8> {
   #[inline(always)]
   fn push(&mut self, c: u8) {
-    self.push(c)
+    match self.spare_capacity_mut().first_mut() {
+      Some(spare) => {
+        spare.write(c);
+        // SAFETY: the byte after the length was initialized above
+        unsafe { self.set_len(self.len() + 1) };
+      }
+      None => self.push(c),
+    }
   }
 
+  /// Writes to the spare capacity directly when it's large enough, which
+  /// it is after the capacity pass, instead of going through `Vec`'s
+  /// growth path on every append.
   #[inline(always)]
   fn extend_from_slice(&mut self, bytes: &[u8]) {
-    self.extend_from_slice(bytes);
+    let spare = self.spare_capacity_mut();
+    if bytes.len() <= spare.len() {
+      // SAFETY: the spare capacity is large enough for the bytes, which
+      // are initialized before increasing the length
+      unsafe {
+        std::ptr::copy_nonoverlapping(
+          bytes.as_ptr(),
+          spare.as_mut_ptr().cast::<u8>(),
+          bytes.len(),
+        );
+        self.set_len(self.len() + bytes.len());
+      }
+    } else {
+      self.extend_from_slice(bytes);
+    }
   }
 
   #[inline(always)]
   fn len(&self) -> usize {
     self.len()
   }
+
+  #[inline(always)]
+  fn slices_from(&self, start: usize, mut f: impl FnMut(&[u8])) {
+    f(&self[start..])
+  }
 }
 
 pub trait BytesAppendable<'a> {
@@ -269,39 +505,153 @@ pub trait EndianBytesAppendable {
   fn push_be_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes);
 }
 
-impl StringAppendableValue for &str {
+/// Text operations shared by `StringBuilder` and `BytesBuilder` so that
+/// one `GenericAppendable` implementation can serve both.
+#[allow(clippy::len_without_is_empty)]
+pub trait Builder<'a> {
+  fn append_str(&mut self, value: &'a str);
+  fn append_char(&mut self, value: char);
+  /// Appends the integer as decimal text.
+  fn append_int(&mut self, value: impl Integer);
+  fn len(&self) -> usize;
+}
+
+/// An integer that can be appended as decimal text.
+pub trait Integer: StringAppendableValue + Copy {
+  #[doc(hidden)]
+  fn is_negative(&self) -> bool;
+}
+
+/// An appendable written once against `Builder` that can be appended to
+/// both a `StringBuilder` and a `BytesBuilder` via `Generic`.
+pub trait GenericAppendable<'a> {
+  fn append_to_builder<TBuilder: Builder<'a>>(self, builder: &mut TBuilder);
+}
+
+/// Appends a `GenericAppendable` to a `StringBuilder` or `BytesBuilder`.
+#[derive(Debug, Clone, Copy)]
+pub struct Generic<T>(pub T);
+
+impl<'a, T: GenericAppendable<'a>> StringAppendable<'a> for Generic<T> {
   #[inline(always)]
-  fn byte_len(&self) -> usize {
-    self.len()
+  fn append_to_builder<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    self.0.append_to_builder(builder)
   }
+}
 
+impl<'a, T: GenericAppendable<'a>> BytesAppendable<'a> for Generic<T> {
   #[inline(always)]
-  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
-    text.push_str(self);
+  fn append_to_builder<TBytes: BytesType>(
+    self,
+    builder: &mut BytesBuilder<'a, TBytes>,
+  ) {
+    self.0.append_to_builder(builder)
+  }
+}
+
+/// Lazily builds an owned value at most once, which allows appending
+/// expensive values by reference without building them on each pass.
+pub struct Memoized<T, F: Fn() -> T> {
+  build: F,
+  value: std::cell::OnceCell<T>,
+}
+
+impl<T, F: Fn() -> T> Memoized<T, F> {
+  pub fn new(build: F) -> Self {
+    Self {
+      build,
+      value: std::cell::OnceCell::new(),
+    }
   }
 
+  /// Gets the value, building it on the first call.
+  pub fn get(&self) -> &T {
+    self.value.get_or_init(&self.build)
+  }
+}
+
+impl<'a, F: Fn() -> String> StringAppendable<'a> for &'a Memoized<String, F> {
   #[inline(always)]
-  fn write_to_formatter(
-    &self,
-    fmt: &mut std::fmt::Formatter<'_>,
-  ) -> std::fmt::Result {
-    fmt.write_str(self)
+  fn append_to_builder<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    builder.append(self.get().as_str())
   }
 }
 
-impl BytesAppendableValue for &str {
+impl<'a, F: Fn() -> String> BytesAppendable<'a> for &'a Memoized<String, F> {
+  #[inline(always)]
+  fn append_to_builder<TBytes: BytesType>(
+    self,
+    builder: &mut BytesBuilder<'a, TBytes>,
+  ) {
+    builder.append(self.get().as_str())
+  }
+}
+
+impl<'a, F: Fn() -> Vec<u8>> BytesAppendable<'a> for &'a Memoized<Vec<u8>, F> {
+  #[inline(always)]
+  fn append_to_builder<TBytes: BytesType>(
+    self,
+    builder: &mut BytesBuilder<'a, TBytes>,
+  ) {
+    builder.append(self.get().as_slice())
+  }
+}
+
+/// A number appended in big-endian byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BigEndian<T: EndianBytesAppendable>(pub T);
+
+impl<T: EndianBytesAppendable> BytesAppendableValue for BigEndian<T> {
   #[inline(always)]
   fn byte_len(&self) -> usize {
-    self.len()
+    self.0.byte_len()
   }
 
   #[inline(always)]
   fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
-    bytes.extend_from_slice(self.as_bytes());
+    self.0.push_be_to(bytes)
   }
 }
 
-impl StringAppendableValue for &String {
+/// A number appended in little-endian byte order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LittleEndian<T: EndianBytesAppendable>(pub T);
+
+impl<T: EndianBytesAppendable> BytesAppendableValue for LittleEndian<T> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.0.byte_len()
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    self.0.push_le_to(bytes)
+  }
+}
+
+/// Provides `.be()` and `.le()` for specifying the byte order of a number
+/// inline, ex. `builder.append(version.be())`.
+pub trait ByteOrderExt: EndianBytesAppendable + Sized {
+  #[inline(always)]
+  fn be(self) -> BigEndian<Self> {
+    BigEndian(self)
+  }
+
+  #[inline(always)]
+  fn le(self) -> LittleEndian<Self> {
+    LittleEndian(self)
+  }
+}
+
+impl<T: EndianBytesAppendable> ByteOrderExt for T {}
+
+impl StringAppendableValue for &str {
   #[inline(always)]
   fn byte_len(&self) -> usize {
     self.len()
@@ -319,9 +669,17 @@ impl StringAppendableValue for &String {
   ) -> std::fmt::Result {
     fmt.write_str(self)
   }
+
+  #[inline(always)]
+  fn into_borrowed_str<'b>(self) -> Result<&'b str, Self>
+  where
+    Self: Sized + 'b,
+  {
+    Ok(self)
+  }
 }
 
-impl BytesAppendableValue for &String {
+impl BytesAppendableValue for &str {
   #[inline(always)]
   fn byte_len(&self) -> usize {
     self.len()
@@ -333,7 +691,7 @@ impl BytesAppendableValue for &String {
   }
 }
 
-impl<'a> StringAppendableValue for &'a Cow<'a, str> {
+impl StringAppendableValue for &String {
   #[inline(always)]
   fn byte_len(&self) -> usize {
     self.len()
@@ -351,9 +709,17 @@ impl<'a> StringAppendableValue for &'a Cow<'a, str> {
   ) -> std::fmt::Result {
     fmt.write_str(self)
   }
+
+  #[inline(always)]
+  fn into_borrowed_str<'b>(self) -> Result<&'b str, Self>
+  where
+    Self: Sized + 'b,
+  {
+    Ok(self.as_str())
+  }
 }
 
-impl<'a> BytesAppendableValue for &'a Cow<'a, str> {
+impl BytesAppendableValue for &String {
   #[inline(always)]
   fn byte_len(&self) -> usize {
     self.len()
@@ -365,19 +731,19 @@ impl<'a> BytesAppendableValue for &'a Cow<'a, str> {
   }
 }
 
-impl_appendable_for_int!(
-  i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
-);
-
-impl StringAppendableValue for char {
+/// Nested references, ex. the `&&str` items of `.iter()` over a `Vec<&str>`.
+impl<'s, T: ?Sized> StringAppendableValue for &&'s T
+where
+  &'s T: StringAppendableValue,
+{
   #[inline(always)]
   fn byte_len(&self) -> usize {
-    self.len_utf8()
+    (**self).byte_len()
   }
 
   #[inline(always)]
   fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
-    text.push(*self);
+    (**self).push_to(text)
   }
 
   #[inline(always)]
@@ -385,37 +751,47 @@ impl StringAppendableValue for char {
     &self,
     fmt: &mut std::fmt::Formatter<'_>,
   ) -> std::fmt::Result {
-    fmt.write_char(*self)
+    (**self).write_to_formatter(fmt)
+  }
+
+  #[inline(always)]
+  fn into_borrowed_str<'b>(self) -> Result<&'b str, Self>
+  where
+    Self: Sized + 'b,
+  {
+    (*self).into_borrowed_str().map_err(|_| self)
   }
 }
 
-impl BytesAppendableValue for char {
+impl<'s, T: ?Sized> BytesAppendableValue for &&'s T
+where
+  &'s T: BytesAppendableValue,
+{
   #[inline(always)]
   fn byte_len(&self) -> usize {
-    self.len_utf8()
+    (**self).byte_len()
   }
 
+  #[inline(always)]
   fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
-    let mut buffer = [0; 4];
-    let encoded = self.encode_utf8(&mut buffer);
-    bytes.extend_from_slice(encoded.as_bytes())
+    (**self).push_to(bytes)
   }
 }
 
-impl<T: StringAppendableValue> StringAppendableValue for Option<T> {
+/// Appends any `AsRef<str>` value, ex. `builder.append(Str(&name))` for a
+/// string newtype or `Rc<str>`.
+#[derive(Debug, Clone, Copy)]
+pub struct Str<T: AsRef<str>>(pub T);
+
+impl<T: AsRef<str>> StringAppendableValue for Str<T> {
   #[inline(always)]
   fn byte_len(&self) -> usize {
-    match self {
-      Some(value) => value.byte_len(),
-      None => 0,
-    }
+    self.0.as_ref().len()
   }
 
   #[inline(always)]
   fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
-    if let Some(value) = self {
-      value.push_to(text);
-    }
+    text.push_str(self.0.as_ref());
   }
 
   #[inline(always)]
@@ -423,56 +799,51 @@ impl<T: StringAppendableValue> StringAppendableValue for Option<T> {
     &self,
     fmt: &mut std::fmt::Formatter<'_>,
   ) -> std::fmt::Result {
-    if let Some(value) = self {
-      value.write_to_formatter(fmt)
-    } else {
-      Ok(())
-    }
+    fmt.write_str(self.0.as_ref())
   }
 }
 
-impl<T: BytesAppendableValue> BytesAppendableValue for Option<T> {
+impl<T: AsRef<str>> BytesAppendableValue for Str<T> {
   #[inline(always)]
   fn byte_len(&self) -> usize {
-    match self {
-      Some(value) => value.byte_len(),
-      None => 0,
-    }
+    self.0.as_ref().len()
   }
 
   #[inline(always)]
   fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
-    if let Some(value) = self {
-      value.push_to(bytes);
-    }
+    bytes.extend_from_slice(self.0.as_ref().as_bytes());
   }
 }
 
-impl BytesAppendableValue for &Vec<u8> {
+impl<'a> StringAppendableValue for &'a Cow<'a, str> {
   #[inline(always)]
   fn byte_len(&self) -> usize {
     self.len()
   }
 
   #[inline(always)]
-  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
-    bytes.extend_from_slice(self)
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    text.push_str(self);
   }
-}
 
-impl BytesAppendableValue for u8 {
   #[inline(always)]
-  fn byte_len(&self) -> usize {
-    1
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    fmt.write_str(self)
   }
 
   #[inline(always)]
-  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
-    bytes.push(*self)
+  fn into_borrowed_str<'b>(self) -> Result<&'b str, Self>
+  where
+    Self: Sized + 'b,
+  {
+    Ok(self.as_ref())
   }
 }
 
-impl BytesAppendableValue for [u8] {
+impl<'a> BytesAppendableValue for &'a Cow<'a, str> {
   #[inline(always)]
   fn byte_len(&self) -> usize {
     self.len()
@@ -480,7 +851,262 @@ impl BytesAppendableValue for [u8] {
 
   #[inline(always)]
   fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
-    bytes.extend_from_slice(self)
+    bytes.extend_from_slice(self.as_bytes());
+  }
+}
+
+impl_appendable_for_int!(
+  i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, u128, usize
+);
+
+macro_rules! impl_appendable_for_num_wrapper {
+  ($($t:ident),*) => {
+    $(
+      impl<T: StringAppendableValue> StringAppendableValue for std::num::$t<T> {
+        #[inline(always)]
+        fn byte_len(&self) -> usize {
+          self.0.byte_len()
+        }
+
+        #[inline(always)]
+        fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+          self.0.push_to(text)
+        }
+
+        #[inline(always)]
+        fn write_to_formatter(
+          &self,
+          fmt: &mut std::fmt::Formatter<'_>,
+        ) -> std::fmt::Result {
+          self.0.write_to_formatter(fmt)
+        }
+      }
+
+      impl<T: EndianBytesAppendable> EndianBytesAppendable for std::num::$t<T> {
+        #[inline(always)]
+        fn byte_len(&self) -> usize {
+          self.0.byte_len()
+        }
+
+        #[inline(always)]
+        fn push_le_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+          self.0.push_le_to(bytes)
+        }
+
+        #[inline(always)]
+        fn push_be_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+          self.0.push_be_to(bytes)
+        }
+      }
+    )*
+  };
+}
+
+impl_appendable_for_num_wrapper!(Wrapping, Saturating);
+
+impl StringAppendableValue for char {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.len_utf8()
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    text.push(*self);
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    fmt.write_char(*self)
+  }
+}
+
+impl BytesAppendableValue for char {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.len_utf8()
+  }
+
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    let mut buffer = [0; 4];
+    let encoded = self.encode_utf8(&mut buffer);
+    bytes.extend_from_slice(encoded.as_bytes())
+  }
+}
+
+impl StringAppendableValue for &char {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    StringAppendableValue::byte_len(*self)
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    StringAppendableValue::push_to(*self, text)
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    StringAppendableValue::write_to_formatter(*self, fmt)
+  }
+}
+
+impl StringAppendableValue for std::fmt::Arguments<'_> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    match self.as_str() {
+      Some(s) => s.len(),
+      None => macro_support::DisplayValue(self).byte_len(),
+    }
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    match self.as_str() {
+      Some(s) => text.push_str(s),
+      None => macro_support::DisplayValue(self).push_to(text),
+    }
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    fmt.write_fmt(*self)
+  }
+
+  #[inline(always)]
+  fn into_borrowed_str<'b>(self) -> Result<&'b str, Self>
+  where
+    Self: Sized + 'b,
+  {
+    self.as_str().ok_or(self)
+  }
+}
+
+impl StringAppendableValue for &[char] {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.iter().map(|c| c.len_utf8()).sum()
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    for c in self.iter() {
+      text.push(*c);
+    }
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    for c in self.iter() {
+      fmt.write_char(*c)?;
+    }
+    Ok(())
+  }
+}
+
+impl<T: StringAppendableValue> StringAppendableValue for Option<T> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    match self {
+      Some(value) => value.byte_len(),
+      None => 0,
+    }
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    if let Some(value) = self {
+      value.push_to(text);
+    }
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    if let Some(value) = self {
+      value.write_to_formatter(fmt)
+    } else {
+      Ok(())
+    }
+  }
+}
+
+impl<T: BytesAppendableValue> BytesAppendableValue for Option<T> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    match self {
+      Some(value) => value.byte_len(),
+      None => 0,
+    }
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    if let Some(value) = self {
+      value.push_to(bytes);
+    }
+  }
+}
+
+impl BytesAppendableValue for &Vec<u8> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.len()
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    bytes.extend_from_slice(self)
+  }
+}
+
+impl BytesAppendableValue for u8 {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    1
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    bytes.push(*self)
+  }
+}
+
+impl BytesAppendableValue for &u8 {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    1
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    bytes.push(**self)
+  }
+}
+
+impl BytesAppendableValue for [u8] {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.len()
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    bytes.extend_from_slice(self)
   }
 }
 
@@ -522,14 +1148,61 @@ impl<const N: usize> BytesAppendableValue for &[u8; N] {
 
 enum Mode<'a, TStringMut> {
   Capacity,
+  /// The capacity pass of `build_cow`.
+  CowCapacity(CowState<'a>),
+  Segments(&'a mut segments::Segments<'a>),
+  /// Writes the text to a sink that doesn't keep it (ex. comparing).
+  Sink(&'a mut dyn StringTypeMut),
   Text(&'a mut TStringMut),
   Format(&'a mut std::fmt::Formatter<'a>),
   FormatError(std::fmt::Error),
 }
 
+/// Whether only a single borrowed string was appended.
+#[derive(Clone, Copy)]
+enum CowState<'a> {
+  Empty,
+  Borrowed(&'a str),
+  Owned,
+}
+
 pub struct StringBuilder<'a, TString: StringType = String> {
   capacity: usize,
   mode: Mode<'a, TString::MutType>,
+  /// Values of `append_owned` built on the capacity pass.
+  owned: VecDeque<String>,
+  /// The capacity passes of each call to `sections`.
+  #[cfg(feature = "rayon")]
+  section_plans: VecDeque<Vec<sink::SinkPlan>>,
+  /// Text of `append_cached` written on the capacity pass, shared by all
+  /// the cached appends.
+  cached: String,
+  /// The lengths of the cached text not yet appended on the next pass.
+  cached_lens: VecDeque<usize>,
+  /// The offset in `cached` of the next cached text to append.
+  cached_pos: usize,
+  /// The largest length reached on the capacity pass before rolling
+  /// back, which is reserved so the second pass doesn't reallocate.
+  peak: usize,
+  /// The lengths truncated to and the indexes inserted at, used to know
+  /// which text is final when writing to a sink.
+  edits: Vec<usize>,
+  /// The length before the append that first grew the text past the
+  /// reserved capacity on the second pass.
+  overflow: Option<usize>,
+  /// The length of the text prepended so far when writing.
+  prepended: usize,
+  /// The first error that occurred in the closure (ex. allocating the
+  /// buffer of `sections`), which is returned by the build.
+  error: Option<Error>,
+  /// Capacity to reserve in addition to the built length.
+  additional: usize,
+  /// Whether sizes that don't match aren't debug asserted (see
+  /// `build_estimate` and `build_checked`).
+  lenient: bool,
+  /// The number of appends across both passes.
+  #[cfg(feature = "tracing")]
+  appends: usize,
 }
 
 impl<'a> StringBuilder<'a, String> {
@@ -537,41 +1210,204 @@ impl<'a> StringBuilder<'a, String> {
   ///
   /// If an error occurs, the error is stored and surfaced
   /// at the end. The remaining `append` calls are then ignored.
+  ///
+  /// Panics when text is truncated or inserted, as written text can't be
+  /// changed. Use `fmt_with_edits` for that instead.
   #[inline(always)]
   pub fn fmt(
     fmt: &mut std::fmt::Formatter<'_>,
     build: impl FnOnce(&mut StringBuilder<'a, String>),
   ) -> std::fmt::Result {
-    let mut state = StringBuilder {
+    let mut state = StringBuilder::with_mode(
       // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
-      mode: Mode::Format(unsafe {
+      Mode::Format(unsafe {
         std::mem::transmute::<
           &mut std::fmt::Formatter<'_>,
           &mut std::fmt::Formatter<'_>,
         >(fmt)
       }),
-      capacity: 0,
-    };
+    );
     build(&mut state);
+    if state.error.is_some() {
+      return Err(std::fmt::Error);
+    }
     match state.mode {
       Mode::Format(_) => Ok(()),
       Mode::FormatError(error) => Err(error),
-      Mode::Capacity | Mode::Text(_) => unreachable!(),
+      Mode::Capacity
+      | Mode::CowCapacity(_)
+      | Mode::Text(_)
+      | Mode::Segments(_)
+      | Mode::Sink(_) => unreachable!(),
+    }
+  }
+
+  /// Builds the string into the provided buffer, clearing it first and
+  /// only reserving when its capacity is too small.
+  pub fn build_with_buffer<'b>(
+    buffer: &'b mut String,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> Result<&'b str, Error> {
+    buffer.clear();
+    let mut state = StringBuilder::with_mode(Mode::Capacity);
+    build(&mut state);
+    buffer
+      .try_reserve_exact(state.alloc_capacity())
+      .map_err(Error::reserve(state.alloc_capacity()))?;
+    // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+    state.mode = Mode::Text(unsafe {
+      std::mem::transmute::<&mut String, &mut String>(&mut *buffer)
+    });
+    build(&mut state);
+    state.check_error()?;
+    mismatch::check_with_offset(state.capacity, buffer.len(), state.overflow);
+    debug_assert_eq!(state.capacity, buffer.len());
+    Ok(buffer.as_str())
+  }
+
+  /// Builds the string, but borrows instead of allocating when the
+  /// closure only appended a single borrowed string.
+  pub fn build_cow(
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> Result<Cow<'a, str>, Error> {
+    let mut state =
+      StringBuilder::with_mode(Mode::CowCapacity(CowState::Empty));
+    build(&mut state);
+    match state.mode {
+      Mode::CowCapacity(CowState::Empty) => return Ok(Cow::Borrowed("")),
+      Mode::CowCapacity(CowState::Borrowed(text)) => {
+        return Ok(Cow::Borrowed(text))
+      }
+      _ => {}
+    }
+    let mut text = String::new();
+    text
+      .try_reserve_exact(state.alloc_capacity())
+      .map_err(Error::reserve(state.alloc_capacity()))?;
+    // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+    state.mode = Mode::Text(unsafe {
+      std::mem::transmute::<&mut String, &mut String>(&mut text)
+    });
+    build(&mut state);
+    state.check_error()?;
+    mismatch::check_with_offset(state.capacity, text.len(), state.overflow);
+    debug_assert_eq!(state.capacity, text.len());
+    Ok(Cow::Owned(text))
+  }
+
+  /// Builds the string running the closure only once.
+  ///
+  /// The appends are recorded as segments (see `build_segments`), which
+  /// borrow the appended strings and only buffer the rest (ex. numbers),
+  /// then concatenated into a string with the exact capacity. This is
+  /// useful when the closure is expensive to run.
+  pub fn build_once(
+    build: impl FnOnce(&mut StringBuilder<'a, String>),
+  ) -> Result<String, Error> {
+    let segments = Self::build_segments(build);
+    let mut text = String::new();
+    text
+      .try_reserve_exact(segments.len())
+      .map_err(Error::reserve(segments.len()))?;
+    for segment in segments.iter() {
+      text.push_str(segment);
     }
+    Ok(text)
   }
 }
 
+/// Allocates the string output, erroring when the capacity exceeds its
+/// maximum.
+fn alloc_string<TString: StringType>(
+  size: usize,
+) -> Result<TString::MutType, Error> {
+  if size > TString::MAX_CAPACITY {
+    return Err(Error::CapacityExceeded {
+      size,
+      max: TString::MAX_CAPACITY,
+    });
+  }
+  TString::with_capacity(size).map_err(Error::reserve(size))
+}
+
+/// Allocates the bytes output, erroring when the capacity exceeds its
+/// maximum.
+fn alloc_bytes<TBytes: BytesType>(
+  size: usize,
+) -> Result<TBytes::MutType, Error> {
+  if size > TBytes::MAX_CAPACITY {
+    return Err(Error::CapacityExceeded {
+      size,
+      max: TBytes::MAX_CAPACITY,
+    });
+  }
+  TBytes::with_capacity(size).map_err(Error::reserve(size))
+}
+
 impl<'a, TString: StringType> StringBuilder<'a, TString> {
+  #[inline(always)]
+  fn with_mode(mode: Mode<'a, TString::MutType>) -> Self {
+    StringBuilder {
+      capacity: 0,
+      mode,
+      owned: VecDeque::new(),
+      #[cfg(feature = "rayon")]
+      section_plans: VecDeque::new(),
+      cached: String::new(),
+      cached_lens: VecDeque::new(),
+      cached_pos: 0,
+      peak: 0,
+      edits: Vec::new(),
+      overflow: None,
+      prepended: 0,
+      error: None,
+      additional: 0,
+      lenient: false,
+      #[cfg(feature = "tracing")]
+      appends: 0,
+    }
+  }
+
+  /// The capacity to allocate after the capacity pass.
+  #[inline(always)]
+  fn alloc_capacity(&self) -> usize {
+    std::cmp::max(self.capacity, self.peak) + self.additional
+  }
+
+  /// Records an error to return from the build.
+  #[cfg(feature = "rayon")]
+  fn set_error(&mut self, error: Error) {
+    if self.error.is_none() {
+      self.error = Some(error);
+    }
+  }
+
+  /// Returns the error that occurred in the closure.
+  #[inline(always)]
+  fn check_error(&mut self) -> Result<(), Error> {
+    match self.error.take() {
+      Some(error) => Err(error),
+      None => Ok(()),
+    }
+  }
+
+  /// Records where the text first grew past the reserved capacity.
+  #[inline(always)]
+  fn track_overflow(&mut self, start: usize, end: usize) {
+    if end > self.alloc_capacity() && self.overflow.is_none() {
+      self.overflow = Some(start);
+    }
+  }
+
   #[inline(always)]
   pub fn build(
     build: impl Fn(&mut StringBuilder<'a, TString>),
-  ) -> Result<TString, TryReserveError> {
-    let mut state = StringBuilder {
-      mode: Mode::Capacity,
-      capacity: 0,
-    };
+  ) -> Result<TString, Error> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::trace_span!("StringBuilder::build").entered();
+    let mut state = StringBuilder::with_mode(Mode::Capacity);
     build(&mut state);
-    let mut text = TString::with_capacity(state.capacity)?;
+    let mut text = alloc_string::<TString>(state.alloc_capacity())?;
     // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
     state.mode = Mode::Text(unsafe {
       std::mem::transmute::<
@@ -580,10 +1416,140 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
       >(&mut text)
     });
     build(&mut state);
+    state.check_error()?;
+    #[cfg(feature = "tracing")]
+    trace_build(state.capacity, text.len(), state.appends / 2);
+    mismatch::check_with_offset(state.capacity, text.len(), state.overflow);
     debug_assert_eq!(state.capacity, text.len());
     Ok(TString::from_mut(text))
   }
 
+  /// Builds the string with a capacity that's already known, running the
+  /// closure only once.
+  ///
+  /// In debug mode this will panic when the capacity does not equal the
+  /// built length.
+  pub fn build_with_capacity(
+    capacity: usize,
+    build: impl FnOnce(&mut StringBuilder<'a, TString>),
+  ) -> Result<TString, Error> {
+    let mut text = alloc_string::<TString>(capacity)?;
+    let mut state = StringBuilder::with_mode(
+      // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+      Mode::Text(unsafe {
+        std::mem::transmute::<
+          &mut <TString as StringType>::MutType,
+          &mut <TString as StringType>::MutType,
+        >(&mut text)
+      }),
+    );
+    state.capacity = capacity;
+    build(&mut state);
+    state.check_error()?;
+    mismatch::check_with_offset(capacity, text.len(), state.overflow);
+    debug_assert_eq!(
+      capacity,
+      text.len(),
+      "build_with_capacity used where the capacity was not equal to the built length"
+    );
+    Ok(TString::from_mut(text))
+  }
+
+  /// Builds the string, returning an error instead of debug asserting
+  /// when the built length doesn't equal the calculated capacity (ex.
+  /// when appending untrusted appendables).
+  pub fn build_checked(
+    build: impl Fn(&mut StringBuilder<'a, TString>),
+  ) -> Result<TString, Error> {
+    let mut state = StringBuilder::with_mode(Mode::Capacity);
+    state.lenient = true;
+    build(&mut state);
+    let capacity = state.alloc_capacity();
+    let mut text = alloc_string::<TString>(capacity)?;
+    // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+    state.mode = Mode::Text(unsafe {
+      std::mem::transmute::<
+        &mut <TString as StringType>::MutType,
+        &mut <TString as StringType>::MutType,
+      >(&mut text)
+    });
+    build(&mut state);
+    state.check_error()?;
+    if state.capacity != text.len() {
+      return Err(Error::CapacityMismatch(mismatch::CapacityMismatch {
+        capacity: state.capacity,
+        len: text.len(),
+        offset: state.overflow,
+      }));
+    }
+    Ok(TString::from_mut(text))
+  }
+
+  /// Builds the string treating the sizes of the appended values as upper
+  /// bounds, which is useful for values that are cheaper to bound than to
+  /// measure exactly (ex. lossy conversions or escaping).
+  ///
+  /// The built string may have more capacity than its length, so call
+  /// `shrink_to_fit` on it afterwards if that matters.
+  pub fn build_estimate(
+    build: impl Fn(&mut StringBuilder<'a, TString>),
+  ) -> Result<TString, Error> {
+    let mut state = StringBuilder::with_mode(Mode::Capacity);
+    state.lenient = true;
+    build(&mut state);
+    let mut text = alloc_string::<TString>(state.alloc_capacity())?;
+    // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+    state.mode = Mode::Text(unsafe {
+      std::mem::transmute::<
+        &mut <TString as StringType>::MutType,
+        &mut <TString as StringType>::MutType,
+      >(&mut text)
+    });
+    build(&mut state);
+    state.check_error()?;
+    Ok(TString::from_mut(text))
+  }
+
+  /// Builds a string and bytes from the same closure, which is useful
+  /// when producing related text and binary output from the same data.
+  pub fn build_with_bytes<TBytes: BytesType>(
+    build: impl Fn(&mut StringBuilder<'a, TString>, &mut BytesBuilder<'a, TBytes>),
+  ) -> Result<(TString, TBytes), Error>
+  where
+    <TBytes as BytesType>::MutType: 'a,
+  {
+    let mut state = StringBuilder::with_mode(Mode::Capacity);
+    let mut bytes_state = BytesBuilder::with_bytes(None);
+    build(&mut state, &mut bytes_state);
+    let mut text = alloc_string::<TString>(state.alloc_capacity())?;
+    let mut bytes = alloc_bytes::<TBytes>(bytes_state.capacity)?;
+    // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+    state.mode = Mode::Text(unsafe {
+      std::mem::transmute::<
+        &mut <TString as StringType>::MutType,
+        &mut <TString as StringType>::MutType,
+      >(&mut text)
+    });
+    // SAFETY: same as above
+    bytes_state.bytes = Some(unsafe {
+      std::mem::transmute::<
+        &mut <TBytes as BytesType>::MutType,
+        &mut <TBytes as BytesType>::MutType,
+      >(&mut bytes)
+    });
+    build(&mut state, &mut bytes_state);
+    state.check_error()?;
+    mismatch::check_with_offset(state.capacity, text.len(), state.overflow);
+    mismatch::check_with_offset(
+      bytes_state.capacity,
+      bytes.len(),
+      bytes_state.overflow,
+    );
+    debug_assert_eq!(state.capacity, text.len());
+    debug_assert_eq!(bytes_state.capacity, bytes.len());
+    Ok((TString::from_mut(text), TBytes::from_mut(bytes)))
+  }
+
   /// Gets the current length of the builder.
   ///
   /// On the first pass this will be the current calculated capacity and
@@ -592,13 +1558,107 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
   pub fn len(&self) -> usize {
     match &self.mode {
       Mode::Text(t) => t.len(),
-      Mode::Capacity | Mode::Format(_) | Mode::FormatError(_) => self.capacity,
+      Mode::Segments(segments) => segments.len(),
+      Mode::Sink(sink) => sink.len(),
+      Mode::Capacity
+      | Mode::CowCapacity(_)
+      | Mode::Format(_)
+      | Mode::FormatError(_) => self.capacity,
     }
   }
 
   #[inline(always)]
   pub fn append(&mut self, value: impl StringAppendable<'a> + 'a) {
+    #[cfg(feature = "tracing")]
+    {
+      self.appends += 1;
+    }
+    value.append_to_builder(self);
+  }
+
+  /// Appends a value before everything that was appended, but after
+  /// previously prepended values.
+  ///
+  /// The prepended text is moved into place, which requires an output
+  /// that supports moving text (ex. `String`). When writing to a sink (ex.
+  /// `matches`), the text after the insertion is held back until it's in
+  /// place. Panics when formatting with `fmt` (see `fmt_with_edits`) or
+  /// hashing with `hash_into`.
+  pub fn prepend(&mut self, value: impl StringAppendable<'a> + 'a) {
+    let len = self.insert_value(self.prepended, value);
+    self.prepended += len;
+  }
+
+  /// Creates a marker at the current offset.
+  pub fn mark(&self) -> Marker {
+    Marker(self.len())
+  }
+
+  /// Inserts a value at a marker created earlier (ex. to write a summary
+  /// above the details it summarizes).
+  ///
+  /// Markers created after the marker aren't updated to include the
+  /// inserted length. See `prepend` for the supported outputs.
+  pub fn insert_at(
+    &mut self,
+    marker: Marker,
+    value: impl StringAppendable<'a> + 'a,
+  ) {
+    let len = self.insert_value(marker.0, value);
+    if marker.0 < self.prepended {
+      self.prepended += len;
+    }
+  }
+
+  /// Appends the value and moves it to the index when writing, returning
+  /// the inserted length.
+  fn insert_value(
+    &mut self,
+    index: usize,
+    value: impl StringAppendable<'a> + 'a,
+  ) -> usize {
+    let start = self.len();
     value.append_to_builder(self);
+    match &mut self.mode {
+      Mode::Text(t) => t.move_to(start, index),
+      Mode::Segments(segments) => segments.move_to(start, index),
+      Mode::Sink(sink) => {
+        sink.move_to(start, index);
+        self.edits.push(index);
+      }
+      Mode::Capacity | Mode::CowCapacity(_) => {
+        // the prepended length isn't tracked on the capacity pass, so
+        // prepends are recorded at the start
+        self.edits.push(index);
+        return 0;
+      }
+      Mode::FormatError(_) => return 0,
+      Mode::Format(_) => {
+        panic!(
+          "inserting is not supported when formatting, use fmt_with_edits"
+        );
+      }
+    }
+    self.len() - start
+  }
+
+  /// Appends an integer with a leading `+` when it's not negative, like
+  /// `{:+}`.
+  #[inline(always)]
+  pub fn append_signed(&mut self, value: impl Integer) {
+    if !value.is_negative() {
+      self.append_value('+');
+    }
+    self.append_value(value);
+  }
+
+  /// Appends formatting arguments, ex. `builder.append_fmt(format_args!(...))`.
+  ///
+  /// The arguments are formatted to a counting writer on the first pass.
+  /// Unlike `append`, the arguments only need to live for the call.
+  #[inline(always)]
+  pub fn append_fmt(&mut self, args: std::fmt::Arguments<'_>) {
+    self.append_value(args);
   }
 
   pub fn append_with_replace(&mut self, value: &'a str, from: &str, to: &str) {
@@ -606,12 +1666,9 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
       if from.len() == to.len() {
         value.len()
       } else {
-        let count = value.match_indices(value).count();
-        if to.len() > from.len() {
-          value.len() + count * (to.len() - from.len())
-        } else {
-          value.len() - count * (from.len() - to.len())
-        }
+        // matches don't overlap, so removing them can't underflow
+        let count = value.match_indices(from).count();
+        value.len() - count * from.len() + count * to.len()
       }
     }
 
@@ -623,12 +1680,11 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
     ) -> Result<usize, std::fmt::Error> {
       let mut start = 0;
       let mut size = 0;
-      while let Some(pos) = value[start..].find(from) {
-        let end_pos = start + pos;
-        formatter.write_str(&value[start..end_pos])?;
+      for (pos, matched) in value.match_indices(from) {
+        formatter.write_str(&value[start..pos])?;
         formatter.write_str(to)?;
-        size += pos + to.len();
-        start += pos + from.len();
+        size += pos - start + to.len();
+        start = pos + matched.len();
       }
       let remaining = &value[start..];
       formatter.write_str(remaining)?;
@@ -636,16 +1692,25 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
       Ok(size)
     }
 
-    match &mut self.mode {
-      Mode::Text(buffer) => {
-        let mut start = 0;
-        while let Some(pos) = value[start..].find(from) {
-          buffer.push_str(&value[start..start + pos]);
-          buffer.push_str(to);
-          start += pos + from.len();
-        }
-        buffer.push_str(&value[start..]);
+    fn push_with_replace(
+      buffer: &mut impl StringTypeMut,
+      value: &str,
+      from: &str,
+      to: &str,
+    ) {
+      let mut start = 0;
+      for (pos, matched) in value.match_indices(from) {
+        buffer.push_str(&value[start..pos]);
+        buffer.push_str(to);
+        start = pos + matched.len();
       }
+      buffer.push_str(&value[start..]);
+    }
+
+    match &mut self.mode {
+      Mode::Text(buffer) => push_with_replace(*buffer, value, from, to),
+      Mode::Segments(segments) => push_with_replace(*segments, value, from, to),
+      Mode::Sink(sink) => push_with_replace(sink, value, from, to),
       Mode::Format(formatter) => {
         match format_with_replace(formatter, value, from, to) {
           Ok(size) => self.capacity += size,
@@ -654,16 +1719,147 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
             // to an error state, storing the error to be surfaced
             // later and don't bother formatting the remaining bytes
             self.mode = Mode::FormatError(e);
-            self.capacity = calculate_capacity(value, from, to);
+            self.capacity += calculate_capacity(value, from, to);
           }
         }
       }
+      Mode::CowCapacity(state) => {
+        *state = CowState::Owned;
+        self.capacity += calculate_capacity(value, from, to);
+      }
       Mode::Capacity | Mode::FormatError(_) => {
         self.capacity += calculate_capacity(value, from, to);
       }
     }
   }
 
+  /// Gets a checkpoint of the current length that can later be rolled
+  /// back to (ex. to discard a section that ended up empty).
+  #[inline(always)]
+  pub fn checkpoint(&self) -> Checkpoint {
+    Checkpoint(self.len())
+  }
+
+  /// Discards everything appended since the checkpoint.
+  ///
+  /// See `truncate_to`.
+  #[inline(always)]
+  pub fn rollback(&mut self, checkpoint: Checkpoint) {
+    self.truncate_to(checkpoint.0);
+  }
+
+  /// Shortens the output to the provided byte length, which must be on a
+  /// char boundary.
+  ///
+  /// The capacity pass reserves the length reached before truncating,
+  /// so this never reallocates. When writing to a sink (ex. `matches`),
+  /// the text that gets removed is held back instead of written. Panics
+  /// when formatting with `fmt` (see `fmt_with_edits`) or hashing with
+  /// `hash_into`.
+  pub fn truncate_to(&mut self, len: usize) {
+    match &mut self.mode {
+      Mode::Text(t) => t.truncate(len),
+      Mode::Segments(segments) => segments.truncate(len),
+      Mode::Sink(sink) => {
+        sink.truncate(len);
+        self.edits.push(sink.len());
+      }
+      Mode::Capacity => {
+        self.peak = std::cmp::max(self.peak, self.capacity);
+        self.capacity = std::cmp::min(self.capacity, len);
+        self.edits.push(self.capacity);
+      }
+      Mode::CowCapacity(state) => {
+        if len == 0 {
+          *state = CowState::Empty;
+        }
+        self.peak = std::cmp::max(self.peak, self.capacity);
+        self.capacity = std::cmp::min(self.capacity, len);
+        self.edits.push(self.capacity);
+      }
+      Mode::Format(_) | Mode::FormatError(_) => {
+        panic!(
+          "truncating is not supported when formatting, use fmt_with_edits"
+        );
+      }
+    }
+  }
+
+  /// Reserves capacity for `additional` more bytes after the built text
+  /// without appending anything (ex. when it will be pushed to after
+  /// building).
+  #[inline(always)]
+  pub fn reserve_additional(&mut self, additional: usize) {
+    if matches!(self.mode, Mode::Capacity | Mode::CowCapacity(_)) {
+      self.additional += additional;
+    }
+  }
+
+  /// Removes the last `len` bytes (ex. a trailing separator).
+  ///
+  /// See `truncate_to`.
+  #[inline(always)]
+  pub fn pop_last(&mut self, len: usize) {
+    self.truncate_to(self.len().saturating_sub(len));
+  }
+
+  /// Appends an owned value, building it once on the capacity pass and
+  /// storing it in the builder until it's appended on the next pass.
+  ///
+  /// Prefer this over `append_owned_unsafe` unless the size is cheap to
+  /// know upfront, since it avoids building the value twice without the
+  /// risk of getting the size wrong.
+  pub fn append_owned(&mut self, build: impl FnOnce() -> String) {
+    match &self.mode {
+      Mode::Capacity | Mode::CowCapacity(_) => {
+        let text = build();
+        self.append_owned_unsafe(text.len(), || "");
+        self.owned.push_back(text);
+      }
+      _ => {
+        let text = self.owned.pop_front().unwrap_or_else(build);
+        self.append_owned_unsafe(text.len(), || text);
+      }
+    }
+  }
+
+  /// Appends text written to a scratch buffer on the capacity pass, which
+  /// is reused on the next pass instead of writing it again.
+  ///
+  /// This is like `append_owned`, but the text of all the cached appends
+  /// is stored in one buffer, so it only allocates when the buffer grows.
+  pub fn append_cached(&mut self, write: impl FnOnce(&mut String)) {
+    match &self.mode {
+      Mode::Capacity | Mode::CowCapacity(_) => {
+        let start = self.cached.len();
+        write(&mut self.cached);
+        let len = self.cached.len() - start;
+        self.cached_lens.push_back(len);
+        self.append_owned_unsafe(len, || "");
+      }
+      _ => {
+        let mut cached = std::mem::take(&mut self.cached);
+        let cached_len = cached.len();
+        let range = match self.cached_lens.pop_front() {
+          Some(len) => {
+            let start = self.cached_pos;
+            self.cached_pos += len;
+            start..self.cached_pos
+          }
+          // nothing was cached (ex. a single pass), so write it temporarily
+          // after the cached text
+          None => {
+            write(&mut cached);
+            cached_len..cached.len()
+          }
+        };
+        self.append_owned_unsafe(range.len(), || &cached[range.clone()]);
+        cached.truncate(cached_len);
+        self.cached = cached;
+      }
+    }
+  }
+
   /// Appends an owned value whose size is known on the first pass.
   ///
   /// WARNING: Be very careful using this as you might accidentally cause
@@ -677,10 +1873,19 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
     match &mut self.mode {
       Mode::Text(t) => {
         let text = build();
-        debug_assert_eq!(text.as_ref().len(), size, "append_owned used where size was not equal! This will cause a reallocation in release mode.");
+        debug_assert!(self.lenient || text.as_ref().len() == size, "append_owned used where size was not equal! This will cause a reallocation in release mode.");
+        let start = t.len();
         t.push_str(text.as_ref());
+        let end = t.len();
+        self.track_overflow(start, end);
       }
+      Mode::Segments(segments) => segments.push_str(build().as_ref()),
+      Mode::Sink(sink) => sink.push_str(build().as_ref()),
       Mode::Capacity => self.capacity += size,
+      Mode::CowCapacity(state) => {
+        *state = CowState::Owned;
+        self.capacity += size;
+      }
       Mode::Format(formatter) => {
         let text = build();
         let result = formatter.write_str(text.as_ref());
@@ -692,18 +1897,111 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
         }
         self.capacity += size;
       }
-      Mode::FormatError(_) => {
-        // keep setting the capacity in case the remaining
-        // code relies on this
-        self.capacity += size;
+      Mode::FormatError(_) => {
+        // keep setting the capacity in case the remaining
+        // code relies on this
+        self.capacity += size;
+      }
+    }
+  }
+
+  /// Appends an owned value whose size is known on the first pass,
+  /// returning an error instead of debug asserting when the built value
+  /// has a different size.
+  ///
+  /// The value isn't appended when it has a different size.
+  pub fn try_append_owned<TStringRef: AsRef<str>>(
+    &mut self,
+    size: usize,
+    build: impl FnOnce() -> TStringRef,
+  ) -> Result<(), Error> {
+    match &self.mode {
+      Mode::Capacity | Mode::CowCapacity(_) | Mode::FormatError(_) => {
+        self.append_owned_unsafe(size, || "");
+        Ok(())
+      }
+      _ => {
+        let text = build();
+        let len = text.as_ref().len();
+        if len != size {
+          return Err(Error::CapacityMismatch(mismatch::CapacityMismatch {
+            capacity: size,
+            len,
+            offset: None,
+          }));
+        }
+        self.append_owned_unsafe(size, || text);
+        Ok(())
+      }
+    }
+  }
+
+  /// Appends an owned value whose size is only a hint on the first pass.
+  ///
+  /// When the built value has a different size, it's still appended and
+  /// the expected length is adjusted, which may cause a reallocation.
+  pub fn append_owned_hint<TStringRef: AsRef<str>>(
+    &mut self,
+    size_hint: usize,
+    build: impl FnOnce() -> TStringRef,
+  ) {
+    match &mut self.mode {
+      Mode::Text(t) => {
+        let text = build();
+        let text = text.as_ref();
+        t.push_str(text);
+        self.capacity = (self.capacity + text.len()).saturating_sub(size_hint);
+      }
+      Mode::Format(_) => {
+        let text = build();
+        let len = text.as_ref().len();
+        self.append_owned_unsafe(len, || text);
+      }
+      _ => self.append_owned_unsafe(size_hint, build),
+    }
+  }
+
+  fn append_borrowable_value(
+    &mut self,
+    value: impl StringAppendableValue + 'a,
+  ) {
+    if let Mode::CowCapacity(state) = &mut self.mode {
+      let len = value.byte_len();
+      if len > 0 {
+        *state = match (*state, value.into_borrowed_str()) {
+          (CowState::Empty, Ok(text)) => CowState::Borrowed(text),
+          _ => CowState::Owned,
+        };
       }
+      self.capacity += len;
+    } else if let Mode::Segments(segments) = &mut self.mode {
+      match value.into_borrowed_str() {
+        Ok(text) => segments.push_borrowed(text),
+        Err(value) => value.push_to(*segments),
+      }
+    } else {
+      self.append_value(value);
     }
   }
 
   fn append_value(&mut self, value: impl StringAppendableValue) {
     match &mut self.mode {
-      Mode::Text(t) => value.push_to(*t),
+      Mode::Text(t) => {
+        let start = t.len();
+        value.push_to(*t);
+        let end = t.len();
+        self.track_overflow(start, end);
+      }
+      Mode::Segments(segments) => value.push_to(*segments),
+      Mode::Sink(sink) => value.push_to(sink),
       Mode::Capacity => self.capacity += value.byte_len(),
+      Mode::CowCapacity(state) => {
+        let len = value.byte_len();
+        if len > 0 {
+          *state = CowState::Owned;
+        }
+        self.capacity += len;
+      }
       Mode::Format(formatter) => {
         let result = value.write_to_formatter(formatter);
         if let Err(e) = result {
@@ -723,32 +2021,453 @@ impl<'a, TString: StringType> StringBuilder<'a, TString> {
   }
 }
 
-/// Helper method for converting an appendable value to a string.
-pub fn appendable_to_string<'a, TString: StringType>(
-  value: impl StringAppendable<'a> + Copy + 'a,
-) -> TString
+impl<'a, TString: StringType> Builder<'a> for StringBuilder<'a, TString> {
+  #[inline(always)]
+  fn append_str(&mut self, value: &'a str) {
+    self.append(value);
+  }
+
+  #[inline(always)]
+  fn append_char(&mut self, value: char) {
+    self.append(value);
+  }
+
+  #[inline(always)]
+  fn append_int(&mut self, value: impl Integer) {
+    self.append_value(value);
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    StringBuilder::len(self)
+  }
+}
+
+impl<'a, TString: StringType> Extend<&'a str> for StringBuilder<'a, TString> {
+  #[inline(always)]
+  fn extend<T: IntoIterator<Item = &'a str>>(&mut self, iter: T) {
+    for value in iter {
+      self.append(value);
+    }
+  }
+}
+
+impl<TString: StringType> Extend<char> for StringBuilder<'_, TString> {
+  #[inline(always)]
+  fn extend<T: IntoIterator<Item = char>>(&mut self, iter: T) {
+    for value in iter {
+      self.append(value);
+    }
+  }
+}
+
+/// Builds several strings from the same closure, tracking the capacity
+/// of each output separately.
+pub struct MultiBuilder<'a, const N: usize, TString: StringType = String> {
+  builders: [StringBuilder<'a, TString>; N],
+}
+
+impl<'a, const N: usize, TString: StringType> MultiBuilder<'a, N, TString> {
+  pub fn build(
+    build: impl Fn(&mut MultiBuilder<'a, N, TString>),
+  ) -> Result<[TString; N], Error> {
+    let mut state = MultiBuilder {
+      builders: std::array::from_fn(|_| {
+        StringBuilder::with_mode(Mode::Capacity)
+      }),
+    };
+    build(&mut state);
+    let mut texts = Vec::with_capacity(N);
+    for builder in &state.builders {
+      let capacity = builder.alloc_capacity();
+      texts.push(alloc_string::<TString>(capacity)?);
+    }
+    for (builder, text) in state.builders.iter_mut().zip(texts.iter_mut()) {
+      // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+      builder.mode = Mode::Text(unsafe {
+        std::mem::transmute::<
+          &mut <TString as StringType>::MutType,
+          &mut <TString as StringType>::MutType,
+        >(text)
+      });
+    }
+    build(&mut state);
+    for builder in &mut state.builders {
+      builder.check_error()?;
+    }
+    for (builder, text) in state.builders.iter().zip(&texts) {
+      mismatch::check_with_offset(
+        builder.capacity,
+        text.len(),
+        builder.overflow,
+      );
+      debug_assert_eq!(builder.capacity, text.len());
+    }
+    let mut texts = texts.into_iter().map(TString::from_mut);
+    Ok(std::array::from_fn(|_| texts.next().unwrap()))
+  }
+
+  /// Gets the builder of the output at the provided index.
+  ///
+  /// Panics when the index is out of bounds.
+  #[inline(always)]
+  pub fn get(&mut self, index: usize) -> &mut StringBuilder<'a, TString> {
+    &mut self.builders[index]
+  }
+}
+
+/// Builds several byte outputs from the same closure, tracking the
+/// capacity of each output separately.
+pub struct MultiBytesBuilder<'a, const N: usize, TBytes: BytesType = Vec<u8>> {
+  builders: [BytesBuilder<'a, TBytes>; N],
+}
+
+impl<'a, const N: usize, TBytes: BytesType> MultiBytesBuilder<'a, N, TBytes> {
+  /// Builds the bytes of each output.
+  ///
+  /// An error reading from a reader (ex. `append_file`) is surfaced as
+  /// `Error::Io`.
+  pub fn build(
+    build: impl Fn(&mut MultiBytesBuilder<'a, N, TBytes>),
+  ) -> Result<[TBytes; N], Error> {
+    let mut state = MultiBytesBuilder {
+      builders: std::array::from_fn(|_| BytesBuilder::with_bytes(None)),
+    };
+    build(&mut state);
+    let mut outputs = Vec::with_capacity(N);
+    for builder in &state.builders {
+      outputs.push(alloc_bytes::<TBytes>(builder.capacity)?);
+    }
+    for (builder, bytes) in state.builders.iter_mut().zip(outputs.iter_mut()) {
+      // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+      builder.bytes = Some(unsafe {
+        std::mem::transmute::<
+          &mut <TBytes as BytesType>::MutType,
+          &mut <TBytes as BytesType>::MutType,
+        >(bytes)
+      });
+    }
+    build(&mut state);
+    for builder in &mut state.builders {
+      if let Some(err) = builder.io_error.take() {
+        return Err(Error::Io(err));
+      }
+    }
+    for (builder, bytes) in state.builders.iter().zip(&outputs) {
+      mismatch::check_with_offset(
+        builder.capacity,
+        bytes.len(),
+        builder.overflow,
+      );
+      debug_assert_eq!(builder.capacity, bytes.len());
+    }
+    let mut outputs = outputs.into_iter().map(TBytes::from_mut);
+    Ok(std::array::from_fn(|_| outputs.next().unwrap()))
+  }
+
+  /// Gets the builder of the output at the provided index.
+  ///
+  /// Panics when the index is out of bounds.
+  #[inline(always)]
+  pub fn get(&mut self, index: usize) -> &mut BytesBuilder<'a, TBytes> {
+    &mut self.builders[index]
+  }
+}
+
+/// Builds a `Vec<T>` with the exact capacity by running the closure once
+/// to count the pushed elements and again to push them.
+pub struct VecBuilder<T> {
+  capacity: usize,
+  items: Option<Vec<T>>,
+}
+
+impl<T> VecBuilder<T> {
+  #[inline(always)]
+  pub fn build(build: impl Fn(&mut VecBuilder<T>)) -> Result<Vec<T>, Error> {
+    let mut state = VecBuilder {
+      capacity: 0,
+      items: None,
+    };
+    build(&mut state);
+    let mut items = Vec::new();
+    items
+      .try_reserve_exact(state.capacity)
+      .map_err(Error::reserve(state.capacity))?;
+    state.items = Some(items);
+    build(&mut state);
+    let items = state.items.unwrap();
+    debug_assert_eq!(state.capacity, items.len());
+    Ok(items)
+  }
+
+  #[inline(always)]
+  pub fn push(&mut self, value: T) {
+    match &mut self.items {
+      Some(items) => items.push(value),
+      None => self.capacity += 1,
+    }
+  }
+
+  /// Pushes the value returned by the closure, which is only run when
+  /// filling the vector.
+  #[inline(always)]
+  pub fn push_with(&mut self, build: impl FnOnce() -> T) {
+    match &mut self.items {
+      Some(items) => items.push(build()),
+      None => self.capacity += 1,
+    }
+  }
+
+  #[inline(always)]
+  pub fn extend_from_slice(&mut self, values: &[T])
+  where
+    T: Clone,
+  {
+    match &mut self.items {
+      Some(items) => items.extend_from_slice(values),
+      None => self.capacity += values.len(),
+    }
+  }
+
+  /// Gets the current number of elements.
+  #[allow(clippy::len_without_is_empty)]
+  #[inline(always)]
+  pub fn len(&self) -> usize {
+    match &self.items {
+      Some(items) => items.len(),
+      None => self.capacity,
+    }
+  }
+}
+
+/// Builds a `Vec<u8>` from the provided appendable values, ex.
+/// `capacity_bytes![b"MAGIC", version.be(), payload, 0u8]`.
+///
+/// Integers must specify their byte order with `.be()` or `.le()`, except
+/// for `u8`. Note that the values are evaluated on both passes. Like
+/// `BytesBuilder::build`, this returns a `Result` that errors when
+/// allocating fails.
+#[macro_export]
+macro_rules! capacity_bytes {
+  ($($value:expr),+ $(,)?) => {
+    $crate::BytesBuilder::<Vec<u8>>::build(|builder| {
+      #[allow(unused_imports)]
+      use $crate::ByteOrderExt as _;
+      $(builder.append($value);)*
+    })
+  };
+}
+
+/// Calculates the capacity of string literals, chars, and integers at
+/// compile time, ex. `const CAPACITY: usize = const_capacity!("v", 1u32);`.
+///
+/// Provide the constant to `StringBuilder::build_with_capacity` to only run
+/// the closure once at runtime. Integer literals need a suffix.
+#[macro_export]
+macro_rules! const_capacity {
+  ($($value:expr),* $(,)?) => {
+    0 $(+ $crate::macro_support::ConstLen($value).byte_len())*
+  };
+}
+
+/// Concatenates constant strings into a `&'static str` at compile time,
+/// ex. `const USER_AGENT: &str = const_concat!(NAME, "/", VERSION);`.
+#[macro_export]
+macro_rules! const_concat {
+  ($($value:expr),* $(,)?) => {{
+    const LEN: usize = $crate::const_capacity!($($value),*);
+    const BYTES: [u8; LEN] = $crate::macro_support::concat_bytes::<LEN>(&[$($value),*]);
+    const TEXT: &str = match ::std::str::from_utf8(&BYTES) {
+      Ok(text) => text,
+      Err(_) => unreachable!(),
+    };
+    TEXT
+  }};
+}
+
+/// Converts an appendable to a string with the exact capacity.
+///
+/// The value is taken by reference so that any type whose reference is
+/// appendable can be converted.
+pub fn appendable_to_string<'a, TString: StringType, T>(value: &'a T) -> TString
 where
+  T: StringAppendableRef<'a> + ?Sized,
   <TString as StringType>::MutType: 'a,
 {
-  StringBuilder::<TString>::build(|builder| builder.append(value)).unwrap()
+  StringBuilder::<TString>::build(|builder| {
+    value.append_ref_to_builder(builder)
+  })
+  .unwrap()
+}
+
+/// Emits an event with the result of a build.
+#[cfg(feature = "tracing")]
+fn trace_build(capacity: usize, len: usize, appends: usize) {
+  tracing::trace!(
+    capacity,
+    len,
+    appends,
+    reallocated = len > capacity,
+    "built"
+  );
+}
+
+/// Gets the exact byte length of an appendable without building it.
+pub fn appendable_len<'a>(value: impl StringAppendable<'a> + 'a) -> usize {
+  let mut builder = StringBuilder::<'a, String>::with_mode(Mode::Capacity);
+  builder.append(value);
+  builder.capacity
+}
+
+/// Gets the exact byte length of a bytes appendable without building it.
+pub fn bytes_appendable_len<'a>(value: impl BytesAppendable<'a> + 'a) -> usize {
+  let mut builder = BytesBuilder::<'a, Vec<u8>>::with_bytes(None);
+  builder.append(value);
+  builder.capacity
+}
+
+/// Implements `std::fmt::Display` for any appendable, which is useful for
+/// types that can't derive `CapacityDisplay`.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayOf<'a, T: ?Sized>(pub &'a T);
+
+impl<'a, T: ?Sized> std::fmt::Display for DisplayOf<'a, T>
+where
+  &'a T: StringAppendable<'a>,
+{
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    StringBuilder::<String>::fmt(f, |builder| builder.append(self.0))
+  }
+}
+
+/// Implemented for types whose references are appendable.
+///
+/// This is used instead of a `&'a T: StringAppendable<'a>` bound in
+/// functions because that bound may overflow the trait solver before the
+/// type is inferred when a generic appendable type exists.
+pub trait StringAppendableRef<'a> {
+  fn append_ref_to_builder<TString: StringType>(
+    &'a self,
+    builder: &mut StringBuilder<'a, TString>,
+  );
+}
+
+impl<'a, T: ?Sized + 'a> StringAppendableRef<'a> for T
+where
+  &'a T: StringAppendable<'a>,
+{
+  #[inline(always)]
+  fn append_ref_to_builder<TString: StringType>(
+    &'a self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    builder.append(self)
+  }
+}
+
+/// A length of a `StringBuilder` to roll back to.
+///
+/// See `StringBuilder::checkpoint`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Checkpoint(usize);
+
+/// An offset into the output of a builder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Marker(usize);
+
+impl Marker {
+  /// The byte offset of the marker.
+  pub fn offset(&self) -> usize {
+    self.0
+  }
 }
 
 pub struct BytesBuilder<'a, TBytes: BytesType> {
   capacity: usize,
   bytes: Option<&'a mut TBytes::MutType>,
+  /// Lengths computed on the first pass that are needed up front on the
+  /// second pass (ex. length-prefixed sections and files) in the order
+  /// they were encountered.
+  section_lens: Vec<usize>,
+  section_index: usize,
+  markers: Vec<Marker>,
+  /// The first error that occurred reading from a reader.
+  io_error: Option<std::io::Error>,
+  /// The length before the append that first grew the bytes past the
+  /// capacity on the second pass.
+  overflow: Option<usize>,
+  /// Whether the bytes are streamed to a reader (see `stream`), where
+  /// readers can't be appended.
+  streaming: bool,
+  /// The number of appends across both passes.
+  #[cfg(feature = "tracing")]
+  appends: usize,
+}
+
+struct BuildResult<TBytesMut> {
+  bytes: TBytesMut,
+  markers: Vec<Marker>,
+  io_error: Option<std::io::Error>,
+}
+
+impl<TBytesMut> BuildResult<TBytesMut> {
+  /// Surfaces the error that occurred reading from a reader.
+  fn check_io(self) -> Result<Self, Error> {
+    match self.io_error {
+      Some(err) => Err(Error::Io(err)),
+      None => Ok(self),
+    }
+  }
 }
 
 impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
+  #[inline(always)]
+  fn with_bytes(bytes: Option<&'a mut TBytes::MutType>) -> Self {
+    BytesBuilder {
+      capacity: 0,
+      bytes,
+      section_lens: Vec::new(),
+      section_index: 0,
+      markers: Vec::new(),
+      io_error: None,
+      overflow: None,
+      streaming: false,
+      #[cfg(feature = "tracing")]
+      appends: 0,
+    }
+  }
+
+  /// Builds the bytes.
+  ///
+  /// An error reading from a reader (ex. `append_file`) is surfaced as
+  /// `Error::Io`.
   #[inline(always)]
   pub fn build(
     build: impl Fn(&mut BytesBuilder<'a, TBytes>),
-  ) -> Result<TBytes, TryReserveError> {
-    let mut builder = BytesBuilder {
-      bytes: None,
-      capacity: 0,
-    };
+  ) -> Result<TBytes, Error> {
+    let result = Self::build_mut(build)?.check_io()?;
+    Ok(TBytes::from_mut(result.bytes))
+  }
+
+  /// Builds the bytes and additionally returns the markers
+  /// created via `mark()` in the order they were created.
+  #[inline(always)]
+  pub fn build_with_markers(
+    build: impl Fn(&mut BytesBuilder<'a, TBytes>),
+  ) -> Result<(TBytes, Vec<Marker>), Error> {
+    let result = Self::build_mut(build)?.check_io()?;
+    Ok((TBytes::from_mut(result.bytes), result.markers))
+  }
+
+  #[inline(always)]
+  fn build_mut(
+    build: impl Fn(&mut BytesBuilder<'a, TBytes>),
+  ) -> Result<BuildResult<TBytes::MutType>, Error> {
+    #[cfg(feature = "tracing")]
+    let _span = tracing::trace_span!("BytesBuilder::build").entered();
+    let mut builder = BytesBuilder::with_bytes(None);
     build(&mut builder);
-    let mut bytes = TBytes::with_capacity(builder.capacity)?;
+    let mut bytes = alloc_bytes::<TBytes>(builder.capacity)?;
     // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
     builder.bytes = Some(unsafe {
       std::mem::transmute::<
@@ -757,8 +2476,25 @@ impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
       >(&mut bytes)
     });
     build(&mut builder);
+    #[cfg(feature = "tracing")]
+    trace_build(
+      builder.capacity,
+      builder.bytes.as_ref().unwrap().len(),
+      builder.appends / 2,
+    );
+    mismatch::check_with_offset(
+      builder.capacity,
+      builder.bytes.as_ref().unwrap().len(),
+      builder.overflow,
+    );
     debug_assert_eq!(builder.capacity, builder.bytes.as_ref().unwrap().len());
-    Ok(TBytes::from_mut(bytes))
+    let markers = std::mem::take(&mut builder.markers);
+    let io_error = builder.io_error.take();
+    Ok(BuildResult {
+      bytes,
+      markers,
+      io_error,
+    })
   }
 
   /// Gets the current length of the builder.
@@ -776,7 +2512,146 @@ impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
 
   #[inline(always)]
   pub fn append(&mut self, value: impl BytesAppendable<'a> + 'a) {
+    #[cfg(feature = "tracing")]
+    {
+      self.appends += 1;
+    }
+    let start = self.len();
     value.append_to_builder(self);
+    if self.bytes.is_some()
+      && self.len() > self.capacity
+      && self.overflow.is_none()
+    {
+      self.overflow = Some(start);
+    }
+  }
+
+  /// Creates a marker at the current offset.
+  ///
+  /// The offset is the same on both passes, except within a section whose
+  /// prefix size depends on its length (ex. a protobuf length-delimited
+  /// field) where the first pass does not yet include the prefix. The
+  /// markers returned from `build_with_markers` are always final.
+  pub fn mark(&mut self) -> Marker {
+    let marker = Marker(self.len());
+    if self.bytes.is_some() {
+      self.markers.push(marker);
+    }
+    marker
+  }
+
+  /// Appends `fill_byte` until the length of the builder is a
+  /// multiple of `alignment`.
+  ///
+  /// Panics if `alignment` is zero.
+  pub fn pad_to_alignment(&mut self, alignment: usize, fill_byte: u8) {
+    assert!(alignment > 0, "alignment must be greater than zero");
+    let remainder = self.len() % alignment;
+    if remainder == 0 {
+      return;
+    }
+    let padding = alignment - remainder;
+    match &mut self.bytes {
+      Some(b) => {
+        for _ in 0..padding {
+          b.push(fill_byte);
+        }
+      }
+      None => self.capacity += padding,
+    }
+  }
+
+  /// Appends `len` bytes read from a raw pointer (ex. a buffer returned
+  /// by a C API with a separate length).
+  ///
+  /// The length is counted on the first pass and the bytes are only read
+  /// on the second pass.
+  ///
+  /// # Safety
+  ///
+  /// Unless `len` is `0`, `ptr` must be valid for reads of `len` bytes
+  /// that aren't mutated during the call, as described by
+  /// `std::slice::from_raw_parts`.
+  pub unsafe fn append_raw(&mut self, ptr: *const u8, len: usize) {
+    match &mut self.bytes {
+      Some(b) => {
+        if len > 0 {
+          b.extend_from_slice(std::slice::from_raw_parts(ptr, len));
+        }
+      }
+      None => self.capacity += len,
+    }
+  }
+
+  /// Appends an unsigned LEB128 variable length integer.
+  pub fn append_varint(&mut self, value: u64) {
+    match &mut self.bytes {
+      Some(b) => {
+        let mut value = value;
+        while value >= 0x80 {
+          b.push((value as u8) | 0x80);
+          value >>= 7;
+        }
+        b.push(value as u8);
+      }
+      None => self.capacity += varint_len(value),
+    }
+  }
+
+  /// Appends a section where the prefix depends on the length of the
+  /// section (ex. a length prefix).
+  ///
+  /// When the size of the prefix is known up front, provide it as
+  /// `prefix_size` so the offsets on the first pass match the second pass.
+  /// Otherwise, on the first pass the section is appended before the prefix
+  /// in order to calculate its length. On the second pass the length from
+  /// the first pass is provided to `append_prefix`, then the section is
+  /// appended.
+  pub(crate) fn append_section_with_len_prefix(
+    &mut self,
+    prefix_size: Option<usize>,
+    append_prefix: impl FnOnce(&mut Self, usize),
+    build: impl FnOnce(&mut Self),
+  ) {
+    if self.bytes.is_some() {
+      let Some(len) = self.section_lens.get(self.section_index).copied() else {
+        // there was no first pass (ex. `hash_into`), so build the section
+        // to a buffer first to get its length
+        let mut buffer = TBytes::with_capacity(0)
+          .expect("allocating an empty buffer should not fail");
+        // SAFETY: the buffer is only used until it's replaced below
+        let outer = self.bytes.replace(unsafe {
+          std::mem::transmute::<
+            &mut <TBytes as BytesType>::MutType,
+            &mut <TBytes as BytesType>::MutType,
+          >(&mut buffer)
+        });
+        build(self);
+        self.bytes = outer;
+        append_prefix(self, buffer.len());
+        let bytes = self.bytes.as_mut().unwrap();
+        buffer.slices_from(0, |slice| bytes.extend_from_slice(slice));
+        return;
+      };
+      self.section_index += 1;
+      append_prefix(self, len);
+      let start = self.len();
+      build(self);
+      debug_assert_eq!(self.len() - start, len);
+    } else {
+      let index = self.section_lens.len();
+      self.section_lens.push(0);
+      if let Some(prefix_size) = prefix_size {
+        self.capacity += prefix_size;
+      }
+      let start = self.capacity;
+      build(self);
+      let len = self.capacity - start;
+      self.section_lens[index] = len;
+      if prefix_size.is_none() {
+        append_prefix(self, len);
+      }
+    }
   }
 
   /// Appends a number in big-endian byte order.
@@ -803,3 +2678,75 @@ impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
     }
   }
 }
+
+impl<'a, TBytes: BytesType> Builder<'a> for BytesBuilder<'a, TBytes> {
+  #[inline(always)]
+  fn append_str(&mut self, value: &'a str) {
+    self.append(value);
+  }
+
+  #[inline(always)]
+  fn append_char(&mut self, value: char) {
+    self.append(value);
+  }
+
+  #[inline(always)]
+  fn append_int(&mut self, value: impl Integer) {
+    match &mut self.bytes {
+      Some(b) => value.push_to(&mut TextBytes(*b)),
+      None => self.capacity += value.byte_len(),
+    }
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    BytesBuilder::len(self)
+  }
+}
+
+impl<TBytes: BytesType> Extend<u8> for BytesBuilder<'_, TBytes> {
+  #[inline(always)]
+  fn extend<T: IntoIterator<Item = u8>>(&mut self, iter: T) {
+    for value in iter {
+      self.append(value);
+    }
+  }
+}
+
+impl<'a, TBytes: BytesType> Extend<&'a [u8]> for BytesBuilder<'a, TBytes> {
+  #[inline(always)]
+  fn extend<T: IntoIterator<Item = &'a [u8]>>(&mut self, iter: T) {
+    for value in iter {
+      self.append(value);
+    }
+  }
+}
+
+/// Writes text to bytes.
+struct TextBytes<'b, TBytes: BytesTypeMut>(&'b mut TBytes);
+
+impl<TBytes: BytesTypeMut> StringTypeMut for TextBytes<'_, TBytes> {
+  #[inline(always)]
+  fn push(&mut self, c: char) {
+    self
+      .0
+      .extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes());
+  }
+
+  #[inline(always)]
+  fn push_str(&mut self, str: &str) {
+    self.0.extend_from_slice(str.as_bytes());
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    self.0.len()
+  }
+}
+
+#[inline(always)]
+fn varint_len(value: u64) -> usize {
+  // each byte stores 7 bits of the value
+  let bits = 64 - (value | 1).leading_zeros() as usize;
+  bits.div_ceil(7)
+}
@@ -0,0 +1,243 @@
+//! Items used by the code generated by the macros. Not public API.
+
+use std::fmt::Display;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::Mode;
+use crate::StringAppendable;
+use crate::StringAppendableValue;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+pub struct Wrap<T>(pub T);
+
+/// Used when the value is appendable.
+pub trait ViaAppendable<'a> {
+  fn __append<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  );
+}
+
+impl<'a, T: StringAppendable<'a> + 'a> ViaAppendable<'a> for Wrap<T> {
+  #[inline(always)]
+  fn __append<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    builder.append(self.0);
+  }
+}
+
+/// Fallback when the value is not appendable, but implements `Display`.
+pub trait ViaDisplay {
+  fn __append<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'_, TString>,
+  );
+}
+
+impl<T: Display> ViaDisplay for &Wrap<T> {
+  #[inline(always)]
+  fn __append<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'_, TString>,
+  ) {
+    builder.append_value(DisplayValue(&self.0));
+  }
+}
+
+/// Implements `Display` using the provided closure.
+pub struct FmtFn<F: Fn(&mut std::fmt::Formatter<'_>) -> std::fmt::Result>(
+  pub F,
+);
+
+impl<F: Fn(&mut std::fmt::Formatter<'_>) -> std::fmt::Result> Display
+  for FmtFn<F>
+{
+  #[inline(always)]
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    (self.0)(f)
+  }
+}
+
+/// Appends a value using its `Display` implementation, which is formatted
+/// to a counting writer on the first pass.
+pub(crate) struct DisplayValue<'b, T: Display + ?Sized>(pub &'b T);
+
+impl<T: Display + ?Sized> StringAppendableValue for DisplayValue<'_, T> {
+  fn byte_len(&self) -> usize {
+    let mut writer = CountingWriter(0);
+    // a counting writer never errors, but the value might
+    let _ = std::fmt::write(&mut writer, format_args!("{}", self.0));
+    writer.0
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let _ = std::fmt::write(
+      &mut StringTypeMutWriter(text),
+      format_args!("{}", self.0),
+    );
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    self.0.fmt(fmt)
+  }
+}
+
+struct CountingWriter(usize);
+
+impl std::fmt::Write for CountingWriter {
+  #[inline(always)]
+  fn write_str(&mut self, s: &str) -> std::fmt::Result {
+    self.0 += s.len();
+    Ok(())
+  }
+}
+
+struct StringTypeMutWriter<'b, TString: StringTypeMut>(&'b mut TString);
+
+impl<TString: StringTypeMut> std::fmt::Write
+  for StringTypeMutWriter<'_, TString>
+{
+  #[inline(always)]
+  fn write_str(&mut self, s: &str) -> std::fmt::Result {
+    self.0.push_str(s);
+    Ok(())
+  }
+
+  #[inline(always)]
+  fn write_char(&mut self, c: char) -> std::fmt::Result {
+    self.0.push(c);
+    Ok(())
+  }
+}
+
+/// A literal folded by `#[precompute]`.
+pub enum Literal {
+  Str(&'static str),
+  Char(char),
+}
+
+/// Skips the literals folded by `#[precompute]` on the capacity pass,
+/// adding their total length up front instead.
+pub trait Precompute {
+  fn __add_precomputed(&mut self, size: usize);
+  fn __append_precomputed(&mut self, literal: Literal);
+}
+
+impl<TString: StringType> Precompute for StringBuilder<'_, TString> {
+  #[inline(always)]
+  fn __add_precomputed(&mut self, size: usize) {
+    if let Mode::Capacity = self.mode {
+      self.capacity += size;
+    }
+  }
+
+  #[inline(always)]
+  fn __append_precomputed(&mut self, literal: Literal) {
+    if let Mode::Capacity = self.mode {
+      return;
+    }
+    match literal {
+      Literal::Str(value) => self.append_value(value),
+      Literal::Char(value) => self.append_value(value),
+    }
+  }
+}
+
+impl<TBytes: BytesType> Precompute for BytesBuilder<'_, TBytes> {
+  #[inline(always)]
+  fn __add_precomputed(&mut self, size: usize) {
+    if self.bytes.is_none() {
+      self.capacity += size;
+    }
+  }
+
+  #[inline(always)]
+  fn __append_precomputed(&mut self, literal: Literal) {
+    if self.bytes.is_none() {
+      return;
+    }
+    match literal {
+      Literal::Str(value) => self.append(value),
+      Literal::Char(value) => self.append(value),
+    }
+  }
+}
+
+/// Calculates the byte length of a value in const contexts for
+/// `const_capacity!`.
+pub struct ConstLen<T>(pub T);
+
+impl ConstLen<&str> {
+  pub const fn byte_len(&self) -> usize {
+    self.0.len()
+  }
+}
+
+impl ConstLen<char> {
+  pub const fn byte_len(&self) -> usize {
+    self.0.len_utf8()
+  }
+}
+
+macro_rules! impl_const_len_for_unsigned {
+  ($($t:ty),*) => {
+    $(
+      impl ConstLen<$t> {
+        pub const fn byte_len(&self) -> usize {
+          match self.0.checked_ilog10() {
+            Some(log) => log as usize + 1,
+            None => 1,
+          }
+        }
+      }
+    )*
+  };
+}
+
+macro_rules! impl_const_len_for_signed {
+  ($($t:ty),*) => {
+    $(
+      impl ConstLen<$t> {
+        pub const fn byte_len(&self) -> usize {
+          let sign = if self.0 < 0 { 1 } else { 0 };
+          sign + ConstLen(self.0.unsigned_abs()).byte_len()
+        }
+      }
+    )*
+  };
+}
+
+impl_const_len_for_unsigned!(u8, u16, u32, u64, u128, usize);
+impl_const_len_for_signed!(i8, i16, i32, i64, i128, isize);
+
+/// Concatenates the strings into an array of the exact length for
+/// `const_concat!`.
+pub const fn concat_bytes<const N: usize>(pieces: &[&str]) -> [u8; N] {
+  let mut bytes = [0; N];
+  let mut offset = 0;
+  let mut i = 0;
+  while i < pieces.len() {
+    let piece = pieces[i].as_bytes();
+    let mut j = 0;
+    while j < piece.len() {
+      bytes[offset] = piece[j];
+      offset += 1;
+      j += 1;
+    }
+    i += 1;
+  }
+  assert!(
+    offset == N,
+    "the capacity did not equal the concatenated length"
+  );
+  bytes
+}
@@ -0,0 +1,73 @@
+//! Reporting when the built length didn't equal the calculated capacity.
+//!
+//! In debug mode a mismatch panics, but in release mode it silently causes
+//! a reallocation or wasted capacity. A hook can be set to surface these.
+
+use std::sync::RwLock;
+
+/// A build whose length didn't equal the calculated capacity.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityMismatch {
+  /// The capacity calculated on the first pass.
+  pub capacity: usize,
+  /// The length that was actually built.
+  pub len: usize,
+  /// The length of the output before the append that first grew it past
+  /// the capacity, which is where it reallocated.
+  ///
+  /// This is `None` when the output didn't grow past the capacity or when
+  /// the offset isn't tracked (ex. when writing to a sink).
+  pub offset: Option<usize>,
+}
+
+impl CapacityMismatch {
+  /// Whether the output grew past the capacity, which causes a
+  /// reallocation when appending at offset `capacity`.
+  pub fn reallocated(&self) -> bool {
+    self.len > self.capacity
+  }
+}
+
+static HOOK: RwLock<Option<fn(&CapacityMismatch)>> = RwLock::new(None);
+
+/// Sets a function that's called for every build with a mismatch,
+/// replacing any previously set hook.
+pub fn set_hook(hook: fn(&CapacityMismatch)) {
+  *HOOK.write().unwrap_or_else(|err| err.into_inner()) = Some(hook);
+}
+
+/// Removes the hook.
+pub fn take_hook() -> Option<fn(&CapacityMismatch)> {
+  HOOK.write().unwrap_or_else(|err| err.into_inner()).take()
+}
+
+/// Reports a mismatch to the hook when the length isn't the capacity.
+#[inline(always)]
+pub(crate) fn check(capacity: usize, len: usize) {
+  check_with_offset(capacity, len, None);
+}
+
+/// Reports a mismatch along with the offset where the output grew past
+/// the capacity.
+#[inline(always)]
+pub(crate) fn check_with_offset(
+  capacity: usize,
+  len: usize,
+  offset: Option<usize>,
+) {
+  if capacity != len {
+    report(CapacityMismatch {
+      capacity,
+      len,
+      offset,
+    });
+  }
+}
+
+#[cold]
+fn report(mismatch: CapacityMismatch) {
+  let hook = *HOOK.read().unwrap_or_else(|err| err.into_inner());
+  if let Some(hook) = hook {
+    hook(&mismatch);
+  }
+}
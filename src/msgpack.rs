@@ -0,0 +1,260 @@
+//! Appendables for MessagePack values and headers.
+//!
+//! Each header has a size that's known up front, so these can be
+//! appended to a `BytesBuilder` where the contents are then appended
+//! after the header.
+
+use crate::BytesAppendableValue;
+use crate::BytesTypeMut;
+
+/// A MessagePack `nil` value.
+#[derive(Debug, Clone, Copy)]
+pub struct Nil;
+
+impl BytesAppendableValue for Nil {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    1
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    bytes.push(0xc0);
+  }
+}
+
+/// A MessagePack boolean value.
+#[derive(Debug, Clone, Copy)]
+pub struct Bool(pub bool);
+
+impl BytesAppendableValue for Bool {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    1
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    bytes.push(if self.0 { 0xc3 } else { 0xc2 });
+  }
+}
+
+/// A MessagePack unsigned integer encoded in the smallest format.
+#[derive(Debug, Clone, Copy)]
+pub struct UInt(pub u64);
+
+impl BytesAppendableValue for UInt {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    match self.0 {
+      0..=0x7f => 1,
+      0x80..=0xff => 2,
+      0x100..=0xffff => 3,
+      0x1_0000..=0xffff_ffff => 5,
+      _ => 9,
+    }
+  }
+
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    let value = self.0;
+    match value {
+      0..=0x7f => bytes.push(value as u8),
+      0x80..=0xff => {
+        bytes.push(0xcc);
+        bytes.push(value as u8);
+      }
+      0x100..=0xffff => {
+        bytes.push(0xcd);
+        bytes.extend_from_slice(&(value as u16).to_be_bytes());
+      }
+      0x1_0000..=0xffff_ffff => {
+        bytes.push(0xce);
+        bytes.extend_from_slice(&(value as u32).to_be_bytes());
+      }
+      _ => {
+        bytes.push(0xcf);
+        bytes.extend_from_slice(&value.to_be_bytes());
+      }
+    }
+  }
+}
+
+/// A MessagePack signed integer encoded in the smallest format.
+///
+/// Non-negative values are encoded the same as `UInt`.
+#[derive(Debug, Clone, Copy)]
+pub struct Int(pub i64);
+
+impl BytesAppendableValue for Int {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    match self.0 {
+      0.. => UInt(self.0 as u64).byte_len(),
+      -32..=-1 => 1,
+      -0x80..=-33 => 2,
+      -0x8000..=-0x81 => 3,
+      -0x8000_0000..=-0x8001 => 5,
+      _ => 9,
+    }
+  }
+
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    let value = self.0;
+    match value {
+      0.. => UInt(value as u64).push_to(bytes),
+      -32..=-1 => bytes.push(value as i8 as u8),
+      -0x80..=-33 => {
+        bytes.push(0xd0);
+        bytes.push(value as i8 as u8);
+      }
+      -0x8000..=-0x81 => {
+        bytes.push(0xd1);
+        bytes.extend_from_slice(&(value as i16).to_be_bytes());
+      }
+      -0x8000_0000..=-0x8001 => {
+        bytes.push(0xd2);
+        bytes.extend_from_slice(&(value as i32).to_be_bytes());
+      }
+      _ => {
+        bytes.push(0xd3);
+        bytes.extend_from_slice(&value.to_be_bytes());
+      }
+    }
+  }
+}
+
+/// A MessagePack 64-bit float.
+#[derive(Debug, Clone, Copy)]
+pub struct Float(pub f64);
+
+impl BytesAppendableValue for Float {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    9
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    bytes.push(0xcb);
+    bytes.extend_from_slice(&self.0.to_be_bytes());
+  }
+}
+
+/// A MessagePack string (header and UTF-8 bytes).
+#[derive(Debug, Clone, Copy)]
+pub struct Str<'a>(pub &'a str);
+
+impl BytesAppendableValue for Str<'_> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    StrHeader(self.0.len()).byte_len() + self.0.len()
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    StrHeader(self.0.len()).push_to(bytes);
+    bytes.extend_from_slice(self.0.as_bytes());
+  }
+}
+
+/// A MessagePack binary value (header and bytes).
+#[derive(Debug, Clone, Copy)]
+pub struct Bin<'a>(pub &'a [u8]);
+
+impl BytesAppendableValue for Bin<'_> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    BinHeader(self.0.len()).byte_len() + self.0.len()
+  }
+
+  #[inline(always)]
+  fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+    BinHeader(self.0.len()).push_to(bytes);
+    bytes.extend_from_slice(self.0);
+  }
+}
+
+macro_rules! impl_header {
+  ($name:ident, $doc:literal, $fix:expr, $fix_max:expr, $h8:expr, $h16:expr, $h32:expr) => {
+    #[doc = $doc]
+    ///
+    /// Panics when appended if the length exceeds `u32::MAX`.
+    #[derive(Debug, Clone, Copy)]
+    pub struct $name(pub usize);
+
+    impl BytesAppendableValue for $name {
+      #[inline(always)]
+      fn byte_len(&self) -> usize {
+        let fix: Option<u8> = $fix;
+        let h8: Option<u8> = $h8;
+        match self.0 {
+          len if fix.is_some() && len <= $fix_max => 1,
+          len if h8.is_some() && len <= 0xff => 2,
+          0..=0xffff => 3,
+          _ => 5,
+        }
+      }
+
+      fn push_to<TBytes: BytesTypeMut>(&self, bytes: &mut TBytes) {
+        let fix: Option<u8> = $fix;
+        let h8: Option<u8> = $h8;
+        match self.0 {
+          len if fix.is_some() && len <= $fix_max => {
+            bytes.push(fix.unwrap() | len as u8);
+          }
+          len if h8.is_some() && len <= 0xff => {
+            bytes.push(h8.unwrap());
+            bytes.push(len as u8);
+          }
+          len @ 0..=0xffff => {
+            bytes.push($h16);
+            bytes.extend_from_slice(&(len as u16).to_be_bytes());
+          }
+          len => {
+            let len = u32::try_from(len)
+              .expect("length exceeded the maximum MessagePack length");
+            bytes.push($h32);
+            bytes.extend_from_slice(&len.to_be_bytes());
+          }
+        }
+      }
+    }
+  };
+}
+
+impl_header!(
+  StrHeader,
+  "A MessagePack string header for a UTF-8 byte length.",
+  Some(0xa0),
+  31,
+  Some(0xd9),
+  0xda,
+  0xdb
+);
+impl_header!(
+  BinHeader,
+  "A MessagePack binary header for a byte length.",
+  None,
+  0,
+  Some(0xc4),
+  0xc5,
+  0xc6
+);
+impl_header!(
+  ArrayHeader,
+  "A MessagePack array header for the number of elements that follow.",
+  Some(0x90),
+  15,
+  None,
+  0xdc,
+  0xdd
+);
+impl_header!(
+  MapHeader,
+  "A MessagePack map header for the number of key/value pairs that follow.",
+  Some(0x80),
+  15,
+  None,
+  0xde,
+  0xdf
+);
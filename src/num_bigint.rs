@@ -0,0 +1,58 @@
+use std::fmt::Write;
+
+use num_bigint::BigInt;
+use num_bigint::BigUint;
+
+use crate::StringAppendable;
+use crate::StringBuilder;
+use crate::StringType;
+
+/// Formats the number once on the capacity pass, keeping the text until
+/// it's appended on the next pass.
+impl<'a> StringAppendable<'a> for &'a BigUint {
+  #[inline(always)]
+  fn append_to_builder<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    builder.append_cached(|text| {
+      let _ = write!(text, "{}", self);
+    });
+  }
+}
+
+/// Formats the number once on the capacity pass, keeping the text until
+/// it's appended on the next pass.
+impl<'a> StringAppendable<'a> for &'a BigInt {
+  #[inline(always)]
+  fn append_to_builder<TString: StringType>(
+    self,
+    builder: &mut StringBuilder<'a, TString>,
+  ) {
+    builder.append_cached(|text| {
+      let _ = write!(text, "{}", self);
+    });
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use num_bigint::BigInt;
+  use num_bigint::BigUint;
+
+  use crate::StringBuilder;
+
+  #[test]
+  fn builds() {
+    let unsigned = BigUint::from(u128::MAX) * BigUint::from(u128::MAX);
+    let signed = -BigInt::from(unsigned.clone());
+    let text = StringBuilder::<String>::build(|builder| {
+      builder.append(&unsigned);
+      builder.append(' ');
+      builder.append(&signed);
+    })
+    .unwrap();
+    assert_eq!(text, format!("{} {}", unsigned, signed));
+    assert_eq!(text.capacity(), text.len());
+  }
+}
@@ -0,0 +1,270 @@
+use std::fmt::Display;
+use std::fmt::Write;
+
+use crate::macro_support::ConstLen;
+use crate::macro_support::DisplayValue;
+use crate::Integer;
+use crate::StringAppendableValue;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+/// Formats a float in engineering notation.
+struct Engineering {
+  value: f64,
+  sig_figs: usize,
+}
+
+impl Display for Engineering {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    if !self.value.is_finite() {
+      return write!(f, "{}", self.value);
+    }
+    let precision = self.sig_figs.max(1) - 1;
+    // find the exponent after rounding to the significant figures
+    let mut exponent = ExponentWriter::default();
+    write!(exponent, "{:.*e}", precision, self.value)?;
+    let exponent = exponent.exponent();
+    let eng_exponent = exponent.div_euclid(3) * 3;
+    let mut mantissa = MantissaWriter {
+      f,
+      int_digits: (exponent - eng_exponent) as usize + 1,
+      written: 0,
+      done: false,
+    };
+    write!(mantissa, "{:.*e}", precision, self.value)?;
+    while mantissa.written < mantissa.int_digits {
+      mantissa.f.write_char('0')?;
+      mantissa.written += 1;
+    }
+    write!(f, "e{}", eng_exponent)
+  }
+}
+
+/// Reads the exponent of a float formatted in scientific notation.
+#[derive(Default)]
+struct ExponentWriter {
+  is_exponent: bool,
+  is_negative: bool,
+  value: i32,
+}
+
+impl ExponentWriter {
+  fn exponent(&self) -> i32 {
+    if self.is_negative {
+      -self.value
+    } else {
+      self.value
+    }
+  }
+}
+
+impl Write for ExponentWriter {
+  fn write_str(&mut self, s: &str) -> std::fmt::Result {
+    for c in s.chars() {
+      if !self.is_exponent {
+        self.is_exponent = c == 'e';
+      } else if c == '-' {
+        self.is_negative = true;
+      } else if let Some(digit) = c.to_digit(10) {
+        self.value = self.value * 10 + digit as i32;
+      }
+    }
+    Ok(())
+  }
+}
+
+/// Writes the digits of a float formatted in scientific notation with the
+/// decimal point moved after `int_digits` digits.
+struct MantissaWriter<'f, 'g> {
+  f: &'f mut std::fmt::Formatter<'g>,
+  int_digits: usize,
+  written: usize,
+  done: bool,
+}
+
+impl Write for MantissaWriter<'_, '_> {
+  fn write_str(&mut self, s: &str) -> std::fmt::Result {
+    for c in s.chars() {
+      match c {
+        _ if self.done => {}
+        'e' => self.done = true,
+        '.' => {}
+        '-' => self.f.write_char(c)?,
+        _ => {
+          if self.written == self.int_digits {
+            self.f.write_char('.')?;
+          }
+          self.f.write_char(c)?;
+          self.written += 1;
+        }
+      }
+    }
+    Ok(())
+  }
+}
+
+/// An integer amount of minor units formatted with a decimal point.
+pub(crate) struct FixedPoint {
+  pub value: i128,
+  pub scale: u32,
+  pub group_separator: Option<char>,
+}
+
+impl FixedPoint {
+  fn write(
+    &self,
+    mut write: impl FnMut(&str) -> std::fmt::Result,
+  ) -> std::fmt::Result {
+    let mut buffer = crate::int_buffer::Buffer::new();
+    let digits = buffer.format(self.value.unsigned_abs());
+    let scale = self.scale as usize;
+    if self.value < 0 {
+      write("-")?;
+    }
+    let (int_digits, frac_digits) =
+      digits.split_at(digits.len().saturating_sub(scale));
+    match self.group_separator {
+      // the integer part is zero when there are no more digits than the scale
+      _ if int_digits.is_empty() => write("0")?,
+      Some(separator) => {
+        let mut separator_buffer = [0; 4];
+        let separator = separator.encode_utf8(&mut separator_buffer);
+        let first_group = (int_digits.len() - 1) % 3 + 1;
+        write(&int_digits[..first_group])?;
+        for start in (first_group..int_digits.len()).step_by(3) {
+          write(separator)?;
+          write(&int_digits[start..start + 3])?;
+        }
+      }
+      None => write(int_digits)?,
+    }
+    if scale > 0 {
+      write(".")?;
+      for _ in frac_digits.len()..scale {
+        write("0")?;
+      }
+      write(frac_digits)?;
+    }
+    Ok(())
+  }
+}
+
+impl StringAppendableValue for FixedPoint {
+  fn byte_len(&self) -> usize {
+    let digits = ConstLen(self.value.unsigned_abs()).byte_len();
+    let scale = self.scale as usize;
+    let int_len = digits.saturating_sub(scale).max(1);
+    let separators = match self.group_separator {
+      Some(separator) => (int_len - 1) / 3 * separator.len_utf8(),
+      None => 0,
+    };
+    let sign = if self.value < 0 { 1 } else { 0 };
+    let frac_len = if scale > 0 { scale + 1 } else { 0 };
+    sign + int_len + separators + frac_len
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let _ = self.write(|value| {
+      text.push_str(value);
+      Ok(())
+    });
+  }
+
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    self.write(|value| fmt.write_str(value))
+  }
+}
+
+/// A slice of integers joined by a separator.
+struct Ints<'s, T> {
+  values: &'s [T],
+  separator: &'s str,
+}
+
+impl<T: Integer> StringAppendableValue for Ints<'_, T> {
+  fn byte_len(&self) -> usize {
+    let separators = self.values.len().saturating_sub(1) * self.separator.len();
+    self
+      .values
+      .iter()
+      .fold(separators, |len, value| len + value.byte_len())
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let Some((first, rest)) = self.values.split_first() else {
+      return;
+    };
+    first.push_to(text);
+    for value in rest {
+      text.push_str(self.separator);
+      value.push_to(text);
+    }
+  }
+
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    let Some((first, rest)) = self.values.split_first() else {
+      return Ok(());
+    };
+    first.write_to_formatter(fmt)?;
+    for value in rest {
+      fmt.write_str(self.separator)?;
+      value.write_to_formatter(fmt)?;
+    }
+    Ok(())
+  }
+}
+
+impl<TString: StringType> StringBuilder<'_, TString> {
+  /// Appends a float in engineering notation, where the exponent is a
+  /// multiple of three (ex. `12.3e3` with three significant figures).
+  pub fn append_float_engineering(&mut self, value: f64, sig_figs: usize) {
+    self.append_value(DisplayValue(&Engineering { value, sig_figs }));
+  }
+
+  /// Appends an integer amount of minor units with `scale` decimal places,
+  /// ex. `123456` with a scale of `2` is `1234.56`.
+  pub fn append_fixed_point(&mut self, value: i64, scale: u8) {
+    self.append_value(FixedPoint {
+      value: value as i128,
+      scale: scale as u32,
+      group_separator: None,
+    });
+  }
+
+  /// Appends an integer amount of minor units like `append_fixed_point`,
+  /// separating groups of thousands in the integer part (ex. `1,234.56`).
+  pub fn append_fixed_point_grouped(
+    &mut self,
+    value: i64,
+    scale: u8,
+    separator: char,
+  ) {
+    self.append_value(FixedPoint {
+      value: value as i128,
+      scale: scale as u32,
+      group_separator: Some(separator),
+    });
+  }
+
+  /// Appends a slice of integers as decimal text separated by the
+  /// separator (ex. `1, 2, 3`), which may be empty.
+  ///
+  /// This is faster than appending each integer separately when there
+  /// are many of them.
+  pub fn append_ints(&mut self, values: &[impl Integer], separator: &str) {
+    self.append_value(Ints { values, separator });
+  }
+
+  /// Appends a ratio as a percentage with the provided number of decimal
+  /// places (ex. `0.425` with one decimal place is `42.5%`).
+  pub fn append_percent(&mut self, ratio: f64, precision: usize) {
+    self.append_fmt(format_args!("{:.*}%", precision, ratio * 100.0));
+  }
+}
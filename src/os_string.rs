@@ -0,0 +1,59 @@
+use std::collections::TryReserveError;
+use std::ffi::OsStr;
+use std::ffi::OsString;
+
+use crate::Mode;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+impl StringType for OsString {
+  type MutType = OsString;
+
+  #[inline(always)]
+  fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+    let mut text = OsString::new();
+    text.try_reserve_exact(size)?;
+    Ok(text)
+  }
+
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    inner
+  }
+}
+
+impl StringTypeMut for OsString {
+  #[inline(always)]
+  fn push(&mut self, c: char) {
+    OsString::push(self, c.encode_utf8(&mut [0; 4]));
+  }
+
+  #[inline(always)]
+  fn push_str(&mut self, str: &str) {
+    OsString::push(self, str);
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    OsStr::len(self)
+  }
+}
+
+impl<'a> StringBuilder<'a, OsString> {
+  /// Appends a platform string without converting it to a `String`.
+  pub fn append_os_str(&mut self, value: &'a OsStr) {
+    match &mut self.mode {
+      Mode::Text(text) => OsString::push(text, value),
+      Mode::Capacity | Mode::FormatError(_) => self.capacity += value.len(),
+      Mode::CowCapacity(_)
+      | Mode::Segments(_)
+      | Mode::Sink(_)
+      | Mode::Format(_) => {
+        // other outputs are only valid utf-8
+        let text = value.to_string_lossy();
+        self.append_owned_unsafe(text.len(), || text);
+      }
+    }
+  }
+}
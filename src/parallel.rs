@@ -0,0 +1,49 @@
+use rayon::prelude::*;
+
+use crate::sink::SliceSink;
+use crate::Error;
+use crate::StringBuilder;
+
+impl<'a> StringBuilder<'a, String> {
+  /// Builds the text of each item in parallel.
+  ///
+  /// The capacity of each item is calculated in parallel and the items
+  /// are then written to their own region of the pre-sized string.
+  ///
+  /// Panics when an item's text has a different length on the second pass.
+  pub fn build_par<T: Sync>(
+    items: &'a [T],
+    build: impl Fn(&mut StringBuilder<'a, String>, &'a T) + Sync,
+  ) -> Result<String, Error> {
+    let plans = items
+      .par_iter()
+      .map(|item| StringBuilder::plan_sink(|state| build(state, item)))
+      .collect::<Vec<_>>();
+    let len = plans.iter().map(|plan| plan.capacity).sum();
+    let mut bytes = Vec::new();
+    bytes.try_reserve_exact(len).map_err(Error::reserve(len))?;
+    let mut regions = Vec::with_capacity(plans.len());
+    let mut remaining = &mut bytes.spare_capacity_mut()[..len];
+    for plan in &plans {
+      let (region, rest) = remaining.split_at_mut(plan.capacity);
+      regions.push(region);
+      remaining = rest;
+    }
+    regions
+      .into_par_iter()
+      .zip(items.par_iter().zip(&plans))
+      .for_each(|(region, (item, plan))| {
+        let mut sink = SliceSink::new(region);
+        StringBuilder::fill_sink(plan, &mut sink, |state| build(state, item));
+        assert!(
+          sink.is_full(),
+          "build_par item had a different length on the second pass"
+        );
+      });
+    // SAFETY: every region was completely filled with whole strings
+    unsafe {
+      bytes.set_len(len);
+      Ok(String::from_utf8_unchecked(bytes))
+    }
+  }
+}
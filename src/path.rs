@@ -0,0 +1,93 @@
+use std::ffi::OsString;
+use std::path::Path;
+use std::path::PathBuf;
+use std::path::MAIN_SEPARATOR_STR;
+
+use crate::Error;
+
+/// Builds a `PathBuf` from components with an exact capacity.
+pub struct PathBuilder<'a> {
+  capacity: usize,
+  ends_with_separator: bool,
+  /// The number of components appended so far.
+  components: usize,
+  /// The index of the last absolute component on the first pass, as the
+  /// components before it are replaced and don't need to be written.
+  last_root: usize,
+  path: Option<OsString>,
+  _marker: std::marker::PhantomData<&'a ()>,
+}
+
+impl<'a> PathBuilder<'a> {
+  #[inline(always)]
+  pub fn build(build: impl Fn(&mut PathBuilder<'a>)) -> Result<PathBuf, Error> {
+    let mut state = PathBuilder {
+      capacity: 0,
+      ends_with_separator: false,
+      components: 0,
+      last_root: 0,
+      path: None,
+      _marker: std::marker::PhantomData,
+    };
+    build(&mut state);
+    let mut path = OsString::new();
+    path
+      .try_reserve_exact(state.capacity)
+      .map_err(Error::reserve(state.capacity))?;
+    let capacity = state.capacity;
+    state.path = Some(path);
+    state.capacity = 0;
+    state.ends_with_separator = false;
+    state.components = 0;
+    build(&mut state);
+    let path = state.path.unwrap();
+    crate::mismatch::check(capacity, path.len());
+    debug_assert_eq!(capacity, path.len());
+    Ok(PathBuf::from(path))
+  }
+
+  /// Appends a component the same way as `PathBuf::push`, inserting a
+  /// separator when necessary and replacing the path when the component
+  /// is absolute.
+  pub fn append_component(
+    &mut self,
+    component: &'a (impl AsRef<Path> + ?Sized),
+  ) {
+    let component = component.as_ref();
+    if component.has_root() {
+      self.capacity = 0;
+      match &mut self.path {
+        Some(path) => path.clear(),
+        None => self.last_root = self.components,
+      }
+    } else if self.capacity > 0 && !self.ends_with_separator {
+      self.push_os(MAIN_SEPARATOR_STR.as_ref());
+    }
+    self.push_os(component.as_os_str());
+    let bytes = component.as_os_str().as_encoded_bytes();
+    if let Some(last) = bytes.last() {
+      self.ends_with_separator = std::path::is_separator(*last as char);
+    } else {
+      // pushing an empty component adds a trailing separator
+      self.ends_with_separator = true;
+    }
+    self.components += 1;
+  }
+
+  /// Gets the current length of the path in bytes.
+  #[allow(clippy::len_without_is_empty)]
+  #[inline(always)]
+  pub fn len(&self) -> usize {
+    self.capacity
+  }
+
+  #[inline(always)]
+  fn push_os(&mut self, value: &std::ffi::OsStr) {
+    self.capacity += value.len();
+    if self.components >= self.last_root {
+      if let Some(path) = &mut self.path {
+        path.push(value);
+      }
+    }
+  }
+}
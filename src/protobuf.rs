@@ -0,0 +1,39 @@
+use crate::BytesBuilder;
+use crate::BytesType;
+
+/// Protobuf wire types used in field tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireType {
+  Varint = 0,
+  I64 = 1,
+  Len = 2,
+  I32 = 5,
+}
+
+impl<'a, TBytes: BytesType> BytesBuilder<'a, TBytes> {
+  /// Appends a protobuf field tag for the provided field number and
+  /// wire type.
+  #[inline(always)]
+  pub fn append_proto_tag(&mut self, field_number: u32, wire_type: WireType) {
+    self.append_varint(((field_number as u64) << 3) | wire_type as u64);
+  }
+
+  /// Appends a length-delimited protobuf field (strings, bytes,
+  /// embedded messages, or packed repeated fields) where the contents
+  /// are appended within the provided closure.
+  ///
+  /// Note that the builder's length within the closure on the first pass
+  /// does not include the length prefix.
+  pub fn append_proto_len_delimited(
+    &mut self,
+    field_number: u32,
+    build: impl FnOnce(&mut BytesBuilder<'a, TBytes>),
+  ) {
+    self.append_proto_tag(field_number, WireType::Len);
+    self.append_section_with_len_prefix(
+      None,
+      |builder, len| builder.append_varint(len as u64),
+      build,
+    );
+  }
+}
@@ -0,0 +1,202 @@
+use std::collections::TryReserveError;
+use std::io::Read;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::SyncSender;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+
+pub(crate) use stream_bytes::StreamBytes;
+use stream_bytes::StreamBytesMut;
+
+/// The size of the chunks sent from the closure to the reader.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// The number of chunks that can be built ahead of the reader.
+const CHUNKS_AHEAD: usize = 4;
+
+type Chunk = std::io::Result<Vec<u8>>;
+
+mod stream_bytes {
+  use super::*;
+
+  /// Output type of `BytesBuilder::stream`, which can't be named so that
+  /// it's only built by `stream`.
+  pub struct StreamBytes(());
+
+  impl BytesType for StreamBytes {
+    type MutType = StreamBytesMut;
+
+    fn with_capacity(_size: usize) -> Result<Self::MutType, TryReserveError> {
+      unreachable!("StreamBytes is only used by BytesBuilder::stream");
+    }
+
+    fn from_mut(_inner: Self::MutType) -> Self {
+      StreamBytes(())
+    }
+  }
+
+  /// Sends the bytes to the reader in chunks.
+  pub struct StreamBytesMut {
+    pub(super) len: usize,
+    pub(super) chunk: Vec<u8>,
+    /// The sender, which is dropped once the reader is dropped.
+    pub(super) sender: Option<SyncSender<Chunk>>,
+  }
+
+  impl StreamBytesMut {
+    /// Sends the bytes appended since the last chunk.
+    pub(super) fn flush(&mut self) {
+      if self.chunk.is_empty() {
+        return;
+      }
+      let Some(sender) = &self.sender else {
+        self.chunk.clear();
+        return;
+      };
+      let chunk =
+        std::mem::replace(&mut self.chunk, Vec::with_capacity(CHUNK_SIZE));
+      if sender.send(Ok(chunk)).is_err() {
+        // the reader was dropped, so the remaining bytes are discarded
+        self.sender = None;
+      }
+    }
+  }
+
+  impl BytesTypeMut for StreamBytesMut {
+    #[inline(always)]
+    fn push(&mut self, c: u8) {
+      self.chunk.push(c);
+      self.len += 1;
+      if self.chunk.len() == CHUNK_SIZE {
+        self.flush();
+      }
+    }
+
+    fn extend_from_slice(&mut self, mut bytes: &[u8]) {
+      self.len += bytes.len();
+      while !bytes.is_empty() {
+        let count = std::cmp::min(CHUNK_SIZE - self.chunk.len(), bytes.len());
+        self.chunk.extend_from_slice(&bytes[..count]);
+        bytes = &bytes[count..];
+        if self.chunk.len() == CHUNK_SIZE {
+          self.flush();
+        }
+      }
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+      self.len
+    }
+
+    /// Panics because the previous bytes aren't kept, so checksums
+    /// can't be calculated when streaming.
+    fn slices_from(&self, _start: usize, _f: impl FnMut(&[u8])) {
+      panic!("reading previously appended bytes is not supported when streaming with stream");
+    }
+  }
+}
+
+/// Reads the bytes of a build closure as they're built.
+///
+/// See `BytesBuilder::stream`.
+pub struct BytesReader {
+  len: usize,
+  receiver: Receiver<Chunk>,
+  chunk: Vec<u8>,
+  chunk_pos: usize,
+}
+
+impl BytesReader {
+  /// The total byte length that will be read.
+  #[allow(clippy::len_without_is_empty)]
+  pub fn len(&self) -> usize {
+    self.len
+  }
+}
+
+impl Read for BytesReader {
+  fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+    if self.chunk_pos == self.chunk.len() {
+      match self.receiver.recv() {
+        Ok(chunk) => {
+          self.chunk = chunk?;
+          self.chunk_pos = 0;
+        }
+        // the closure finished
+        Err(_) => return Ok(0),
+      }
+    }
+    let remaining = &self.chunk[self.chunk_pos..];
+    let read = std::cmp::min(remaining.len(), buf.len());
+    buf[..read].copy_from_slice(&remaining[..read]);
+    self.chunk_pos += read;
+    Ok(read)
+  }
+}
+
+impl<'a> BytesBuilder<'a, StreamBytes> {
+  /// Streams the bytes of the closure to a reader instead of building
+  /// them all at once.
+  ///
+  /// The closure is run once to calculate the length and then once more
+  /// on another thread, which sends the bytes to the reader provided to
+  /// `read` in 64 KiB chunks as they're built. Only a few chunks are held
+  /// in memory at a time. When `read` returns before reading all the
+  /// bytes, the remaining bytes are discarded once the closure finishes.
+  ///
+  /// Since the closure runs twice, `append_reader_exact` isn't supported
+  /// (its reader would be consumed by the capacity pass) and surfaces an
+  /// `ErrorKind::Unsupported` error when reading. Errors occurring in files
+  /// are surfaced when reading. Checksums are not supported and panic.
+  pub fn stream<R>(
+    build: impl Fn(&mut BytesBuilder<'a, StreamBytes>) + Send,
+    read: impl FnOnce(&mut BytesReader) -> R,
+  ) -> R {
+    let mut builder = BytesBuilder::with_bytes(None);
+    builder.streaming = true;
+    build(&mut builder);
+    let len = builder.capacity;
+    let section_lens = builder.section_lens;
+    let (sender, receiver) = std::sync::mpsc::sync_channel(CHUNKS_AHEAD);
+    std::thread::scope(|scope| {
+      scope.spawn(move || {
+        let mut bytes = StreamBytesMut {
+          len: 0,
+          chunk: Vec::with_capacity(CHUNK_SIZE),
+          sender: Some(sender),
+        };
+        let mut builder = BytesBuilder::with_bytes(
+          // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+          Some(unsafe {
+            std::mem::transmute::<&mut StreamBytesMut, &mut StreamBytesMut>(
+              &mut bytes,
+            )
+          }),
+        );
+        builder.capacity = len;
+        builder.section_lens = section_lens;
+        builder.streaming = true;
+        build(&mut builder);
+        let io_error = builder.io_error.take();
+        bytes.flush();
+        debug_assert_eq!(bytes.len, len);
+        if let (Some(err), Some(sender)) = (io_error, &bytes.sender) {
+          _ = sender.send(Err(err));
+        }
+      });
+      let mut reader = BytesReader {
+        len,
+        receiver,
+        chunk: Vec::new(),
+        chunk_pos: 0,
+      };
+      let result = read(&mut reader);
+      // stop sending so the closure doesn't block waiting for the reader
+      drop(reader);
+      result
+    })
+  }
+}
@@ -0,0 +1,86 @@
+use rust_decimal::Decimal;
+
+use crate::number::FixedPoint;
+use crate::StringAppendableValue;
+use crate::StringTypeMut;
+
+/// Appends the same text as the `Display` implementation without
+/// allocating a temporary string.
+impl StringAppendableValue for Decimal {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    fixed_point(self).byte_len()
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    fixed_point(self).push_to(text)
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    fixed_point(self).write_to_formatter(fmt)
+  }
+}
+
+impl StringAppendableValue for &Decimal {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    StringAppendableValue::byte_len(*self)
+  }
+
+  #[inline(always)]
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    StringAppendableValue::push_to(*self, text)
+  }
+
+  #[inline(always)]
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    StringAppendableValue::write_to_formatter(*self, fmt)
+  }
+}
+
+#[inline(always)]
+fn fixed_point(value: &Decimal) -> FixedPoint {
+  FixedPoint {
+    value: value.mantissa(),
+    scale: value.scale(),
+    group_separator: None,
+  }
+}
+
+#[cfg(test)]
+mod test {
+  use rust_decimal::Decimal;
+
+  use crate::StringBuilder;
+
+  #[test]
+  fn matches_display() {
+    let values = [
+      Decimal::new(12345, 2),
+      Decimal::new(-5, 3),
+      Decimal::new(100, 2),
+      Decimal::new(0, 4),
+      Decimal::new(-42, 0),
+      Decimal::MAX,
+      Decimal::MIN,
+    ];
+    for value in &values {
+      let text = StringBuilder::<String>::build(|builder| {
+        builder.append(*value);
+        builder.append(' ');
+        builder.append(value);
+      })
+      .unwrap();
+      assert_eq!(text, format!("{} {}", value, value));
+      assert_eq!(text.capacity(), text.len());
+    }
+  }
+}
@@ -0,0 +1,102 @@
+use rayon::prelude::*;
+
+use crate::sink::SliceSink;
+use crate::Error;
+use crate::Mode;
+use crate::StringBuilder;
+use crate::StringType;
+
+type Section<'s, 'a> = Box<dyn Fn(&mut StringBuilder<'a, String>) + Send + 's>;
+
+/// Collects the sections of `StringBuilder::sections`.
+pub struct SectionScope<'s, 'a> {
+  sections: Vec<Section<'s, 'a>>,
+}
+
+impl<'s, 'a> SectionScope<'s, 'a> {
+  /// Adds a section that will be built on the rayon thread pool.
+  pub fn spawn(
+    &mut self,
+    build: impl Fn(&mut StringBuilder<'a, String>) + Send + 's,
+  ) {
+    self.sections.push(Box::new(build));
+  }
+}
+
+impl<'a, TString: StringType> StringBuilder<'a, TString> {
+  /// Builds independent sections in parallel, appending them in the order
+  /// they were spawned.
+  ///
+  /// The capacity pass calculates the length of each section on the rayon
+  /// thread pool, then the second pass builds each section into its region
+  /// of a buffer that's appended to the output.
+  ///
+  /// When the buffer for the sections can't be allocated, the error is
+  /// returned by the build. Builds to a sink (ex. `hash_into`) panic
+  /// instead.
+  pub fn sections<'s>(&mut self, scope: impl FnOnce(&mut SectionScope<'s, 'a>))
+  where
+    'a: 's,
+  {
+    let mut section_scope = SectionScope {
+      sections: Vec::new(),
+    };
+    scope(&mut section_scope);
+    let sections = section_scope.sections;
+    if matches!(self.mode, Mode::Capacity | Mode::CowCapacity(_)) {
+      let plans = sections
+        .into_par_iter()
+        .map(|section| StringBuilder::plan_sink(|builder| section(builder)))
+        .collect::<Vec<_>>();
+      let len = plans.iter().map(|plan| plan.capacity).sum();
+      self.append_owned_unsafe(len, || "");
+      self.section_plans.push_back(plans);
+      return;
+    }
+
+    let (plans, sections): (Vec<_>, Vec<_>) =
+      match self.section_plans.pop_front() {
+        Some(plans) => (plans, sections),
+        // the capacity pass ran on another builder (ex. a template)
+        None => sections
+          .into_par_iter()
+          .map(|section| {
+            (
+              StringBuilder::plan_sink(|builder| section(builder)),
+              section,
+            )
+          })
+          .unzip(),
+      };
+    let len = plans.iter().map(|plan| plan.capacity).sum();
+    let mut bytes = Vec::new();
+    if let Err(err) = bytes.try_reserve_exact(len) {
+      self.set_error(Error::reserve(len)(err));
+      return;
+    }
+    let mut regions = Vec::with_capacity(plans.len());
+    let mut remaining = &mut bytes.spare_capacity_mut()[..len];
+    for plan in &plans {
+      let (region, rest) = remaining.split_at_mut(plan.capacity);
+      regions.push(region);
+      remaining = rest;
+    }
+    regions
+      .into_par_iter()
+      .zip(sections.into_par_iter().zip(&plans))
+      .for_each(|(region, (section, plan))| {
+        let mut sink = SliceSink::new(region);
+        StringBuilder::fill_sink(plan, &mut sink, |builder| section(builder));
+        assert!(
+          sink.is_full(),
+          "a section had a different length on the second pass"
+        );
+      });
+    // SAFETY: every region was completely filled with whole strings
+    let text = unsafe {
+      bytes.set_len(len);
+      String::from_utf8_unchecked(bytes)
+    };
+    self.append_owned_unsafe(len, || text);
+  }
+}
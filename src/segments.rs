@@ -0,0 +1,183 @@
+use crate::Mode;
+use crate::StringBuilder;
+use crate::StringTypeMut;
+
+/// The output of `StringBuilder::build_segments`.
+///
+/// Borrowed strings are kept as is and everything else (ex. numbers) is
+/// written to a shared buffer.
+#[derive(Debug, Default)]
+pub struct Segments<'a> {
+  segments: Vec<Segment<'a>>,
+  buffer: String,
+  len: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Segment<'a> {
+  Borrowed(&'a str),
+  /// A range in the buffer.
+  Owned(usize, usize),
+}
+
+impl<'a> Segments<'a> {
+  /// Gets the total byte length of the segments.
+  pub fn len(&self) -> usize {
+    self.len
+  }
+
+  pub fn is_empty(&self) -> bool {
+    self.len == 0
+  }
+
+  /// Iterates over the segments in order.
+  pub fn iter(&self) -> impl Iterator<Item = &str> + '_ {
+    self.segments.iter().map(|segment| match segment {
+      Segment::Borrowed(text) => *text,
+      Segment::Owned(start, end) => &self.buffer[*start..*end],
+    })
+  }
+
+  /// Gets the segments as slices for vectored writes.
+  pub fn io_slices(&self) -> Vec<std::io::IoSlice<'_>> {
+    self
+      .iter()
+      .map(|text| std::io::IoSlice::new(text.as_bytes()))
+      .collect()
+  }
+
+  /// Splits the segment containing the byte offset, returning the index
+  /// of the segment that starts at it.
+  fn split_at(&mut self, offset: usize) -> usize {
+    let mut pos = 0;
+    for i in 0..self.segments.len() {
+      if pos == offset {
+        return i;
+      }
+      let segment = self.segments[i];
+      let (len, first, second) = match segment {
+        Segment::Borrowed(text) => {
+          let keep = offset.saturating_sub(pos).min(text.len());
+          (
+            text.len(),
+            Segment::Borrowed(&text[..keep]),
+            Segment::Borrowed(&text[keep..]),
+          )
+        }
+        Segment::Owned(start, end) => {
+          let keep = offset.saturating_sub(pos).min(end - start);
+          (
+            end - start,
+            Segment::Owned(start, start + keep),
+            Segment::Owned(start + keep, end),
+          )
+        }
+      };
+      if offset < pos + len {
+        self.segments[i] = first;
+        self.segments.insert(i + 1, second);
+        return i + 1;
+      }
+      pos += len;
+    }
+    self.segments.len()
+  }
+
+  pub(crate) fn push_borrowed(&mut self, text: &'a str) {
+    if !text.is_empty() {
+      self.len += text.len();
+      self.segments.push(Segment::Borrowed(text));
+    }
+  }
+}
+
+impl StringTypeMut for Segments<'_> {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    if str.is_empty() {
+      return;
+    }
+    let start = self.buffer.len();
+    self.buffer.push_str(str);
+    let end = self.buffer.len();
+    self.len += str.len();
+    // extend the last segment when it's directly before this one
+    match self.segments.last_mut() {
+      Some(Segment::Owned(_, last_end)) if *last_end == start => {
+        *last_end = end;
+      }
+      _ => self.segments.push(Segment::Owned(start, end)),
+    }
+  }
+
+  fn len(&self) -> usize {
+    self.len
+  }
+
+  fn truncate(&mut self, len: usize) {
+    while self.len > len {
+      let Some(segment) = self.segments.pop() else {
+        break;
+      };
+      let segment_len = match segment {
+        Segment::Borrowed(text) => text.len(),
+        Segment::Owned(start, end) => end - start,
+      };
+      let segment_start = self.len - segment_len;
+      self.len = segment_start;
+      if segment_start < len {
+        // keep the start of the segment
+        let keep = len - segment_start;
+        self.len = len;
+        match segment {
+          Segment::Borrowed(text) => {
+            self.segments.push(Segment::Borrowed(&text[..keep]));
+          }
+          Segment::Owned(start, end) => {
+            if end == self.buffer.len() {
+              self.buffer.truncate(start + keep);
+            }
+            self.segments.push(Segment::Owned(start, start + keep));
+          }
+        }
+      } else if let Segment::Owned(start, end) = segment {
+        if end == self.buffer.len() {
+          self.buffer.truncate(start);
+        }
+      }
+    }
+  }
+
+  fn move_to(&mut self, start: usize, index: usize) {
+    assert!(index <= start);
+    let index = self.split_at(index);
+    let start = self.split_at(start);
+    self.segments[index..].rotate_left(start - index);
+  }
+}
+
+impl<'a> StringBuilder<'a, String> {
+  /// Runs the closure once, collecting the appended strings as segments
+  /// instead of concatenating them (ex. for vectored writes).
+  pub fn build_segments(
+    build: impl FnOnce(&mut StringBuilder<'a, String>),
+  ) -> Segments<'a> {
+    let mut segments = Segments::default();
+    let mut state = StringBuilder::with_mode(
+      // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+      Mode::Segments(unsafe {
+        std::mem::transmute::<&mut Segments<'a>, &mut Segments<'a>>(
+          &mut segments,
+        )
+      }),
+    );
+    build(&mut state);
+    if let Err(err) = state.check_error() {
+      panic!("{}", err);
+    }
+    segments
+  }
+}
@@ -0,0 +1,618 @@
+//! Outputs that consume the built bytes without keeping them.
+
+use std::collections::TryReserveError;
+use std::hash::Hasher;
+#[cfg(feature = "rayon")]
+use std::mem::MaybeUninit;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+use crate::Mode;
+use crate::StringBuilder;
+use crate::StringTypeMut;
+
+impl<'a> StringBuilder<'a, String> {
+  /// Gets if the text built by the closure would equal the expected text
+  /// without building it.
+  ///
+  /// The closure is only run once, unless it truncates or inserts text, in
+  /// which case it's run again holding back the edited text.
+  pub fn matches(
+    expected: &str,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> bool {
+    let sink = Self::build_to_new_sink(|| MatchSink::new(expected), build);
+    sink.matches && sink.pos == expected.len()
+  }
+
+  /// Gets if the text built by the closure would start with the prefix
+  /// without building it.
+  ///
+  /// See `matches` for how often the closure is run.
+  pub fn starts_with(
+    prefix: &str,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> bool {
+    let sink = Self::build_to_new_sink(|| MatchSink::new(prefix), build);
+    sink.matches && sink.pos >= prefix.len()
+  }
+
+  /// Gets if the text built by the closure would end with the suffix
+  /// without building it.
+  ///
+  /// See `matches` for how often the closure is run.
+  pub fn ends_with(
+    suffix: &str,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> bool {
+    let sink = Self::build_to_new_sink(
+      || TailSink {
+        keep: suffix.len(),
+        tail: Vec::new(),
+        len: 0,
+      },
+      build,
+    );
+    sink.len >= suffix.len()
+      && sink.tail[sink.tail.len() - suffix.len()..] == *suffix.as_bytes()
+  }
+
+  /// Feeds the text built by the closure to the hasher without building it.
+  ///
+  /// The closure is only run once, so truncating or inserting text panics
+  /// as the text was already hashed. Each append is written separately, so use a hasher whose output
+  /// doesn't depend on how the input is split (ex. `DefaultHasher`) to get
+  /// the same result as writing the built text's bytes with
+  /// `Hasher::write`. This differs from `Hash::hash` for the text, as
+  /// `str` also writes a trailing `0xff`.
+  pub fn hash_into(
+    hasher: &mut impl Hasher,
+    build: impl FnOnce(&mut StringBuilder<'a, String>),
+  ) {
+    let mut sink = HashSink { hasher, len: 0 };
+    Self::run_sink(&mut sink, build);
+  }
+
+  /// Runs the closure once writing the text to a new sink, which is only
+  /// discarded for another sink when the closure truncates or inserts text.
+  ///
+  /// The edits found on the first pass are then held back when writing to
+  /// the second sink, so edits are only planned when there are any.
+  fn build_to_new_sink<TSink: StringTypeMut>(
+    new_sink: impl Fn() -> TSink,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> TSink {
+    let mut sink = EditDetector {
+      sink: new_sink(),
+      len: 0,
+      edited: false,
+    };
+    let plan = Self::run_sink(&mut sink, &build);
+    if !sink.edited {
+      return sink.sink;
+    }
+    let mut sink = new_sink();
+    Self::fill_sink(&plan, &mut sink, build);
+    sink
+  }
+
+  /// Runs the closure twice, writing the text to the sink on the second
+  /// pass.
+  ///
+  /// The capacity pass finds where the text is truncated, so that only the
+  /// final text is written to the sink.
+  pub(crate) fn build_to_sink(
+    sink: &mut dyn StringTypeMut,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> SinkPlan {
+    let plan = Self::plan_sink(&build);
+    Self::fill_sink(&plan, sink, build);
+    plan
+  }
+
+  /// Runs the capacity pass of a build to a sink.
+  pub(crate) fn plan_sink(
+    build: impl FnOnce(&mut StringBuilder<'a, String>),
+  ) -> SinkPlan {
+    let mut state = StringBuilder::with_mode(Mode::Capacity);
+    build(&mut state);
+    SinkPlan {
+      capacity: state.capacity,
+      alloc_capacity: state.alloc_capacity(),
+      floors: SinkPlan::floors(std::mem::take(&mut state.edits)),
+    }
+  }
+
+  /// Runs the closure once writing the final text to the sink.
+  pub(crate) fn fill_sink(
+    plan: &SinkPlan,
+    sink: &mut dyn StringTypeMut,
+    build: impl FnOnce(&mut StringBuilder<'a, String>),
+  ) {
+    if !plan.has_edits() {
+      Self::run_sink(sink, build);
+    } else {
+      let mut buffer = EditBuffer {
+        sink,
+        floors: &plan.floors,
+        pending: String::new(),
+        flushed: 0,
+      };
+      Self::run_sink(&mut buffer, build);
+      buffer.finish();
+    }
+  }
+
+  /// Formats the string like `fmt`, but supports truncating and
+  /// inserting text.
+  ///
+  /// A capacity pass runs first to find any text that's truncated or
+  /// moved, which is held back until it's final.
+  pub fn fmt_with_edits(
+    fmt: &mut std::fmt::Formatter<'_>,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> std::fmt::Result {
+    let mut sink = FormatSink {
+      fmt,
+      len: 0,
+      result: Ok(()),
+    };
+    Self::build_to_sink(&mut sink, build);
+    sink.result
+  }
+
+  /// Runs the closure once writing directly to a sink that supports
+  /// truncating and moving text.
+  ///
+  /// Returns the plan for writing the same text to a sink that doesn't.
+  pub(crate) fn run_sink(
+    sink: &mut dyn StringTypeMut,
+    build: impl FnOnce(&mut StringBuilder<'a, String>),
+  ) -> SinkPlan {
+    let mut state = StringBuilder::with_mode(
+      // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+      Mode::Sink(unsafe {
+        std::mem::transmute::<
+          &mut (dyn StringTypeMut + '_),
+          &mut (dyn StringTypeMut + '_),
+        >(sink)
+      }),
+    );
+    build(&mut state);
+    // sinks have no way to surface the error
+    if let Err(err) = state.check_error() {
+      panic!("{}", err);
+    }
+    let capacity = state.len();
+    SinkPlan {
+      capacity,
+      alloc_capacity: capacity,
+      floors: SinkPlan::floors(std::mem::take(&mut state.edits)),
+    }
+  }
+}
+
+/// The result of the capacity pass of a build to a sink.
+pub(crate) struct SinkPlan {
+  /// The length of the built text.
+  pub capacity: usize,
+  /// The capacity to reserve, which includes the largest length reached
+  /// before truncating.
+  pub alloc_capacity: usize,
+  /// The lowest position changed by each edit or any edit after it.
+  floors: Vec<usize>,
+}
+
+impl SinkPlan {
+  /// Gets if the text is truncated or moved after being written.
+  pub fn has_edits(&self) -> bool {
+    !self.floors.is_empty()
+  }
+
+  /// Gets the lowest position edited from each edit onward.
+  fn floors(mut edits: Vec<usize>) -> Vec<usize> {
+    for i in (1..edits.len()).rev() {
+      edits[i - 1] = std::cmp::min(edits[i - 1], edits[i]);
+    }
+    edits
+  }
+}
+
+/// Holds the text written to a sink that a later edit may still change,
+/// writing it to the sink once it's final.
+struct EditBuffer<'s, 'p> {
+  sink: &'s mut dyn StringTypeMut,
+  floors: &'p [usize],
+  pending: String,
+  /// The length written to the sink.
+  flushed: usize,
+}
+
+impl EditBuffer<'_, '_> {
+  fn floor(&self) -> usize {
+    self.floors.first().copied().unwrap_or(usize::MAX)
+  }
+
+  fn flush(&mut self) {
+    let len = std::cmp::min(
+      self.floor().saturating_sub(self.flushed),
+      self.pending.len(),
+    );
+    if len > 0 {
+      self.sink.push_str(&self.pending[..len]);
+      self.pending.drain(..len);
+      self.flushed += len;
+    }
+  }
+
+  fn edited(&mut self) {
+    self.floors = self.floors.get(1..).unwrap_or_default();
+    self.flush();
+  }
+
+  fn finish(mut self) {
+    self.floors = &[];
+    self.flush();
+  }
+}
+
+impl StringTypeMut for EditBuffer<'_, '_> {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    if self.pending.is_empty() && self.flushed + str.len() <= self.floor() {
+      self.sink.push_str(str);
+      self.flushed += str.len();
+    } else {
+      self.pending.push_str(str);
+      self.flush();
+    }
+  }
+
+  fn len(&self) -> usize {
+    self.flushed + self.pending.len()
+  }
+
+  fn truncate(&mut self, len: usize) {
+    assert!(
+      len >= self.flushed,
+      "the text was truncated differently than on the capacity pass"
+    );
+    self.pending.truncate(len - self.flushed);
+    self.edited();
+  }
+
+  fn move_to(&mut self, start: usize, index: usize) {
+    assert!(
+      index >= self.flushed,
+      "the text was moved differently than on the capacity pass"
+    );
+    self
+      .pending
+      .move_to(start - self.flushed, index - self.flushed);
+    self.edited();
+  }
+}
+
+/// Writes the text to the inner sink until the text is first truncated or
+/// moved, which the inner sink can't handle.
+struct EditDetector<TSink: StringTypeMut> {
+  sink: TSink,
+  len: usize,
+  edited: bool,
+}
+
+impl<TSink: StringTypeMut> StringTypeMut for EditDetector<TSink> {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    if !self.edited {
+      self.sink.push_str(str);
+    }
+    self.len += str.len();
+  }
+
+  fn len(&self) -> usize {
+    self.len
+  }
+
+  fn truncate(&mut self, len: usize) {
+    self.len = std::cmp::min(self.len, len);
+    self.edited = true;
+  }
+
+  fn move_to(&mut self, _start: usize, _index: usize) {
+    self.edited = true;
+  }
+}
+
+/// Writes the text to a region of the spare capacity of a buffer.
+#[cfg(feature = "rayon")]
+pub(crate) struct SliceSink<'b> {
+  slice: &'b mut [MaybeUninit<u8>],
+  pos: usize,
+}
+
+#[cfg(feature = "rayon")]
+impl<'b> SliceSink<'b> {
+  pub fn new(slice: &'b mut [MaybeUninit<u8>]) -> Self {
+    SliceSink { slice, pos: 0 }
+  }
+
+  /// Gets if the whole region was written.
+  pub fn is_full(&self) -> bool {
+    self.pos == self.slice.len()
+  }
+}
+
+#[cfg(feature = "rayon")]
+impl StringTypeMut for SliceSink<'_> {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    let end = self.pos + str.len();
+    assert!(
+      end <= self.slice.len(),
+      "the text had a different length on the second pass"
+    );
+    // SAFETY: the region was checked to have room for the bytes
+    unsafe {
+      std::ptr::copy_nonoverlapping(
+        str.as_ptr(),
+        self.slice[self.pos..end].as_mut_ptr().cast::<u8>(),
+        str.len(),
+      );
+    }
+    self.pos = end;
+  }
+
+  fn len(&self) -> usize {
+    self.pos
+  }
+
+  fn truncate(&mut self, len: usize) {
+    self.pos = std::cmp::min(self.pos, len);
+  }
+}
+
+struct FormatSink<'f, 'b> {
+  fmt: &'f mut std::fmt::Formatter<'b>,
+  len: usize,
+  result: std::fmt::Result,
+}
+
+impl StringTypeMut for FormatSink<'_, '_> {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    // don't bother formatting the remaining text after an error
+    if self.result.is_ok() {
+      self.result = self.fmt.write_str(str);
+    }
+    self.len += str.len();
+  }
+
+  fn len(&self) -> usize {
+    self.len
+  }
+}
+
+/// Compares the start of the text with the expected text.
+struct MatchSink<'e> {
+  expected: &'e [u8],
+  pos: usize,
+  matches: bool,
+}
+
+impl<'e> MatchSink<'e> {
+  fn new(expected: &'e str) -> Self {
+    Self {
+      expected: expected.as_bytes(),
+      pos: 0,
+      matches: true,
+    }
+  }
+}
+
+impl StringTypeMut for MatchSink<'_> {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    let end = self.pos + str.len();
+    if self.matches && self.pos < self.expected.len() {
+      let stop = end.min(self.expected.len());
+      self.matches =
+        str.as_bytes()[..stop - self.pos] == self.expected[self.pos..stop];
+    }
+    self.pos = end;
+  }
+
+  fn len(&self) -> usize {
+    self.pos
+  }
+}
+
+/// Keeps the last `keep` bytes of the text.
+struct TailSink {
+  keep: usize,
+  tail: Vec<u8>,
+  len: usize,
+}
+
+impl StringTypeMut for TailSink {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    let bytes = str.as_bytes();
+    if bytes.len() >= self.keep {
+      self.tail.clear();
+      self
+        .tail
+        .extend_from_slice(&bytes[bytes.len() - self.keep..]);
+    } else {
+      self.tail.extend_from_slice(bytes);
+      // only drain once the buffer doubles so that it's amortized
+      if self.tail.len() >= self.keep * 2 {
+        self.tail.drain(..self.tail.len() - self.keep);
+      }
+    }
+    self.len += bytes.len();
+  }
+
+  fn len(&self) -> usize {
+    self.len
+  }
+}
+
+struct HashSink<'h, H: Hasher> {
+  hasher: &'h mut H,
+  len: usize,
+}
+
+impl<H: Hasher> StringTypeMut for HashSink<'_, H> {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    self.hasher.write(str.as_bytes());
+    self.len += str.len();
+  }
+
+  fn len(&self) -> usize {
+    self.len
+  }
+
+  fn truncate(&mut self, _len: usize) {
+    panic!("truncating is not supported when hashing with hash_into");
+  }
+
+  fn move_to(&mut self, _start: usize, _index: usize) {
+    panic!("inserting is not supported when hashing with hash_into");
+  }
+}
+
+pub(crate) use hash_bytes::HashBytes;
+
+mod hash_bytes {
+  use super::*;
+
+  /// Output type of `BytesBuilder::hash_into`, which can't be named so
+  /// that it's only built by `hash_into`.
+  pub struct HashBytes(());
+
+  impl BytesType for HashBytes {
+    type MutType = HashBytesMut;
+
+    /// Creates an output that buffers the bytes (ex. for a length-prefixed
+    /// section whose length is needed before it's hashed).
+    fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+      let mut buffer = Vec::new();
+      buffer.try_reserve_exact(size)?;
+      Ok(HashBytesMut::Buffer(buffer))
+    }
+
+    fn from_mut(_inner: Self::MutType) -> Self {
+      HashBytes(())
+    }
+  }
+
+  pub enum HashBytesMut {
+    Hasher {
+      hasher: &'static mut dyn Hasher,
+      len: usize,
+    },
+    Buffer(Vec<u8>),
+  }
+
+  impl BytesTypeMut for HashBytesMut {
+    #[inline(always)]
+    fn push(&mut self, c: u8) {
+      match self {
+        HashBytesMut::Hasher { hasher, len } => {
+          hasher.write_u8(c);
+          *len += 1;
+        }
+        HashBytesMut::Buffer(buffer) => buffer.push(c),
+      }
+    }
+
+    #[inline(always)]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+      match self {
+        HashBytesMut::Hasher { hasher, len } => {
+          hasher.write(bytes);
+          *len += bytes.len();
+        }
+        HashBytesMut::Buffer(buffer) => buffer.extend_from_slice(bytes),
+      }
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+      match self {
+        HashBytesMut::Hasher { len, .. } => *len,
+        HashBytesMut::Buffer(buffer) => buffer.len(),
+      }
+    }
+
+    /// Panics for bytes that were already hashed, so checksums can only
+    /// be calculated within length-prefixed sections.
+    fn slices_from(&self, start: usize, mut f: impl FnMut(&[u8])) {
+      match self {
+        HashBytesMut::Hasher { .. } => panic!(
+          "reading previously appended bytes is not supported when hashing with hash_into"
+        ),
+        HashBytesMut::Buffer(buffer) => f(&buffer[start..]),
+      }
+    }
+  }
+}
+
+impl<'a> BytesBuilder<'a, HashBytes> {
+  /// Feeds the bytes built by the closure to the hasher without
+  /// building them.
+  ///
+  /// The closure is only run once. Length-prefixed sections are buffered
+  /// until their length is known. The result equals writing the built
+  /// bytes with `Hasher::write` (unlike `Hash::hash` for a slice, which
+  /// also writes the length) when the hasher doesn't depend on how the
+  /// input is split (ex. `DefaultHasher`). Checksums outside of
+  /// length-prefixed sections are not supported and panic.
+  pub fn hash_into(
+    hasher: &mut impl Hasher,
+    build: impl FnOnce(&mut BytesBuilder<'a, HashBytes>),
+  ) {
+    let hasher: &mut dyn Hasher = hasher;
+    let mut bytes = hash_bytes::HashBytesMut::Hasher {
+      // SAFETY: the hasher outlives the builder, which doesn't escape this function
+      hasher: unsafe {
+        std::mem::transmute::<&mut dyn Hasher, &'static mut dyn Hasher>(hasher)
+      },
+      len: 0,
+    };
+    let mut builder = BytesBuilder::with_bytes(
+      // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+      Some(unsafe {
+        std::mem::transmute::<
+          &mut hash_bytes::HashBytesMut,
+          &mut hash_bytes::HashBytesMut,
+        >(&mut bytes)
+      }),
+    );
+    build(&mut builder);
+  }
+}
@@ -0,0 +1,237 @@
+//! Outputs that write into a buffer provided by the caller.
+
+use std::collections::TryReserveError;
+
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+use crate::Error;
+use crate::StringBuilder;
+use crate::StringTypeMut;
+
+pub(crate) use slice_bytes::SliceBytes;
+use slice_bytes::SliceBytesMut;
+
+mod slice_bytes {
+  use super::*;
+
+  /// Output type of `BytesBuilder::build_in_place`, which can't be named so
+  /// that it's only built by `build_in_place`.
+  pub struct SliceBytes(());
+
+  impl BytesType for SliceBytes {
+    type MutType = SliceBytesMut;
+
+    fn with_capacity(_size: usize) -> Result<Self::MutType, TryReserveError> {
+      unreachable!("SliceBytes is only used by BytesBuilder::build_in_place");
+    }
+
+    fn from_mut(_inner: Self::MutType) -> Self {
+      SliceBytes(())
+    }
+  }
+
+  /// Writes bytes to the start of a slice.
+  pub struct SliceBytesMut {
+    bytes: &'static mut [u8],
+    pub(super) len: usize,
+  }
+
+  impl SliceBytesMut {
+    /// Creates the output for a slice that outlives it.
+    ///
+    /// # Safety
+    ///
+    /// The slice must outlive the returned value.
+    pub(super) unsafe fn new(bytes: &mut [u8]) -> Self {
+      Self {
+        bytes: std::mem::transmute::<&mut [u8], &'static mut [u8]>(bytes),
+        len: 0,
+      }
+    }
+  }
+
+  impl BytesTypeMut for SliceBytesMut {
+    #[inline(always)]
+    fn push(&mut self, c: u8) {
+      self.extend_from_slice(&[c]);
+    }
+
+    #[inline(always)]
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+      let end = self.len + bytes.len();
+      assert!(
+        end <= self.bytes.len(),
+        "the bytes exceeded the length of the provided buffer"
+      );
+      self.bytes[self.len..end].copy_from_slice(bytes);
+      self.len = end;
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+      self.len
+    }
+
+    fn slices_from(&self, start: usize, mut f: impl FnMut(&[u8])) {
+      f(&self.bytes[start..self.len]);
+    }
+  }
+
+  impl StringTypeMut for SliceBytesMut {
+    #[inline(always)]
+    fn push(&mut self, c: char) {
+      self.push_str(c.encode_utf8(&mut [0; 4]));
+    }
+
+    #[inline(always)]
+    fn push_str(&mut self, str: &str) {
+      self.extend_from_slice(str.as_bytes());
+    }
+
+    #[inline(always)]
+    fn len(&self) -> usize {
+      self.len
+    }
+
+    fn truncate(&mut self, len: usize) {
+      self.len = std::cmp::min(self.len, len);
+    }
+
+    fn move_to(&mut self, start: usize, index: usize) {
+      assert!(index <= start && start <= self.len);
+      self.bytes[index..self.len].rotate_right(self.len - start);
+    }
+  }
+}
+
+/// The result of building into a raw buffer with `build_into_raw`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BuildRawResult {
+  /// The number of bytes the buffer requires, which includes the largest
+  /// length reached before truncating.
+  pub needed: usize,
+  /// The number of bytes written, which is `0` when the buffer was too
+  /// small.
+  pub written: usize,
+}
+
+impl<'a> StringBuilder<'a, String> {
+  /// Builds the text into a raw buffer, which is shaped for exposing
+  /// builders over FFI.
+  ///
+  /// When `cap` is smaller than the needed length, nothing is written and
+  /// the caller can call again with a buffer of at least `needed` bytes.
+  /// No nul terminator is written, but one can be appended.
+  ///
+  /// # Safety
+  ///
+  /// `buf` must be valid for writes of `cap` bytes unless `cap` is `0`, in
+  /// which case it may be null.
+  pub unsafe fn build_into_raw(
+    buf: *mut u8,
+    cap: usize,
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> BuildRawResult {
+    let plan = Self::plan_sink(&build);
+    let needed = plan.alloc_capacity;
+    if cap < needed || needed == 0 {
+      return BuildRawResult { needed, written: 0 };
+    }
+    let mut bytes =
+      SliceBytesMut::new(std::slice::from_raw_parts_mut(buf, cap));
+    Self::run_sink(&mut bytes, build);
+    crate::mismatch::check(plan.capacity, bytes.len);
+    debug_assert_eq!(plan.capacity, bytes.len);
+    BuildRawResult {
+      needed,
+      written: bytes.len,
+    }
+  }
+}
+
+impl<'a> BytesBuilder<'a, SliceBytes> {
+  /// Builds the bytes directly into a buffer allocated by the caller.
+  ///
+  /// The capacity pass runs first and then `alloc` is called with the
+  /// calculated capacity to allocate the buffer (ex. a memory-mapped file
+  /// of that size), which the second pass fills in place. The bytes are
+  /// written to the start of the buffer, so a larger buffer is allowed.
+  ///
+  /// ```
+  /// use capacity_builder::BytesBuilder;
+  ///
+  /// let buffer = BytesBuilder::build_in_place(
+  ///   |size| Ok(vec![0; size].into_boxed_slice()),
+  ///   |builder| {
+  ///     builder.append("Hello");
+  ///     builder.append_be(1u16);
+  ///   },
+  /// )
+  /// .unwrap();
+  /// assert_eq!(&*buffer, b"Hello\0\x01");
+  /// ```
+  pub fn build_in_place<TBuffer: AsMut<[u8]>>(
+    alloc: impl FnOnce(usize) -> std::io::Result<TBuffer>,
+    build: impl Fn(&mut BytesBuilder<'a, SliceBytes>),
+  ) -> Result<TBuffer, Error> {
+    let mut builder = BytesBuilder::with_bytes(None);
+    build(&mut builder);
+    let mut buffer = alloc(builder.capacity)?;
+    let buffer_len = buffer.as_mut().len();
+    if buffer_len < builder.capacity {
+      return Err(Error::CapacityExceeded {
+        size: builder.capacity,
+        max: buffer_len,
+      });
+    }
+    // SAFETY: the buffer outlives the builder, which doesn't escape this function
+    let mut bytes = unsafe { SliceBytesMut::new(buffer.as_mut()) };
+    // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+    builder.bytes = Some(unsafe {
+      std::mem::transmute::<&mut SliceBytesMut, &mut SliceBytesMut>(&mut bytes)
+    });
+    build(&mut builder);
+    crate::mismatch::check_with_offset(
+      builder.capacity,
+      bytes.len,
+      builder.overflow,
+    );
+    debug_assert_eq!(builder.capacity, bytes.len);
+    if let Some(err) = builder.io_error.take() {
+      return Err(Error::Io(err));
+    }
+    Ok(buffer)
+  }
+
+  /// Builds the bytes into a raw buffer like
+  /// `StringBuilder::build_into_raw`.
+  ///
+  /// # Safety
+  ///
+  /// `buf` must be valid for writes of `cap` bytes unless `cap` is `0`, in
+  /// which case it may be null.
+  pub unsafe fn build_into_raw(
+    buf: *mut u8,
+    cap: usize,
+    build: impl Fn(&mut BytesBuilder<'a, SliceBytes>),
+  ) -> BuildRawResult {
+    let mut needed = 0;
+    let result = Self::build_in_place(
+      |size| {
+        needed = size;
+        if cap < size || size == 0 {
+          // errors with the needed size when too small
+          Ok(&mut [][..])
+        } else {
+          Ok(std::slice::from_raw_parts_mut(buf, cap))
+        }
+      },
+      build,
+    );
+    BuildRawResult {
+      needed,
+      written: if result.is_ok() { needed } else { 0 },
+    }
+  }
+}
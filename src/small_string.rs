@@ -0,0 +1,227 @@
+//! A string stored inline when it fits in `N` bytes.
+
+use std::collections::TryReserveError;
+use std::fmt;
+use std::ops::Deref;
+
+use crate::StringAppendable;
+use crate::StringType;
+use crate::StringTypeMut;
+
+/// A string stored inline without allocating when it's at most `N` bytes,
+/// otherwise stored on the heap.
+///
+/// Because the capacity is calculated before building, building a
+/// `SmallString` decides up front whether to allocate:
+///
+/// ```
+/// use capacity_builder::SmallString;
+/// use capacity_builder::StringBuilder;
+///
+/// let id = StringBuilder::<SmallString<32>>::build(|builder| {
+///   builder.append("user-");
+///   builder.append(42);
+/// })
+/// .unwrap();
+/// assert_eq!(id, "user-42");
+/// assert!(id.is_inline());
+/// ```
+#[derive(Clone)]
+pub struct SmallString<const N: usize>(Repr<N>);
+
+#[derive(Clone)]
+enum Repr<const N: usize> {
+  Inline { bytes: [u8; N], len: usize },
+  Heap(String),
+}
+
+impl<const N: usize> SmallString<N> {
+  pub const fn new() -> Self {
+    Self(Repr::Inline {
+      bytes: [0; N],
+      len: 0,
+    })
+  }
+
+  pub fn as_str(&self) -> &str {
+    match &self.0 {
+      // SAFETY: only complete utf-8 strings are written to the bytes
+      Repr::Inline { bytes, len } => unsafe {
+        std::str::from_utf8_unchecked(&bytes[..*len])
+      },
+      Repr::Heap(text) => text,
+    }
+  }
+
+  /// Gets if the text is stored inline instead of on the heap.
+  pub fn is_inline(&self) -> bool {
+    matches!(self.0, Repr::Inline { .. })
+  }
+
+  /// Converts to a `String`, allocating when stored inline.
+  pub fn into_string(self) -> String {
+    match self.0 {
+      Repr::Inline { .. } => self.as_str().to_string(),
+      Repr::Heap(text) => text,
+    }
+  }
+
+  /// Moves the text to the heap, reserving space for `additional` bytes.
+  fn spill(&mut self, additional: usize) -> &mut String {
+    if let Repr::Inline { .. } = self.0 {
+      let mut text = String::with_capacity(self.len() + additional);
+      text.push_str(self.as_str());
+      self.0 = Repr::Heap(text);
+    }
+    match &mut self.0 {
+      Repr::Heap(text) => text,
+      Repr::Inline { .. } => unreachable!(),
+    }
+  }
+}
+
+impl<const N: usize> Default for SmallString<N> {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl<const N: usize> Deref for SmallString<N> {
+  type Target = str;
+
+  fn deref(&self) -> &str {
+    self.as_str()
+  }
+}
+
+impl<const N: usize> AsRef<str> for SmallString<N> {
+  fn as_ref(&self) -> &str {
+    self.as_str()
+  }
+}
+
+impl<const N: usize> fmt::Display for SmallString<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Display::fmt(self.as_str(), f)
+  }
+}
+
+impl<const N: usize> fmt::Debug for SmallString<N> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    fmt::Debug::fmt(self.as_str(), f)
+  }
+}
+
+impl<const N: usize> PartialEq for SmallString<N> {
+  fn eq(&self, other: &Self) -> bool {
+    self.as_str() == other.as_str()
+  }
+}
+
+impl<const N: usize> Eq for SmallString<N> {}
+
+impl<const N: usize> PartialEq<str> for SmallString<N> {
+  fn eq(&self, other: &str) -> bool {
+    self.as_str() == other
+  }
+}
+
+impl<const N: usize> PartialEq<&str> for SmallString<N> {
+  fn eq(&self, other: &&str) -> bool {
+    self.as_str() == *other
+  }
+}
+
+impl<const N: usize> std::hash::Hash for SmallString<N> {
+  fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+    self.as_str().hash(state)
+  }
+}
+
+impl<const N: usize> From<SmallString<N>> for String {
+  fn from(value: SmallString<N>) -> Self {
+    value.into_string()
+  }
+}
+
+impl<const N: usize> StringType for SmallString<N> {
+  type MutType = SmallString<N>;
+
+  #[inline(always)]
+  fn with_capacity(size: usize) -> Result<Self::MutType, TryReserveError> {
+    if size <= N {
+      Ok(Self::new())
+    } else {
+      let mut text = String::new();
+      text.try_reserve_exact(size)?;
+      Ok(Self(Repr::Heap(text)))
+    }
+  }
+
+  #[inline(always)]
+  fn from_mut(inner: Self::MutType) -> Self {
+    inner
+  }
+}
+
+impl<const N: usize> StringTypeMut for SmallString<N> {
+  #[inline(always)]
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  #[inline(always)]
+  fn push_str(&mut self, str: &str) {
+    match &mut self.0 {
+      Repr::Inline { bytes, len } if *len + str.len() <= N => {
+        bytes[*len..*len + str.len()].copy_from_slice(str.as_bytes());
+        *len += str.len();
+      }
+      // only happens when the built text exceeds the calculated capacity
+      _ => self.spill(str.len()).push_str(str),
+    }
+  }
+
+  #[inline(always)]
+  fn len(&self) -> usize {
+    match &self.0 {
+      Repr::Inline { len, .. } => *len,
+      Repr::Heap(text) => text.len(),
+    }
+  }
+
+  fn truncate(&mut self, new_len: usize) {
+    if new_len >= self.len() {
+      return;
+    }
+    assert!(self.as_str().is_char_boundary(new_len));
+    match &mut self.0 {
+      Repr::Inline { len, .. } => *len = new_len,
+      Repr::Heap(text) => text.truncate(new_len),
+    }
+  }
+
+  fn move_to(&mut self, start: usize, index: usize) {
+    let text = self.as_str();
+    assert!(text.is_char_boundary(start) && text.is_char_boundary(index));
+    assert!(index <= start);
+    match &mut self.0 {
+      // both rotated parts stay valid utf-8 as they start and end on
+      // char boundaries
+      Repr::Inline { bytes, len } => {
+        bytes[index..*len].rotate_right(*len - start)
+      }
+      Repr::Heap(text) => text.move_to(start, index),
+    }
+  }
+}
+
+impl<'a, const N: usize> StringAppendable<'a> for &'a SmallString<N> {
+  #[inline(always)]
+  fn append_to_builder<TString: StringType>(
+    self,
+    builder: &mut crate::StringBuilder<'a, TString>,
+  ) {
+    builder.append(self.as_str());
+  }
+}
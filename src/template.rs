@@ -0,0 +1,140 @@
+use std::sync::OnceLock;
+
+use crate::mismatch;
+use crate::BytesBuilder;
+use crate::BytesType;
+use crate::BytesTypeMut;
+use crate::Error;
+use crate::Mode;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+/// A string build closure that's rendered many times with inputs that
+/// produce the same length.
+///
+/// The capacity is calculated on the first render and reused afterwards
+/// so each subsequent render only runs the closure once. In debug builds,
+/// rendering a different length than the first render panics.
+pub struct StringTemplate<TInput: ?Sized, F, TString: StringType = String>
+where
+  F: for<'a> Fn(&mut StringBuilder<'a, TString>, &'a TInput),
+{
+  build: F,
+  sizes: OnceLock<Sizes>,
+  _marker: std::marker::PhantomData<fn(&TInput) -> TString>,
+}
+
+/// The sizes calculated on the first render.
+struct Sizes {
+  capacity: usize,
+  /// The largest length reached before truncating.
+  peak: usize,
+  /// Capacity reserved in addition to the built length.
+  additional: usize,
+}
+
+impl<TInput: ?Sized, F, TString: StringType> StringTemplate<TInput, F, TString>
+where
+  F: for<'a> Fn(&mut StringBuilder<'a, TString>, &'a TInput),
+{
+  pub fn new(build: F) -> Self {
+    Self {
+      build,
+      sizes: OnceLock::new(),
+      _marker: std::marker::PhantomData,
+    }
+  }
+
+  pub fn render(&self, input: &TInput) -> Result<TString, Error> {
+    let sizes = self.sizes.get_or_init(|| {
+      let mut state = StringBuilder::with_mode(Mode::Capacity);
+      (self.build)(&mut state, input);
+      Sizes {
+        capacity: state.capacity,
+        peak: state.peak,
+        additional: state.additional,
+      }
+    });
+    let capacity = sizes.capacity;
+    let mut state = StringBuilder::<TString>::with_mode(Mode::Capacity);
+    state.capacity = capacity;
+    state.peak = sizes.peak;
+    state.additional = sizes.additional;
+    let mut text = crate::alloc_string::<TString>(state.alloc_capacity())?;
+    // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+    state.mode = Mode::Text(unsafe {
+      std::mem::transmute::<
+        &mut <TString as StringType>::MutType,
+        &mut <TString as StringType>::MutType,
+      >(&mut text)
+    });
+    (self.build)(&mut state, input);
+    state.check_error()?;
+    mismatch::check_with_offset(capacity, text.len(), state.overflow);
+    debug_assert_eq!(
+      capacity,
+      text.len(),
+      "the template rendered a different length than the first render"
+    );
+    Ok(TString::from_mut(text))
+  }
+}
+
+/// A bytes build closure that's rendered many times with inputs that
+/// produce the same length.
+///
+/// See `StringTemplate`. The lengths of length-prefixed sections are also
+/// reused, so they must be the same for every input.
+pub struct BytesTemplate<TInput: ?Sized, F, TBytes: BytesType = Vec<u8>>
+where
+  F: for<'a> Fn(&mut BytesBuilder<'a, TBytes>, &'a TInput),
+{
+  build: F,
+  lens: OnceLock<(usize, Vec<usize>)>,
+  _marker: std::marker::PhantomData<fn(&TInput) -> TBytes>,
+}
+
+impl<TInput: ?Sized, F, TBytes: BytesType> BytesTemplate<TInput, F, TBytes>
+where
+  F: for<'a> Fn(&mut BytesBuilder<'a, TBytes>, &'a TInput),
+{
+  pub fn new(build: F) -> Self {
+    Self {
+      build,
+      lens: OnceLock::new(),
+      _marker: std::marker::PhantomData,
+    }
+  }
+
+  pub fn render(&self, input: &TInput) -> Result<TBytes, Error> {
+    let (capacity, section_lens) = self.lens.get_or_init(|| {
+      let mut builder = BytesBuilder::with_bytes(None);
+      (self.build)(&mut builder, input);
+      (builder.capacity, builder.section_lens)
+    });
+    let mut bytes = crate::alloc_bytes::<TBytes>(*capacity)?;
+    let mut builder = BytesBuilder::with_bytes(
+      // SAFETY: mutable interior whose lifetimes we don't want to expose in the public API
+      Some(unsafe {
+        std::mem::transmute::<
+          &mut <TBytes as BytesType>::MutType,
+          &mut <TBytes as BytesType>::MutType,
+        >(&mut bytes)
+      }),
+    );
+    builder.capacity = *capacity;
+    builder.section_lens = section_lens.clone();
+    (self.build)(&mut builder, input);
+    mismatch::check_with_offset(*capacity, bytes.len(), builder.overflow);
+    debug_assert_eq!(
+      *capacity,
+      bytes.len(),
+      "the template rendered a different length than the first render"
+    );
+    if let Some(err) = builder.io_error.take() {
+      return Err(Error::Io(err));
+    }
+    Ok(TBytes::from_mut(bytes))
+  }
+}
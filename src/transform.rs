@@ -0,0 +1,250 @@
+use std::ops::Bound;
+use std::ops::RangeBounds;
+
+use crate::StringAppendableValue;
+use crate::StringBuilder;
+use crate::StringType;
+use crate::StringTypeMut;
+
+/// Text that's transformed as it's written, so the capacity pass counts
+/// the transformed length without allocating.
+trait Transform {
+  fn write(
+    &self,
+    write: &mut impl FnMut(&str) -> std::fmt::Result,
+  ) -> std::fmt::Result;
+}
+
+struct Transformed<T: Transform>(T);
+
+impl<T: Transform> StringAppendableValue for Transformed<T> {
+  fn byte_len(&self) -> usize {
+    let mut len = 0;
+    let _ = self.0.write(&mut |value| {
+      len += value.len();
+      Ok(())
+    });
+    len
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let _ = self.0.write(&mut |value| {
+      text.push_str(value);
+      Ok(())
+    });
+  }
+
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    self.0.write(&mut |value| fmt.write_str(value))
+  }
+}
+
+/// Titlecases the first letter of each word separated by whitespace and
+/// lowercases the rest of the word.
+struct TitleCase<'b>(&'b str);
+
+impl Transform for TitleCase<'_> {
+  fn write(
+    &self,
+    write: &mut impl FnMut(&str) -> std::fmt::Result,
+  ) -> std::fmt::Result {
+    let mut start = 0;
+    let mut is_word_start = true;
+    for (index, c) in self.0.char_indices() {
+      if !is_word_start {
+        write_mapped(self.0, &mut start, index, c, c.to_lowercase(), write)?;
+      } else if let Some(title) = title_case_digraph(c) {
+        let title = std::iter::once(title);
+        write_mapped(self.0, &mut start, index, c, title, write)?;
+      } else {
+        write_mapped(self.0, &mut start, index, c, c.to_uppercase(), write)?;
+      }
+      is_word_start = c.is_whitespace();
+    }
+    write(&self.0[start..])
+  }
+}
+
+/// Gets the titlecase form of the characters that have one separate from
+/// their uppercase form (ex. `ǆ` becomes `ǅ` rather than `Ǆ`).
+fn title_case_digraph(c: char) -> Option<char> {
+  match c {
+    'Ǆ' | 'ǅ' | 'ǆ' => Some('ǅ'),
+    'Ǉ' | 'ǈ' | 'ǉ' => Some('ǈ'),
+    'Ǌ' | 'ǋ' | 'ǌ' => Some('ǋ'),
+    'Ǳ' | 'ǲ' | 'ǳ' => Some('ǲ'),
+    _ => None,
+  }
+}
+
+/// Writes the mapped characters in place of the character at the index,
+/// or leaves it to be written with the unchanged text when they're the same.
+fn write_mapped(
+  text: &str,
+  start: &mut usize,
+  index: usize,
+  c: char,
+  mapped: impl Iterator<Item = char> + Clone,
+  write: &mut impl FnMut(&str) -> std::fmt::Result,
+) -> std::fmt::Result {
+  let mut unchanged = mapped.clone();
+  if unchanged.next() == Some(c) && unchanged.next().is_none() {
+    return Ok(());
+  }
+  write(&text[*start..index])?;
+  for mapped in mapped {
+    write(mapped.encode_utf8(&mut [0; 4]))?;
+  }
+  *start = index + c.len_utf8();
+  Ok(())
+}
+
+/// Replaces each run of whitespace with a single space.
+struct CollapseWhitespace<'b>(&'b str);
+
+impl Transform for CollapseWhitespace<'_> {
+  fn write(
+    &self,
+    write: &mut impl FnMut(&str) -> std::fmt::Result,
+  ) -> std::fmt::Result {
+    let mut start = 0;
+    // the start of the current run of whitespace
+    let mut run_start = None;
+    for (index, c) in self.0.char_indices() {
+      if c.is_whitespace() {
+        if run_start.is_none() {
+          run_start = Some(index);
+        }
+      } else if let Some(run) = run_start.take() {
+        // keep single spaces as is to write fewer pieces
+        if &self.0[run..index] != " " {
+          write(&self.0[start..run])?;
+          write(" ")?;
+          start = index;
+        }
+      }
+    }
+    match run_start {
+      Some(run) if &self.0[run..] != " " => {
+        write(&self.0[start..run])?;
+        write(" ")
+      }
+      _ => write(&self.0[start..]),
+    }
+  }
+}
+
+/// Writes the grapheme clusters in reverse order.
+#[cfg(feature = "unicode-segmentation")]
+struct Reversed<'b>(&'b str);
+
+#[cfg(feature = "unicode-segmentation")]
+impl Reversed<'_> {
+  fn write(
+    &self,
+    mut write: impl FnMut(&str) -> std::fmt::Result,
+  ) -> std::fmt::Result {
+    use unicode_segmentation::UnicodeSegmentation;
+
+    for grapheme in self.0.graphemes(true).rev() {
+      write(grapheme)?;
+    }
+    Ok(())
+  }
+}
+
+#[cfg(feature = "unicode-segmentation")]
+impl StringAppendableValue for Reversed<'_> {
+  #[inline(always)]
+  fn byte_len(&self) -> usize {
+    self.0.len()
+  }
+
+  fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+    let _ = self.write(|value| {
+      text.push_str(value);
+      Ok(())
+    });
+  }
+
+  fn write_to_formatter(
+    &self,
+    fmt: &mut std::fmt::Formatter<'_>,
+  ) -> std::fmt::Result {
+    self.write(|value| fmt.write_str(value))
+  }
+}
+
+impl<'a, TString: StringType> StringBuilder<'a, TString> {
+  /// Appends the text without leading and trailing whitespace.
+  #[inline(always)]
+  pub fn append_trimmed(&mut self, value: &'a str) {
+    self.append(value.trim());
+  }
+
+  /// Appends the text without leading whitespace.
+  #[inline(always)]
+  pub fn append_trimmed_start(&mut self, value: &'a str) {
+    self.append(value.trim_start());
+  }
+
+  /// Appends the text without trailing whitespace.
+  #[inline(always)]
+  pub fn append_trimmed_end(&mut self, value: &'a str) {
+    self.append(value.trim_end());
+  }
+
+  /// Appends the characters in the range of character indexes. Indexes
+  /// past the end of the text are clamped.
+  pub fn append_char_range(
+    &mut self,
+    value: &'a str,
+    range: impl RangeBounds<usize>,
+  ) {
+    let byte_index = |char_index: usize| {
+      value
+        .char_indices()
+        .nth(char_index)
+        .map(|(index, _)| index)
+        .unwrap_or(value.len())
+    };
+    let start = match range.start_bound() {
+      Bound::Included(start) => byte_index(*start),
+      Bound::Excluded(start) => byte_index(start.saturating_add(1)),
+      Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+      Bound::Included(end) => byte_index(end.saturating_add(1)),
+      Bound::Excluded(end) => byte_index(*end),
+      Bound::Unbounded => value.len(),
+    };
+    self.append(&value[start..end.max(start)]);
+  }
+
+  /// Appends the text with each run of whitespace replaced by a single
+  /// space. Leading and trailing whitespace is collapsed, but not removed.
+  pub fn append_collapse_whitespace(&mut self, value: &str) {
+    self.append_value(Transformed(CollapseWhitespace(value)));
+  }
+
+  /// Appends the text with its grapheme clusters in reverse order.
+  #[cfg(feature = "unicode-segmentation")]
+  pub fn append_reversed(&mut self, value: &str) {
+    self.append_value(Reversed(value));
+  }
+
+  /// Appends the text with the first letter of each word titlecased and
+  /// the rest of the word lowercased.
+  ///
+  /// Words are separated by whitespace. The digraphs like `ǆ` become their
+  /// titlecase form (`ǅ`), while other letters are uppercased, which may
+  /// change the length (ex. `ß` becomes `SS` rather than `Ss`). The
+  /// capacity accounts for this. Letters are mapped one at a time, so
+  /// the context of a letter isn't considered (ex. a final `Σ` becomes `σ`).
+  pub fn append_title_case(&mut self, value: &str) {
+    self.append_value(Transformed(TitleCase(value)));
+  }
+}
@@ -0,0 +1,142 @@
+use crate::Error;
+use crate::StringBuilder;
+use crate::StringTypeMut;
+
+impl<'a> StringBuilder<'a, String> {
+  /// Builds UTF-16 code units with a capacity counted in code units, ex.
+  /// for wide strings in the Windows API.
+  ///
+  /// The code units are counted on the first pass and encoded on the
+  /// second. The closure is only run a third time to count the code units
+  /// when non-ASCII text is partly truncated or text is truncated after
+  /// inserting. Note that `len()` in the closure is still the length in
+  /// UTF-8 bytes.
+  pub fn build_utf16(
+    build: impl Fn(&mut StringBuilder<'a, String>),
+  ) -> Result<Vec<u16>, Error> {
+    let mut counter = Utf16Counter {
+      len: 0,
+      excess: 0,
+      non_ascii: Vec::new(),
+      moved: false,
+      exact: true,
+    };
+    let plan = Self::run_sink(&mut counter, &build);
+    let capacity = if counter.exact {
+      counter.len - counter.excess
+    } else {
+      let mut sink = Utf16Sink {
+        len: 0,
+        units: 0,
+        buffer: None,
+      };
+      Self::fill_sink(&plan, &mut sink, &build);
+      sink.units
+    };
+    let mut buffer = Vec::new();
+    buffer
+      .try_reserve_exact(capacity)
+      .map_err(Error::reserve(capacity))?;
+    let mut sink = Utf16Sink {
+      len: 0,
+      units: 0,
+      buffer: Some(buffer),
+    };
+    Self::fill_sink(&plan, &mut sink, &build);
+    let buffer = sink.buffer.unwrap();
+    crate::mismatch::check(capacity, buffer.len());
+    debug_assert_eq!(capacity, buffer.len());
+    Ok(buffer)
+  }
+}
+
+/// Counts the code units of the final text on the first pass.
+///
+/// The code units are counted as the UTF-8 length less the bytes that
+/// don't have their own code unit, so only the appends of non-ASCII text
+/// are logged to know how many of those bytes are removed by a truncate.
+struct Utf16Counter {
+  len: usize,
+  /// The UTF-8 bytes that don't have their own code unit.
+  excess: usize,
+  /// The start, end and prior excess of each non-ASCII append.
+  non_ascii: Vec<(usize, usize, usize)>,
+  moved: bool,
+  /// Whether the code units could be tracked through every truncate.
+  exact: bool,
+}
+
+impl StringTypeMut for Utf16Counter {
+  fn push(&mut self, c: char) {
+    self.push_str(c.encode_utf8(&mut [0; 4]));
+  }
+
+  fn push_str(&mut self, str: &str) {
+    if !str.is_ascii() {
+      let excess = str.len() - str.encode_utf16().count();
+      self
+        .non_ascii
+        .push((self.len, self.len + str.len(), self.excess));
+      self.excess += excess;
+    }
+    self.len += str.len();
+  }
+
+  fn len(&self) -> usize {
+    self.len
+  }
+
+  fn truncate(&mut self, len: usize) {
+    if len >= self.len {
+      return;
+    }
+    // the logged positions are out of date once text is moved
+    if self.moved {
+      self.exact = false;
+    }
+    while let Some(&(start, end, excess)) = self.non_ascii.last() {
+      if end <= len {
+        break;
+      }
+      if start < len {
+        self.exact = false;
+      }
+      self.non_ascii.pop();
+      self.excess = excess;
+    }
+    self.len = len;
+  }
+
+  fn move_to(&mut self, _start: usize, _index: usize) {
+    self.moved = true;
+  }
+}
+
+/// Counts the code units of the final text or encodes them.
+struct Utf16Sink {
+  len: usize,
+  units: usize,
+  buffer: Option<Vec<u16>>,
+}
+
+impl StringTypeMut for Utf16Sink {
+  fn push(&mut self, c: char) {
+    self.len += c.len_utf8();
+    match &mut self.buffer {
+      Some(buffer) => buffer.extend_from_slice(c.encode_utf16(&mut [0; 2])),
+      None => self.units += c.len_utf16(),
+    }
+  }
+
+  fn push_str(&mut self, str: &str) {
+    self.len += str.len();
+    match &mut self.buffer {
+      Some(buffer) => buffer.extend(str.encode_utf16()),
+      None => self.units += str.encode_utf16().count(),
+    }
+  }
+
+  fn len(&self) -> usize {
+    self.len
+  }
+}
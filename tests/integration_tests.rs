@@ -1,12 +1,21 @@
 use capacity_builder::BytesAppendable;
 use capacity_builder::BytesBuilder;
 use capacity_builder::BytesType;
+use capacity_builder::BytesTypeMut;
 use capacity_builder::CapacityDisplay;
+use capacity_builder::DurationUnit;
+use capacity_builder::HexDisplay;
+use capacity_builder::Memoized;
+use capacity_builder::PathBuilder;
+use capacity_builder::SmallString;
+use capacity_builder::Str;
 use capacity_builder::StringAppendable;
 use capacity_builder::StringAppendableValue;
 use capacity_builder::StringBuilder;
 use capacity_builder::StringType;
 use capacity_builder::StringTypeMut;
+use capacity_builder::TimestampPrecision;
+use capacity_builder::VecBuilder;
 
 #[derive(CapacityDisplay)]
 struct MyStruct;
@@ -123,6 +132,11 @@ fn string_append_with_replace() {
     ("package/test", "package", "test", "test/test"),
     // to greather than from
     ("package/test", "test", "package", "package/package"),
+    // multiple matches
+    ("a/b/c", "/", "--", "a--b--c"),
+    ("a/b/c", "/", "", "abc"),
+    // empty from matches between every char like `str::replace`
+    ("ab", "", "-", "-a-b-"),
   ];
   for (input, from, to, output) in cases {
     let text = StringBuilder::<String>::build(|builder| {
@@ -132,6 +146,8 @@ fn string_append_with_replace() {
     })
     .unwrap();
     assert_eq!(text, format!("testing{}testing", output));
+    assert_eq!(output, input.replace(from, to));
+    assert_eq!(text.capacity(), text.len());
   }
 }
 
@@ -189,6 +205,22 @@ fn box_slice() {
   assert_eq!(bytes, "hi there".as_bytes().to_vec().into_boxed_slice());
 }
 
+#[test]
+fn cow_output() {
+  use std::borrow::Cow;
+
+  let text = StringBuilder::<Cow<'static, str>>::build(|builder| {
+    builder.append("hi");
+  })
+  .unwrap();
+  assert!(matches!(&text, Cow::Owned(text) if text == "hi"));
+  let bytes = BytesBuilder::<Cow<'static, [u8]>>::build(|builder| {
+    builder.append("hi");
+  })
+  .unwrap();
+  assert!(matches!(&bytes, Cow::Owned(bytes) if bytes == b"hi"));
+}
+
 #[cfg(feature = "ecow")]
 #[test]
 fn to_string_helpers_ecow() {
@@ -207,3 +239,2778 @@ fn to_string_helpers_hipstr() {
     capacity_builder::appendable_to_string(&MyStruct);
   assert_eq!(text, "Hello there!");
 }
+
+#[cfg(feature = "arrayvec")]
+#[test]
+fn fixed_capacity_arrayvec() {
+  assert_fixed_capacity_outputs::<
+    arrayvec::ArrayString<4>,
+    arrayvec::ArrayVec<u8, 4>,
+  >();
+}
+
+#[cfg(feature = "heapless")]
+#[test]
+fn fixed_capacity_heapless() {
+  assert_fixed_capacity_outputs::<heapless::String<4>, heapless::Vec<u8, 4>>();
+}
+
+#[cfg(any(feature = "arrayvec", feature = "heapless"))]
+fn assert_fixed_capacity_outputs<
+  TString: StringType + AsRef<str> + std::fmt::Debug,
+  TBytes: BytesType + AsRef<[u8]> + std::fmt::Debug,
+>() {
+  use capacity_builder::Error;
+
+  let text = StringBuilder::<TString>::build(|builder| {
+    builder.append("Hi");
+    builder.append(' ');
+    builder.append(1);
+  })
+  .unwrap();
+  assert_eq!(text.as_ref(), "Hi 1");
+  let text = StringBuilder::<TString>::build(|builder| {
+    builder.append("Hey");
+    builder.truncate_to(2);
+    builder.append("!!");
+  })
+  .unwrap();
+  assert_eq!(text.as_ref(), "He!!");
+  let err = StringBuilder::<TString>::build(|builder| {
+    builder.append("Hello");
+  })
+  .unwrap_err();
+  assert!(matches!(err, Error::CapacityExceeded { size: 5, max: 4 }));
+
+  let bytes = BytesBuilder::<TBytes>::build(|builder| {
+    builder.append_be(1u16);
+    builder.append("hi");
+  })
+  .unwrap();
+  assert_eq!(bytes.as_ref(), &[0, 1, b'h', b'i']);
+  let err = BytesBuilder::<TBytes>::build(|builder| {
+    builder.append_le(1u32);
+    builder.append(0u8);
+  })
+  .unwrap_err();
+  assert!(matches!(err, Error::CapacityExceeded { size: 5, max: 4 }));
+}
+
+#[test]
+fn bytes_pad_to_alignment() {
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append("abc");
+    builder.pad_to_alignment(4, 0);
+    assert_eq!(builder.len(), 4);
+    builder.pad_to_alignment(4, 0);
+    assert_eq!(builder.len(), 4);
+    builder.append(1u8);
+    builder.pad_to_alignment(8, 0xFF);
+    assert_eq!(builder.len(), 8);
+  })
+  .unwrap();
+  assert_eq!(bytes, vec![b'a', b'b', b'c', 0, 1, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn bytes_varint() {
+  let cases: [(u64, &[u8]); 5] = [
+    (0, &[0]),
+    (1, &[1]),
+    (127, &[0x7F]),
+    (300, &[0xAC, 0x02]),
+    (
+      u64::MAX,
+      &[0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x01],
+    ),
+  ];
+  for (value, expected) in cases {
+    let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+      builder.append_varint(value);
+    })
+    .unwrap();
+    assert_eq!(bytes, expected);
+  }
+}
+
+#[test]
+fn bytes_protobuf() {
+  use capacity_builder::protobuf::WireType;
+
+  let long_text = "a".repeat(200);
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    // field 1: varint 150
+    builder.append_proto_tag(1, WireType::Varint);
+    builder.append_varint(150);
+    // field 2: embedded message with a string
+    builder.append_proto_len_delimited(2, |builder| {
+      builder.append_proto_len_delimited(1, |builder| {
+        builder.append("testing");
+      });
+    });
+    builder.append_proto_len_delimited(3, |builder| {
+      builder.append(long_text.as_str());
+    });
+  })
+  .unwrap();
+  let mut expected = vec![0x08, 0x96, 0x01, 0x12, 0x09, 0x0A, 0x07];
+  expected.extend_from_slice(b"testing");
+  expected.extend_from_slice(&[0x1A, 0xC8, 0x01]);
+  expected.extend_from_slice(long_text.as_bytes());
+  assert_eq!(bytes, expected);
+}
+
+#[test]
+fn bytes_msgpack() {
+  use capacity_builder::msgpack;
+
+  let long_text = "a".repeat(40);
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(msgpack::MapHeader(2));
+    builder.append(msgpack::Str("compact"));
+    builder.append(msgpack::Bool(true));
+    builder.append(msgpack::Str("schema"));
+    builder.append(msgpack::ArrayHeader(6));
+    builder.append(msgpack::UInt(0));
+    builder.append(msgpack::UInt(300));
+    builder.append(msgpack::Int(-5));
+    builder.append(msgpack::Int(-200));
+    builder.append(msgpack::Nil);
+    builder.append(msgpack::Bin(&[1, 2]));
+    builder.append(msgpack::Str(&long_text));
+  })
+  .unwrap();
+  let mut expected = vec![0x82, 0xa7];
+  expected.extend_from_slice(b"compact");
+  expected.extend_from_slice(&[0xc3, 0xa6]);
+  expected.extend_from_slice(b"schema");
+  expected.extend_from_slice(&[
+    0x96, 0x00, 0xcd, 0x01, 0x2c, 0xfb, 0xd1, 0xff, 0x38, 0xc0, 0xc4, 0x02,
+    0x01, 0x02, 0xd9, 40,
+  ]);
+  expected.extend_from_slice(long_text.as_bytes());
+  assert_eq!(bytes, expected);
+}
+
+#[test]
+fn bytes_cbor() {
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append_cbor_map_header(1);
+    builder.append_cbor_text("a");
+    builder.append_cbor_array_header(7);
+    builder.append_cbor_uint(10);
+    builder.append_cbor_uint(500);
+    builder.append_cbor_int(-1);
+    builder.append_cbor_int(-1000);
+    builder.append_cbor_bytes(&[1, 2]);
+    builder.append_cbor_bool(false);
+    builder.append_cbor_null();
+    builder.append_cbor_tag(1);
+    builder.append_cbor_uint(1_000_000);
+  })
+  .unwrap();
+  assert_eq!(
+    bytes,
+    vec![
+      0xa1, 0x61, b'a', 0x87, 0x0a, 0x19, 0x01, 0xf4, 0x20, 0x39, 0x03, 0xe7,
+      0x42, 0x01, 0x02, 0xf4, 0xf6, 0xc1, 0x1a, 0x00, 0x0f, 0x42, 0x40,
+    ]
+  );
+}
+
+#[test]
+fn bytes_markers() {
+  let (bytes, markers) =
+    BytesBuilder::<Vec<u8>>::build_with_markers(|builder| {
+      builder.append("abc");
+      let start = builder.mark();
+      assert_eq!(start.offset(), 3);
+      builder.append_be(1u32);
+      builder.mark();
+    })
+    .unwrap();
+  assert_eq!(bytes.len(), 7);
+  let offsets = markers.iter().map(|m| m.offset()).collect::<Vec<_>>();
+  assert_eq!(offsets, vec![3, 7]);
+}
+
+#[test]
+fn bytes_crc32() {
+  use capacity_builder::crc::Crc32Kind;
+
+  assert_eq!(capacity_builder::crc::crc32(b"123456789"), 0xCBF4_3926);
+  assert_eq!(capacity_builder::crc::crc32c(b"123456789"), 0xE306_9283);
+
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append("ab");
+    builder.append_crc32_be_of(Crc32Kind::Ieee, |builder| {
+      builder.append("1234");
+      builder.append("56789");
+    });
+    builder.append_crc32_le_of(Crc32Kind::Castagnoli, |builder| {
+      builder.append("123456789");
+    });
+  })
+  .unwrap();
+  let mut expected = b"ab123456789".to_vec();
+  expected.extend_from_slice(&0xCBF4_3926u32.to_be_bytes());
+  expected.extend_from_slice(b"123456789");
+  expected.extend_from_slice(&0xE306_9283u32.to_le_bytes());
+  assert_eq!(bytes, expected);
+}
+
+#[cfg(feature = "digest")]
+#[test]
+fn bytes_build_with_digest() {
+  use sha2::Digest;
+  use sha2::Sha256;
+
+  let (bytes, digest) =
+    BytesBuilder::<Vec<u8>>::build_with_digest::<Sha256>(|builder| {
+      builder.append("Hello");
+      builder.append(" there!");
+    })
+    .unwrap();
+  assert_eq!(bytes, b"Hello there!");
+  assert_eq!(digest, Sha256::digest(b"Hello there!"));
+
+  // checksums read back the bytes written through the digest
+  let (bytes, digest) =
+    BytesBuilder::<Vec<u8>>::build_with_digest::<Sha256>(|builder| {
+      builder.append_crc32_be_of(
+        capacity_builder::crc::Crc32Kind::Ieee,
+        |builder| builder.append("IEND"),
+      );
+    })
+    .unwrap();
+  assert_eq!(bytes, b"IEND\xae\x42\x60\x82");
+  assert_eq!(digest, Sha256::digest(&bytes));
+}
+
+#[test]
+fn bytes_framed() {
+  use capacity_builder::crc::crc32;
+  use capacity_builder::crc::Crc32Kind;
+  use capacity_builder::frame::Endianness;
+  use capacity_builder::frame::FrameLayout;
+  use capacity_builder::frame::LenPrefix;
+
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append_framed(FrameLayout::default(), |builder| {
+      assert_eq!(builder.len(), 4);
+      builder.append("payload");
+    });
+    builder.append_framed(
+      FrameLayout {
+        len_prefix: LenPrefix::U16(Endianness::Little),
+        checksum: Some((Crc32Kind::Ieee, Endianness::Little)),
+      },
+      |builder| builder.append("ab"),
+    );
+    builder.append_len_prefixed(LenPrefix::U8, |builder| {
+      builder.append("xyz");
+    });
+  })
+  .unwrap();
+  let mut expected = vec![0, 0, 0, 7];
+  expected.extend_from_slice(b"payload");
+  expected.extend_from_slice(&crc32(b"payload").to_be_bytes());
+  expected.extend_from_slice(&[2, 0, b'a', b'b']);
+  expected.extend_from_slice(&crc32(b"ab").to_le_bytes());
+  expected.extend_from_slice(&[3, b'x', b'y', b'z']);
+  assert_eq!(bytes, expected);
+}
+
+#[test]
+#[should_panic(expected = "exceeded the maximum")]
+fn bytes_len_prefixed_overflow() {
+  use capacity_builder::frame::LenPrefix;
+
+  let text = "a".repeat(256);
+  let _ = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append_len_prefixed(LenPrefix::U8, |builder| {
+      builder.append(text.as_str());
+    });
+  });
+}
+
+#[test]
+fn bytes_str_with_len_prefix() {
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append_str_u8_len("a");
+    builder.append_str_u16_be_len("bc");
+    builder.append_str_u16_le_len("d");
+    builder.append_str_u32_be_len("e");
+    builder.append_str_u32_le_len("");
+    builder.append_str_u64_le_len("f");
+  })
+  .unwrap();
+  assert_eq!(
+    bytes,
+    vec![
+      1, b'a', 0, 2, b'b', b'c', 1, 0, b'd', 0, 0, 0, 1, b'e', 0, 0, 0, 0, 1,
+      0, 0, 0, 0, 0, 0, 0, b'f'
+    ]
+  );
+}
+
+#[test]
+#[should_panic(expected = "exceeded the maximum")]
+fn bytes_str_with_len_prefix_overflow() {
+  let text = "a".repeat(256);
+  let _ = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append_str_u8_len(&text);
+  });
+}
+
+#[test]
+fn bytes_append_reader_exact() {
+  let data = b"Hello there!".to_vec();
+  let bytes = BytesBuilder::<Vec<u8>>::build_io(|builder| {
+    builder.append("a");
+    builder.append_reader_exact(data.as_slice(), 5);
+    builder.append("b");
+  })
+  .unwrap();
+  assert_eq!(bytes, b"aHellob");
+
+  let err = BytesBuilder::<Vec<u8>>::build_io(|builder| {
+    builder.append_reader_exact(data.as_slice(), data.len() + 1);
+    builder.append("b");
+  })
+  .unwrap_err();
+  assert_eq!(err.kind(), std::io::ErrorKind::UnexpectedEof);
+
+  let build = |builder: &mut BytesBuilder<'_, Vec<u8>>| {
+    builder.append_reader_exact(data.as_slice(), data.len() + 1);
+  };
+  let err = BytesBuilder::build(build).unwrap_err();
+  assert!(matches!(
+    err,
+    capacity_builder::Error::Io(err)
+      if err.kind() == std::io::ErrorKind::UnexpectedEof
+  ));
+  let err = BytesBuilder::build_with_markers(build).unwrap_err();
+  assert!(matches!(err, capacity_builder::Error::Io(_)));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)]
+fn bytes_append_file() {
+  let dir = std::env::temp_dir().join(format!(
+    "capacity_builder_append_file_{}",
+    std::process::id()
+  ));
+  std::fs::create_dir_all(&dir).unwrap();
+  let file_a = dir.join("a.txt");
+  let file_b = dir.join("b.txt");
+  std::fs::write(&file_a, "Hello").unwrap();
+  std::fs::write(&file_b, " there!").unwrap();
+
+  let bytes = BytesBuilder::<Vec<u8>>::build_io(|builder| {
+    builder.append_file(&file_a);
+    builder.append_file(&file_b);
+  })
+  .unwrap();
+  assert_eq!(bytes, b"Hello there!");
+
+  let err = BytesBuilder::<Vec<u8>>::build_io(|builder| {
+    builder.append_file(dir.join("missing.txt"));
+    builder.append_file(&file_a);
+  })
+  .unwrap_err();
+  assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+
+  let missing = dir.join("missing.txt");
+  let build = |builder: &mut BytesBuilder<'_, Vec<u8>>| {
+    builder.append_file(&missing);
+  };
+  let err = BytesBuilder::build(build).unwrap_err();
+  assert!(matches!(
+    err,
+    capacity_builder::Error::Io(err)
+      if err.kind() == std::io::ErrorKind::NotFound
+  ));
+  let err = BytesBuilder::build_in_place(
+    |size| Ok(vec![0; size]),
+    |builder| builder.append_file(&missing),
+  )
+  .unwrap_err();
+  assert!(matches!(err, capacity_builder::Error::Io(_)));
+
+  std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn derive_string_appendable() {
+  #[derive(capacity_builder::StringAppendable)]
+  #[append(format = "{major}.{minor}.{patch}{{{pre}}}")]
+  struct Version {
+    major: u64,
+    minor: u64,
+    patch: u64,
+    pre: String,
+  }
+
+  #[derive(capacity_builder::StringAppendable)]
+  struct Tagged<'b> {
+    #[append(prefix = "v", suffix = "-")]
+    version: &'b Version,
+    #[append(skip)]
+    _hidden: u8,
+    name: char,
+  }
+
+  #[derive(capacity_builder::StringAppendable)]
+  #[append(format = "{1}:{0}")]
+  struct Pair(u8, &'static str);
+
+  let version = Version {
+    major: 1,
+    minor: 20,
+    patch: 3,
+    pre: "alpha".to_string(),
+  };
+  let tagged = Tagged {
+    version: &version,
+    _hidden: 0,
+    name: 'x',
+  };
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(&tagged);
+    builder.append(' ');
+    builder.append(&Pair(5, "key"));
+  })
+  .unwrap();
+  assert_eq!(text, "v1.20.3{alpha}-x key:5");
+}
+
+#[test]
+fn derive_string_appendable_enum() {
+  #[derive(CapacityDisplay, capacity_builder::StringAppendable)]
+  enum Error {
+    #[append(format = "file not found: {0}")]
+    NotFound(&'static str),
+    #[append(format = "expected {expected} bytes, found {found}")]
+    Length {
+      expected: usize,
+      found: usize,
+    },
+    #[append(format = "unknown")]
+    Unknown,
+    Other,
+    Wrapped(#[append(prefix = "[")] char, #[append(suffix = "]")] u8),
+  }
+
+  assert_eq!(
+    Error::NotFound("a.txt").to_string(),
+    "file not found: a.txt"
+  );
+  assert_eq!(
+    Error::Length {
+      expected: 4,
+      found: 2
+    }
+    .to_string(),
+    "expected 4 bytes, found 2"
+  );
+  assert_eq!(Error::Unknown.to_string(), "unknown");
+  assert_eq!(Error::Other.to_string(), "Other");
+  assert_eq!(format!("{}", Error::Wrapped('a', 1)), "[a1]");
+}
+
+#[test]
+fn derive_bytes_appendable() {
+  #[derive(capacity_builder::BytesAppendable)]
+  #[bytes(le)]
+  struct Header<'b> {
+    #[bytes(be)]
+    magic: u32,
+    version: u16,
+    #[bytes(skip)]
+    _cached: u64,
+    #[bytes(len_prefix = "u8")]
+    name: &'b str,
+    #[bytes(len_prefix = "u16_be")]
+    payload: Vec<u8>,
+  }
+
+  #[derive(capacity_builder::BytesAppendable)]
+  struct Frame<'b>(&'b Header<'b>, u8, [u8; 2]);
+
+  let header = Header {
+    magic: 0xCAFE_BABE,
+    version: 2,
+    _cached: 0,
+    name: "ab",
+    payload: vec![9, 8, 7],
+  };
+  let frame = Frame(&header, 1, [2, 3]);
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(&frame);
+  })
+  .unwrap();
+  assert_eq!(
+    bytes,
+    vec![0xCA, 0xFE, 0xBA, 0xBE, 2, 0, 2, b'a', b'b', 0, 3, 9, 8, 7, 1, 2, 3]
+  );
+}
+
+#[test]
+fn capacity_display_generics() {
+  #[derive(CapacityDisplay)]
+  struct Wrapper<T>(T)
+  where
+    T: Copy;
+
+  impl<'a, T: StringAppendableValue + Copy + 'a> StringAppendable<'a>
+    for &'a Wrapper<T>
+  {
+    fn append_to_builder<TString: StringType>(
+      self,
+      builder: &mut StringBuilder<'a, TString>,
+    ) {
+      builder.append('<');
+      builder.append(self.0);
+      builder.append('>');
+    }
+  }
+
+  #[derive(CapacityDisplay, capacity_builder::StringAppendable)]
+  #[append(format = "{name}={value}")]
+  struct Pair<'b, V> {
+    name: &'b str,
+    value: V,
+  }
+
+  assert_eq!(Wrapper(5u8).to_string(), "<5>");
+  assert_eq!(format!("{}", Wrapper('a')), "<a>");
+  let pair = Pair {
+    name: "a",
+    value: "b".to_string(),
+  };
+  assert_eq!(pair.to_string(), "a=b");
+  assert_eq!(format!("{}", pair), "a=b");
+}
+
+#[test]
+fn capacity_display_no_inherent() {
+  #[derive(CapacityDisplay, capacity_builder::StringAppendable)]
+  #[capacity_display(no_inherent)]
+  #[append(format = "{0}!")]
+  struct Exclaim(&'static str);
+
+  impl Exclaim {
+    // would conflict with the generated inherent method
+    #[allow(dead_code)]
+    fn to_custom_string(&self) {}
+  }
+
+  assert_eq!(ToString::to_string(&Exclaim("hi")), "hi!");
+  assert_eq!(Exclaim("hi").to_string(), "hi!");
+}
+
+#[test]
+fn capacity_display_into_string() {
+  #[derive(CapacityDisplay, capacity_builder::StringAppendable)]
+  #[capacity_display(no_inherent, into_string)]
+  #[append(format = "v{0}")]
+  struct Version<T>(T);
+
+  fn take(value: impl Into<String>) -> String {
+    value.into()
+  }
+
+  let version = Version(1u8);
+  assert_eq!(take(&version), "v1");
+  let text: String = version.into();
+  assert_eq!(text, "v1");
+  assert_eq!(text.capacity(), 2);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn capacity_display_serialize() {
+  #[derive(CapacityDisplay, capacity_builder::StringAppendable)]
+  #[capacity_display(serialize)]
+  #[append(format = "{0}.{1}")]
+  struct Version(u32, u32);
+
+  assert_eq!(serde_json::to_string(&Version(1, 2)).unwrap(), "\"1.2\"");
+}
+
+#[test]
+fn capacity_display_debug() {
+  #[derive(CapacityDisplay, capacity_builder::StringAppendable)]
+  #[capacity_display(no_inherent, debug)]
+  #[append(format = "{0}.{1}")]
+  struct Version(u32, u32);
+
+  assert_eq!(format!("{:?}", Version(1, 2)), "1.2");
+
+  #[derive(CapacityDisplay, capacity_builder::StringAppendable)]
+  #[capacity_display(no_inherent, debug = "Pair({name}, {value})")]
+  #[append(format = "{name}={value}")]
+  struct Pair<'b, V> {
+    name: &'b str,
+    value: V,
+  }
+
+  let pair = Pair {
+    name: "a",
+    value: 1,
+  };
+  assert_eq!(pair.to_string(), "a=1");
+  assert_eq!(format!("{:?}", pair), "Pair(a, 1)");
+}
+
+#[test]
+fn capacity_format_macro() {
+  use capacity_builder::capacity_format;
+
+  let place = "the park".to_string();
+  let count = 5;
+  let text = capacity_format!("{} in {}", count, &place);
+  assert_eq!(text, "5 in the park");
+  assert_eq!(text.capacity(), text.len());
+
+  // display fallback, specs, named, and implicit captures
+  let ratio = 0.5f64;
+  let text = capacity_format!(
+    "{ratio} {0:>3}|{1:?} {{{name}}} {count}",
+    count,
+    "quoted",
+    name = &MyStruct,
+  );
+  assert_eq!(text, "0.5   5|\"quoted\" {Hello there!} 5");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn capacity_bytes_macro() {
+  use capacity_builder::capacity_bytes;
+
+  let payload = vec![1u8, 2];
+  let version = 1u16;
+  let bytes =
+    capacity_bytes![b"MAGIC", version.be(), &payload, 0u8, 2u32.le(),].unwrap();
+  assert_eq!(bytes, b"MAGIC\x00\x01\x01\x02\x00\x02\x00\x00\x00");
+  assert_eq!(bytes.capacity(), bytes.len());
+}
+
+#[test]
+fn display_of() {
+  struct Point(u32, u32);
+
+  impl<'a> StringAppendable<'a> for &'a Point {
+    fn append_to_builder<TString: StringType>(
+      self,
+      builder: &mut StringBuilder<'a, TString>,
+    ) {
+      builder.append(self.0);
+      builder.append(',');
+      builder.append(self.1);
+    }
+  }
+
+  let point = Point(1, 2);
+  let display = capacity_builder::DisplayOf(&point);
+  assert_eq!(format!("({})", display), "(1,2)");
+  assert_eq!(display.to_string(), "1,2");
+  assert_eq!(capacity_builder::DisplayOf("text").to_string(), "text");
+}
+
+#[test]
+fn appendable_to_string_non_copy() {
+  #[derive(capacity_builder::StringAppendable)]
+  #[append(format = "{name}: {count}")]
+  struct Entry {
+    name: String,
+    count: usize,
+  }
+
+  let entry = Entry {
+    name: "items".to_string(),
+    count: 3,
+  };
+  let text: String = capacity_builder::appendable_to_string(&entry);
+  assert_eq!(text, "items: 3");
+  assert_eq!(text.capacity(), text.len());
+  let text: String = capacity_builder::appendable_to_string("text");
+  assert_eq!(text, "text");
+}
+
+#[test]
+fn appendable_lens() {
+  assert_eq!(capacity_builder::appendable_len(&MyStruct), 12);
+  assert_eq!(capacity_builder::appendable_len(12345u32), 5);
+  assert_eq!(capacity_builder::appendable_len(Some("abc")), 3);
+  assert_eq!(capacity_builder::bytes_appendable_len("abc"), 3);
+  assert_eq!(capacity_builder::bytes_appendable_len([0u8; 16]), 16);
+}
+
+#[test]
+fn generic_appendable() {
+  use capacity_builder::Builder;
+  use capacity_builder::Generic;
+  use capacity_builder::GenericAppendable;
+
+  struct Version {
+    major: u32,
+    minor: u32,
+    tag: &'static str,
+  }
+
+  impl<'a> GenericAppendable<'a> for &'a Version {
+    fn append_to_builder<TBuilder: Builder<'a>>(self, builder: &mut TBuilder) {
+      builder.append_int(self.major);
+      builder.append_char('.');
+      builder.append_int(self.minor);
+      builder.append_str("-");
+      builder.append_str(self.tag);
+    }
+  }
+
+  let version = Version {
+    major: 1,
+    minor: 20,
+    tag: "beta",
+  };
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(Generic(&version));
+  })
+  .unwrap();
+  assert_eq!(text, "1.20-beta");
+  assert_eq!(text.capacity(), text.len());
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(Generic(&version));
+  })
+  .unwrap();
+  assert_eq!(bytes, b"1.20-beta");
+  assert_eq!(bytes.capacity(), bytes.len());
+}
+
+#[test]
+fn multi_builder() {
+  let items = ["a", "bb", "ccc"];
+  let [header, body] = capacity_builder::MultiBuilder::<2>::build(|outs| {
+    outs.get(0).append("count: ");
+    outs.get(0).append(items.len());
+    for item in &items {
+      outs.get(1).append(*item);
+      outs.get(1).append('\n');
+    }
+  })
+  .unwrap();
+  assert_eq!(header, "count: 3");
+  assert_eq!(header.capacity(), header.len());
+  assert_eq!(body, "a\nbb\nccc\n");
+  assert_eq!(body.capacity(), body.len());
+}
+
+#[test]
+fn multi_bytes_builder() {
+  let items = ["a", "bb", "ccc"];
+  let [header, body] =
+    capacity_builder::MultiBytesBuilder::<2>::build(|outs| {
+      outs.get(0).append_be(items.len() as u16);
+      for item in &items {
+        outs.get(1).append(*item);
+        outs.get(1).append(0u8);
+      }
+    })
+    .unwrap();
+  assert_eq!(header, [0, 3]);
+  assert_eq!(header.capacity(), header.len());
+  assert_eq!(body, b"a\0bb\0ccc\0");
+  assert_eq!(body.capacity(), body.len());
+}
+
+#[test]
+fn string_build_with_bytes() {
+  let values = [1u16, 300];
+  let (text, bytes) =
+    StringBuilder::<String>::build_with_bytes::<Vec<u8>>(|text, bytes| {
+      for value in values {
+        text.append(value);
+        text.append(';');
+        bytes.append_be(value);
+      }
+    })
+    .unwrap();
+  assert_eq!(text, "1;300;");
+  assert_eq!(text.capacity(), text.len());
+  assert_eq!(bytes, [0, 1, 1, 44]);
+  assert_eq!(bytes.capacity(), bytes.len());
+}
+
+#[test]
+fn string_build_once() {
+  let calls = std::cell::Cell::new(0);
+  let text = StringBuilder::<String>::build_once(|builder| {
+    calls.set(calls.get() + 1);
+    builder.append("value: ");
+    builder.append(123);
+    builder.append(&MyStruct);
+  })
+  .unwrap();
+  assert_eq!(calls.get(), 1);
+  assert_eq!(text, "value: 123Hello there!");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn templates() {
+  use capacity_builder::template::BytesTemplate;
+  use capacity_builder::template::StringTemplate;
+
+  let template = StringTemplate::new(|builder, name: &str| {
+    builder.append("Hello ");
+    builder.append(name);
+    builder.append('!');
+  });
+  for name in ["abc", "def"] {
+    let text: String = template.render(name).unwrap();
+    assert_eq!(text, format!("Hello {}!", name));
+    assert_eq!(text.capacity(), text.len());
+  }
+
+  // reserves the peak length and additional capacity
+  let template = StringTemplate::new(|builder, name: &str| {
+    builder.append(name);
+    builder.append(", ");
+    builder.pop_last(2);
+    builder.reserve_additional(3);
+  });
+  let text: String = template.render("abc").unwrap();
+  assert_eq!(text, "abc");
+  assert_eq!(text.capacity(), 8);
+
+  let template = BytesTemplate::new(|builder, values: &[u16; 2]| {
+    let prefix = capacity_builder::frame::LenPrefix::U16(
+      capacity_builder::frame::Endianness::Big,
+    );
+    builder.append_len_prefixed(prefix, |builder| {
+      builder.append_be(values[0]);
+      builder.append_be(values[1]);
+    });
+  });
+  let bytes: Vec<u8> = template.render(&[1, 2]).unwrap();
+  assert_eq!(bytes, [0, 4, 0, 1, 0, 2]);
+  let bytes: Vec<u8> = template.render(&[3, 4]).unwrap();
+  assert_eq!(bytes, [0, 4, 0, 3, 0, 4]);
+  assert_eq!(bytes.capacity(), bytes.len());
+}
+
+#[test]
+fn string_build_with_buffer() {
+  let mut buffer = String::new();
+  let text = StringBuilder::build_with_buffer(&mut buffer, |builder| {
+    builder.append("Hello ");
+    builder.append(123);
+  })
+  .unwrap();
+  assert_eq!(text, "Hello 123");
+  assert_eq!(buffer.capacity(), 9);
+  let text = StringBuilder::build_with_buffer(&mut buffer, |builder| {
+    builder.append("abc");
+  })
+  .unwrap();
+  assert_eq!(text, "abc");
+  // keeps the allocation
+  assert_eq!(buffer.capacity(), 9);
+}
+
+#[test]
+fn string_build_cow() {
+  use std::borrow::Cow;
+
+  let owned = "owned".to_string();
+  let text = StringBuilder::build_cow(|builder| {
+    builder.append(&owned);
+  })
+  .unwrap();
+  assert!(matches!(text, Cow::Borrowed("owned")));
+  let text = StringBuilder::build_cow(|builder| {
+    builder.append("");
+    builder.append("value");
+    builder.append(None::<&str>);
+  })
+  .unwrap();
+  assert!(matches!(text, Cow::Borrowed("value")));
+  let text = StringBuilder::build_cow(|_| {}).unwrap();
+  assert!(matches!(text, Cow::Borrowed("")));
+
+  let text = StringBuilder::build_cow(|builder| {
+    builder.append("value");
+    builder.append(1);
+  })
+  .unwrap();
+  assert!(matches!(&text, Cow::Owned(text) if text == "value1"));
+  let text = StringBuilder::build_cow(|builder| {
+    builder.append(&MyStruct);
+  })
+  .unwrap();
+  assert!(matches!(&text, Cow::Owned(text) if text == "Hello there!"));
+}
+
+#[test]
+fn string_build_segments() {
+  let owned = "owned".to_string();
+  let segments = StringBuilder::build_segments(|builder| {
+    builder.append("a");
+    builder.append(&owned);
+    builder.append(12);
+    builder.append('c');
+    builder.append("");
+    builder.append_with_replace("x-y", "-", "+");
+    builder.append(&MyStruct);
+  });
+  assert_eq!(
+    segments.iter().collect::<Vec<_>>(),
+    ["a", "owned", "12cx+y", "Hello", " there!"],
+  );
+  assert_eq!(segments.len(), 24);
+  assert_eq!(segments.io_slices().len(), 5);
+}
+
+#[test]
+fn bytes_stream_rejects_readers() {
+  use std::io::Read;
+
+  let data = b"data".to_vec();
+  let err = BytesBuilder::stream(
+    |builder| {
+      builder.append_reader_exact(data.as_slice(), data.len());
+    },
+    |reader| {
+      assert_eq!(reader.len(), 4);
+      reader.read_to_end(&mut Vec::new()).unwrap_err()
+    },
+  );
+  assert_eq!(err.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test]
+fn bytes_stream() {
+  use std::io::Read;
+  use std::sync::atomic::AtomicUsize;
+  use std::sync::atomic::Ordering;
+
+  let data = (0..200_000u32).map(|i| i as u8).collect::<Vec<_>>();
+  let runs = AtomicUsize::new(0);
+  let bytes = BytesBuilder::stream(
+    |builder| {
+      runs.fetch_add(1, Ordering::SeqCst);
+      builder.append(b"start".as_slice());
+      builder.append_len_prefixed(
+        capacity_builder::frame::LenPrefix::U32(
+          capacity_builder::frame::Endianness::Big,
+        ),
+        |builder| {
+          for chunk in data.chunks(1000) {
+            builder.append(chunk);
+          }
+        },
+      );
+      builder.append(0u8);
+    },
+    |reader| {
+      assert_eq!(reader.len(), 200_010);
+      let mut bytes = Vec::new();
+      reader.read_to_end(&mut bytes).unwrap();
+      bytes
+    },
+  );
+  assert_eq!(runs.load(Ordering::SeqCst), 2);
+  let expected = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(b"start".as_slice());
+    builder.append_be(200_000u32);
+    builder.append(&data);
+    builder.append(0u8);
+  })
+  .unwrap();
+  assert_eq!(bytes, expected);
+
+  // stopping early discards the rest of the bytes
+  let start = BytesBuilder::stream(
+    |builder| {
+      for _ in 0..10 {
+        builder.append(&data);
+      }
+    },
+    |reader| {
+      let mut start = [0; 5];
+      reader.read_exact(&mut start).unwrap();
+      start
+    },
+  );
+  assert_eq!(start, [0, 1, 2, 3, 4]);
+}
+
+#[test]
+fn string_matches() {
+  let build = |builder: &mut StringBuilder<'_, String>| {
+    builder.append("value: ");
+    builder.append(123);
+    builder.append(&MyStruct);
+  };
+  assert!(StringBuilder::matches("value: 123Hello there!", build));
+  assert!(!StringBuilder::matches("value: 124Hello there!", build));
+  assert!(!StringBuilder::matches("value: 123Hello", build));
+  assert!(!StringBuilder::matches("value: 123Hello there!!", build));
+  assert!(StringBuilder::matches("", |_| {}));
+}
+
+#[test]
+fn hash_into() {
+  use std::hash::DefaultHasher;
+  use std::hash::Hasher;
+
+  let hash = |bytes: &[u8]| {
+    let mut hasher = DefaultHasher::new();
+    hasher.write(bytes);
+    hasher.finish()
+  };
+
+  let mut runs = 0;
+  let mut hasher = DefaultHasher::new();
+  StringBuilder::hash_into(&mut hasher, |builder| {
+    runs += 1;
+    builder.append("value: ");
+    builder.append(123);
+    builder.append('!');
+  });
+  assert_eq!(hasher.finish(), hash(b"value: 123!"));
+  assert_eq!(runs, 1);
+
+  let mut hasher = DefaultHasher::new();
+  BytesBuilder::hash_into(&mut hasher, |builder| {
+    builder.append("value");
+    builder.append_be(1u16);
+    builder.append_varint(300);
+  });
+  assert_eq!(hasher.finish(), hash(b"value\x00\x01\xac\x02"));
+
+  fn build_sections<TBytes: BytesType>(builder: &mut BytesBuilder<'_, TBytes>) {
+    builder.append("a");
+    builder.append_len_prefixed(
+      capacity_builder::frame::LenPrefix::U16(
+        capacity_builder::frame::Endianness::Big,
+      ),
+      |builder| {
+        builder.append("nested");
+        builder.append_crc32_be_of(
+          capacity_builder::crc::Crc32Kind::Ieee,
+          |builder| builder.append("IEND"),
+        );
+      },
+    );
+  }
+
+  // the closure is only run once
+  let mut runs = 0;
+  let mut hasher = DefaultHasher::new();
+  BytesBuilder::hash_into(&mut hasher, |builder| {
+    runs += 1;
+    build_sections(builder);
+  });
+  assert_eq!(runs, 1);
+  assert_eq!(
+    hasher.finish(),
+    hash(&BytesBuilder::<Vec<u8>>::build(build_sections).unwrap())
+  );
+}
+
+#[test]
+fn string_starts_and_ends_with() {
+  let build = |builder: &mut StringBuilder<'_, String>| {
+    builder.append("key:");
+    builder.append(123);
+    builder.append(&MyStruct);
+  };
+  assert!(StringBuilder::starts_with("key:1", build));
+  assert!(StringBuilder::starts_with("", build));
+  assert!(!StringBuilder::starts_with("key:2", build));
+  assert!(!StringBuilder::starts_with("key:123Hello there!!", build));
+  assert!(StringBuilder::ends_with("3Hello there!", build));
+  assert!(StringBuilder::ends_with("key:123Hello there!", build));
+  assert!(StringBuilder::ends_with("", build));
+  assert!(!StringBuilder::ends_with("there", build));
+  assert!(!StringBuilder::ends_with("_key:123Hello there!", build));
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn string_build_par() {
+  let items = (0..1000).collect::<Vec<u32>>();
+  let text = StringBuilder::build_par(&items, |builder, item| {
+    builder.append(*item);
+    builder.append(',');
+  })
+  .unwrap();
+  let expected = StringBuilder::<String>::build(|builder| {
+    for item in &items {
+      builder.append(*item);
+      builder.append(',');
+    }
+  })
+  .unwrap();
+  assert_eq!(text, expected);
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn string_sections_run_each_section_once_per_pass() {
+  use std::sync::atomic::AtomicUsize;
+  use std::sync::atomic::Ordering;
+
+  let runs = AtomicUsize::new(0);
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.sections(|scope| {
+      for i in 0..20 {
+        let runs = &runs;
+        scope.spawn(move |builder| {
+          runs.fetch_add(1, Ordering::SeqCst);
+          builder.append(i);
+          builder.append(", ");
+          builder.pop_last(2);
+        });
+      }
+    });
+  })
+  .unwrap();
+  assert_eq!(text, (0..20).map(|i| i.to_string()).collect::<String>());
+  assert_eq!(text.capacity(), text.len());
+  assert_eq!(runs.load(Ordering::SeqCst), 40);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn string_sections() {
+  let items = (0..100).collect::<Vec<u32>>();
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append('[');
+    builder.sections(|scope| {
+      scope.spawn(|builder| {
+        for item in &items[..50] {
+          builder.append(*item);
+        }
+      });
+      scope.spawn(|builder| builder.append('|'));
+      scope.spawn(|builder| {
+        for item in &items[50..] {
+          builder.append(*item);
+        }
+      });
+    });
+    builder.append(']');
+  })
+  .unwrap();
+  let expected = format!(
+    "[{}|{}]",
+    items[..50]
+      .iter()
+      .map(|i| i.to_string())
+      .collect::<String>(),
+    items[50..]
+      .iter()
+      .map(|i| i.to_string())
+      .collect::<String>(),
+  );
+  assert_eq!(text, expected);
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn vec_builder() {
+  #[derive(Debug, Clone, PartialEq)]
+  enum Token {
+    Word(&'static str),
+    Num(u32),
+  }
+
+  let words = ["a", "b", "c"];
+  let items = VecBuilder::build(|builder| {
+    for word in &words {
+      builder.push(Token::Word(word));
+    }
+    builder.push_with(|| Token::Num(0));
+    builder.extend_from_slice(&[Token::Num(1), Token::Num(2)]);
+  })
+  .unwrap();
+  assert_eq!(
+    items,
+    vec![
+      Token::Word("a"),
+      Token::Word("b"),
+      Token::Word("c"),
+      Token::Num(0),
+      Token::Num(1),
+      Token::Num(2),
+    ]
+  );
+  assert_eq!(items.capacity(), items.len());
+}
+
+#[test]
+fn builders_extend() {
+  let words = ["a", "bc", "def"];
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.extend(words.iter().copied());
+    builder.extend("xyz".chars().rev());
+  })
+  .unwrap();
+  assert_eq!(text, "abcdefzyx");
+  assert_eq!(text.capacity(), text.len());
+
+  let chunks: [&[u8]; 2] = [b"ab", b"cd"];
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.extend(chunks);
+    builder.extend(1..=3u8);
+  })
+  .unwrap();
+  assert_eq!(bytes, b"abcd\x01\x02\x03");
+  assert_eq!(bytes.capacity(), bytes.len());
+}
+
+#[test]
+fn string_append_owned_cached() {
+  let calls = std::cell::Cell::new(0);
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append("a");
+    builder.append_owned(|| {
+      calls.set(calls.get() + 1);
+      format!("{:?}", [1, 2])
+    });
+    builder.append("b");
+  })
+  .unwrap();
+  assert_eq!(text, "a[1, 2]b");
+  assert_eq!(text.capacity(), text.len());
+  assert_eq!(calls.get(), 1);
+
+  let text = StringBuilder::<String>::build_once(|builder| {
+    builder.append_owned(|| "once".to_string());
+  })
+  .unwrap();
+  assert_eq!(text, "once");
+}
+
+#[test]
+fn memoized_appendable() {
+  let calls = std::cell::Cell::new(0);
+  let value = Memoized::new(|| {
+    calls.set(calls.get() + 1);
+    format!("{:?}", (1, 2))
+  });
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append('<');
+    builder.append(&value);
+    builder.append('>');
+  })
+  .unwrap();
+  assert_eq!(text, "<(1, 2)>");
+  assert_eq!(text.capacity(), text.len());
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(&value);
+  })
+  .unwrap();
+  assert_eq!(bytes, b"(1, 2)");
+  assert_eq!(calls.get(), 1);
+
+  let value = Memoized::new(|| vec![1u8, 2, 3]);
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(&value);
+    builder.append(&value);
+  })
+  .unwrap();
+  assert_eq!(bytes, [1, 2, 3, 1, 2, 3]);
+}
+
+#[test]
+fn string_append_owned_hint() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append("a");
+    builder.append_owned_hint(2, || "longer".to_string());
+    builder.append_owned_hint(4, || "x");
+    builder.append("b");
+  })
+  .unwrap();
+  assert_eq!(text, "alongerxb");
+
+  let text = StringBuilder::<String>::build_once(|builder| {
+    builder.append_owned_hint(4, || "x");
+  })
+  .unwrap();
+  assert_eq!(text, "x");
+}
+
+#[test]
+fn string_checkpoint_rollback() {
+  let items = [vec![], vec!["a", "b"], vec![]];
+  let build = |builder: &mut StringBuilder<'_, String>| {
+    for (i, group) in items.iter().enumerate() {
+      let checkpoint = builder.checkpoint();
+      builder.append("group ");
+      builder.append(i);
+      builder.append(':');
+      let before = builder.len();
+      for item in group {
+        builder.append(' ');
+        builder.append(*item);
+      }
+      if builder.len() == before {
+        builder.rollback(checkpoint);
+      } else {
+        builder.append('\n');
+      }
+    }
+  };
+  let text = StringBuilder::<String>::build(build).unwrap();
+  assert_eq!(text, "group 1: a b\n");
+  assert_eq!(StringBuilder::build_cow(build).unwrap(), "group 1: a b\n");
+  let segments = StringBuilder::build_segments(build);
+  assert_eq!(segments.iter().collect::<String>(), "group 1: a b\n");
+  assert_eq!(segments.len(), 13);
+  let segments = StringBuilder::build_segments(|builder| {
+    builder.append(1);
+    let checkpoint = builder.checkpoint();
+    builder.append(2);
+    builder.append("borrowed");
+    builder.rollback(checkpoint);
+    builder.append(3);
+  });
+  assert_eq!(segments.iter().collect::<Vec<_>>(), ["13"]);
+
+  let text = StringBuilder::<String>::build_cow(|builder| {
+    let checkpoint = builder.checkpoint();
+    builder.append(1);
+    builder.rollback(checkpoint);
+    builder.append("borrowed");
+  })
+  .unwrap();
+  assert!(matches!(text, std::borrow::Cow::Borrowed("borrowed")));
+}
+
+#[test]
+fn string_prepend() {
+  let imports = ["a", "b"];
+  let build = |builder: &mut StringBuilder<'_, String>| {
+    builder.append("body\n");
+    for import in &imports {
+      builder.append("use ");
+      builder.append(*import);
+      builder.append(";\n");
+      builder.prepend("// import\n");
+    }
+    builder.prepend(imports.len());
+    builder.append("end");
+  };
+  let text = StringBuilder::<String>::build(build).unwrap();
+  assert_eq!(text, "// import\n// import\n2body\nuse a;\nuse b;\nend");
+  assert_eq!(text.capacity(), text.len());
+  assert_eq!(StringBuilder::build_once(build).unwrap(), text);
+}
+
+#[test]
+fn string_insert_at() {
+  let items = [1, 22, 333];
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append("# Report\n");
+    let summary = builder.mark();
+    let details_start = builder.len();
+    for item in &items {
+      builder.append("- ");
+      builder.append(*item);
+      builder.append('\n');
+    }
+    builder.insert_at(summary, builder.len() - details_start);
+    builder.insert_at(summary, "detail bytes: ");
+    builder.prepend("<report>\n");
+  })
+  .unwrap();
+  assert_eq!(
+    text,
+    "<report>\n# Report\ndetail bytes: 15- 1\n- 22\n- 333\n"
+  );
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn string_truncate_and_pop() {
+  let items = ["a", "b", "c"];
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append('[');
+    for item in &items {
+      builder.append(*item);
+      builder.append(", ");
+    }
+    builder.pop_last(2);
+    builder.append(']');
+    let len = builder.len();
+    builder.append("ignored");
+    builder.truncate_to(len);
+  })
+  .unwrap();
+  assert_eq!(text, "[a, b, c]");
+}
+
+#[test]
+fn string_reserve_additional() {
+  let mut text = StringBuilder::<String>::build(|builder| {
+    builder.reserve_additional(6);
+    builder.append("Hello");
+  })
+  .unwrap();
+  assert_eq!(text, "Hello");
+  assert_eq!(text.capacity(), 11);
+  text.push_str(" world");
+  assert_eq!(text.capacity(), 11);
+}
+
+#[test]
+fn string_build_estimate() {
+  struct Escaped<'a>(&'a str);
+
+  impl StringAppendableValue for Escaped<'_> {
+    fn byte_len(&self) -> usize {
+      // upper bound where every char is escaped
+      self.0.len() * 2
+    }
+
+    fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+      for c in self.0.chars() {
+        if c == '"' {
+          text.push('\\');
+        }
+        text.push(c);
+      }
+    }
+
+    fn write_to_formatter(
+      &self,
+      fmt: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+      fmt.write_str(&self.0.replace('"', "\\\""))
+    }
+  }
+
+  let text = StringBuilder::<String>::build_estimate(|builder| {
+    builder.append('"');
+    builder.append(Escaped("a \"quote\""));
+    builder.append_owned_unsafe(10, || "short");
+    builder.append('"');
+  })
+  .unwrap();
+  assert_eq!(text, "\"a \\\"quote\\\"short\"");
+  assert_eq!(text.capacity(), 2 + 18 + 10);
+}
+
+#[test]
+fn string_build_with_capacity() {
+  let text = StringBuilder::<String>::build_with_capacity(12, |builder| {
+    builder.append(&MyStruct);
+  })
+  .unwrap();
+  assert_eq!(text, "Hello there!");
+  assert_eq!(text.capacity(), 12);
+}
+
+#[test]
+fn capacity_mismatch_hook() {
+  use capacity_builder::mismatch;
+  use capacity_builder::mismatch::CapacityMismatch;
+  use std::sync::atomic::AtomicUsize;
+  use std::sync::atomic::Ordering;
+
+  static REPORTED_LEN: AtomicUsize = AtomicUsize::new(0);
+
+  struct WrongLen;
+
+  impl StringAppendableValue for WrongLen {
+    fn byte_len(&self) -> usize {
+      1
+    }
+
+    fn push_to<TString: StringTypeMut>(&self, text: &mut TString) {
+      text.push_str("abc");
+    }
+
+    fn write_to_formatter(
+      &self,
+      fmt: &mut std::fmt::Formatter<'_>,
+    ) -> std::fmt::Result {
+      fmt.write_str("abc")
+    }
+  }
+
+  fn hook(mismatch: &CapacityMismatch) {
+    assert_eq!(mismatch.capacity, 4);
+    assert_eq!(mismatch.offset, Some(2));
+    assert!(mismatch.reallocated());
+    REPORTED_LEN.store(mismatch.len, Ordering::SeqCst);
+  }
+
+  mismatch::set_hook(hook);
+  // panics in debug mode after reporting
+  let _ = std::panic::catch_unwind(|| {
+    StringBuilder::<String>::build(|builder| {
+      builder.append("ab");
+      builder.append(WrongLen);
+      builder.append("c");
+    })
+  });
+  assert_eq!(mismatch::take_hook(), Some(hook as fn(&CapacityMismatch)));
+  assert_eq!(REPORTED_LEN.load(Ordering::SeqCst), 6);
+}
+
+#[test]
+fn build_error() {
+  let err = StringBuilder::<String>::build_with_capacity(usize::MAX, |_| {})
+    .unwrap_err();
+  assert!(matches!(
+    err,
+    capacity_builder::Error::CapacityOverflow { size: usize::MAX }
+  ));
+  assert_eq!(
+    err.to_string(),
+    format!("capacity of {} bytes overflowed", usize::MAX)
+  );
+  let err = std::io::Error::from(err);
+  assert_eq!(err.kind(), std::io::ErrorKind::OutOfMemory);
+}
+
+#[test]
+fn string_build_checked() {
+  let text = StringBuilder::<String>::build_checked(|builder| {
+    builder.append(&MyStruct);
+  })
+  .unwrap();
+  assert_eq!(text, "Hello there!");
+
+  let err = StringBuilder::<String>::build_checked(|builder| {
+    builder.append_owned_unsafe(2, || "abc");
+  })
+  .unwrap_err();
+  match err {
+    capacity_builder::Error::CapacityMismatch(mismatch) => {
+      assert_eq!(mismatch.capacity, 2);
+      assert_eq!(mismatch.len, 3);
+      assert_eq!(mismatch.offset, Some(0));
+    }
+    _ => unreachable!(),
+  }
+}
+
+#[test]
+fn string_try_append_owned() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.try_append_owned(3, || "abc").unwrap();
+  })
+  .unwrap();
+  assert_eq!(text, "abc");
+
+  // only fails when the value is built on the second pass
+  let results = std::cell::RefCell::new(Vec::new());
+  let text = StringBuilder::<String>::build_checked(|builder| {
+    builder.append('a');
+    let result = builder.try_append_owned(1, || "bc");
+    results.borrow_mut().push(result.is_ok());
+  });
+  assert_eq!(results.into_inner(), [true, false]);
+  assert!(text.is_err());
+}
+
+#[test]
+fn string_const_capacity() {
+  const NAME: &str = "pkg";
+  const MAJOR: u32 = 1;
+  const CAPACITY: usize =
+    capacity_builder::const_capacity!(NAME, '@', MAJOR, ".", 20u8, '-', -3i64);
+  assert_eq!(CAPACITY, 11);
+  assert_eq!(capacity_builder::const_capacity!(), 0);
+  assert_eq!(
+    capacity_builder::const_capacity!(0u8, u64::MAX, i8::MIN),
+    25
+  );
+  let text =
+    StringBuilder::<String>::build_with_capacity(CAPACITY, |builder| {
+      builder.append(NAME);
+      builder.append('@');
+      builder.append(MAJOR);
+      builder.append(".");
+      builder.append(20u8);
+      builder.append('-');
+      builder.append(-3i64);
+    })
+    .unwrap();
+  assert_eq!(text, "pkg@1.20--3");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn const_concat() {
+  const NAME: &str = "capacity_builder";
+  const USER_AGENT: &str = capacity_builder::const_concat!(NAME, "/", "0.5 ✓");
+  assert_eq!(USER_AGENT, "capacity_builder/0.5 ✓");
+  assert_eq!(capacity_builder::const_concat!(), "");
+  let text: &'static str = capacity_builder::const_concat!("a", "b");
+  assert_eq!(text, "ab");
+}
+
+#[test]
+fn os_string_build() {
+  use std::ffi::OsStr;
+  use std::ffi::OsString;
+
+  let paths = [OsStr::new("/usr/bin"), OsStr::new("/bin")];
+  let text = StringBuilder::<OsString>::build(|builder| {
+    for (i, path) in paths.iter().enumerate() {
+      if i > 0 {
+        builder.append(':');
+      }
+      builder.append_os_str(path);
+    }
+    builder.append(":/opt/");
+    builder.append(1);
+  })
+  .unwrap();
+  assert_eq!(text, "/usr/bin:/bin:/opt/1");
+}
+
+#[test]
+fn path_builder_components() {
+  use std::path::PathBuf;
+
+  let components = ["src", "nested/", "", "file.rs"];
+  let path = PathBuilder::build(|builder| {
+    for component in &components {
+      builder.append_component(component);
+    }
+  })
+  .unwrap();
+  let mut expected = PathBuf::new();
+  for component in &components {
+    expected.push(component);
+  }
+  assert_eq!(path, expected);
+  assert_eq!(path.capacity(), path.as_os_str().len());
+
+  let prefix = "a-long-relative-prefix/".repeat(8);
+  let path = PathBuilder::build(|builder| {
+    builder.append_component(&prefix);
+    builder.append_component("nested");
+    builder.append_component("/absolute");
+    builder.append_component("file.rs");
+  })
+  .unwrap();
+  assert_eq!(path, PathBuf::from("/absolute/file.rs"));
+  assert_eq!(path.capacity(), path.as_os_str().len());
+}
+
+#[test]
+fn c_string_build() {
+  use std::ffi::CString;
+
+  let c_string = BytesBuilder::<CString>::build(|builder| {
+    builder.append("Hello");
+    builder.append(b' ');
+    builder.append("there");
+  })
+  .unwrap();
+  assert_eq!(c_string.as_bytes_with_nul(), b"Hello there\0");
+
+  let err = BytesBuilder::<CString>::build_c_string(|builder| {
+    builder.append("Hello\0there");
+  })
+  .unwrap_err();
+  match err {
+    capacity_builder::Error::InteriorNul(err) => {
+      assert_eq!(err.nul_position(), 5)
+    }
+    err => panic!("unexpected error: {}", err),
+  }
+}
+
+#[test]
+fn build_utf16() {
+  let runs = std::cell::Cell::new(0);
+  let units = StringBuilder::<String>::build_utf16(|builder| {
+    runs.set(runs.get() + 1);
+    builder.append("Hi ");
+    builder.append('😀');
+    builder.append(' ');
+    builder.append(123);
+    // rolling back whole appends keeps the count exact
+    let checkpoint = builder.checkpoint();
+    builder.append("é!");
+    builder.rollback(checkpoint);
+  })
+  .unwrap();
+  assert_eq!(units, "Hi 😀 123".encode_utf16().collect::<Vec<_>>());
+  assert_eq!(units.capacity(), units.len());
+  assert_eq!(runs.get(), 2);
+
+  // partly truncating non-ascii text counts the code units again
+  let units = StringBuilder::<String>::build_utf16(|builder| {
+    runs.set(runs.get() + 1);
+    builder.append("a😀é😀");
+    builder.pop_last(4);
+  })
+  .unwrap();
+  assert_eq!(units, "a😀é".encode_utf16().collect::<Vec<_>>());
+  assert_eq!(units.capacity(), units.len());
+  assert_eq!(runs.get(), 5);
+}
+
+#[test]
+fn negative_ints_capacity() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(-1i32);
+    builder.append(' ');
+    builder.append(i64::MIN);
+    builder.append(' ');
+    builder.append(-0i8);
+  })
+  .unwrap();
+  assert_eq!(text, format!("-1 {} 0", i64::MIN));
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_fmt_arguments() {
+  let name = "world";
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(format_args!("Hello"));
+    builder.append_fmt(format_args!(", {}! {:>4}", name, 1.5));
+  })
+  .unwrap();
+  assert_eq!(text, "Hello, world!  1.5");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn num_wrappers() {
+  use std::num::Saturating;
+  use std::num::Wrapping;
+
+  let wrapping = Wrapping(u8::MAX) + Wrapping(2);
+  let saturating = Saturating(i8::MIN) - Saturating(1);
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(wrapping);
+    builder.append(' ');
+    builder.append(saturating);
+  })
+  .unwrap();
+  assert_eq!(text, "1 -128");
+
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append_be(Wrapping(0x0102u16));
+    builder.append_le(Saturating(0x0304u16));
+  })
+  .unwrap();
+  assert_eq!(bytes, [1, 2, 4, 3]);
+}
+
+#[test]
+fn atomic_appendables() {
+  use std::sync::atomic::AtomicI32;
+  use std::sync::atomic::AtomicU64;
+  use std::sync::atomic::Ordering;
+
+  let requests = AtomicU64::new(9);
+  let delta = AtomicI32::new(-5);
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(&requests);
+    // the second pass uses the value loaded on the first
+    requests.fetch_add(1, Ordering::Relaxed);
+    builder.append(' ');
+    builder.append(&delta);
+  })
+  .unwrap();
+  assert_eq!(text, "9 -5");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_signed_ints() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_signed(5u8);
+    builder.append(' ');
+    builder.append_signed(0i32);
+    builder.append(' ');
+    builder.append_signed(-12i64);
+  })
+  .unwrap();
+  assert_eq!(text, format!("{:+} {:+} {:+}", 5u8, 0i32, -12i64));
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_duration_human() {
+  use std::time::Duration;
+
+  let format = |duration: Duration, granularity: DurationUnit| {
+    StringBuilder::<String>::build(|builder| {
+      builder.append_duration_human(duration, granularity);
+    })
+    .unwrap()
+  };
+  let duration = Duration::from_millis(2 * 3_600_000 + 3 * 60_000 + 5_450);
+  assert_eq!(format(duration, DurationUnit::Seconds), "2h 3m 5s");
+  assert_eq!(
+    format(duration, DurationUnit::Milliseconds),
+    "2h 3m 5s 450ms"
+  );
+  assert_eq!(format(duration, DurationUnit::Hours), "2h");
+  assert_eq!(
+    format(Duration::from_millis(450), DurationUnit::Milliseconds),
+    "450ms"
+  );
+  assert_eq!(
+    format(Duration::from_millis(450), DurationUnit::Seconds),
+    "0s"
+  );
+  assert_eq!(
+    format(Duration::from_secs(3_605), DurationUnit::Seconds),
+    "1h 5s"
+  );
+}
+
+#[test]
+fn append_system_time() {
+  use std::time::Duration;
+  use std::time::SystemTime;
+
+  let time = SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123);
+  let before_epoch = SystemTime::UNIX_EPOCH - Duration::from_millis(1_500);
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(time);
+    builder.append(' ');
+    builder.append_timestamp(time, TimestampPrecision::Milliseconds);
+    builder.append(' ');
+    builder.append_timestamp(time, TimestampPrecision::Microseconds);
+    builder.append(' ');
+    builder.append(before_epoch);
+  })
+  .unwrap();
+  assert_eq!(text, "1700000000 1700000000123 1700000000123000 -2");
+}
+
+#[test]
+fn append_as_ref_str() {
+  use std::rc::Rc;
+
+  struct Name(String);
+
+  impl AsRef<str> for Name {
+    fn as_ref(&self) -> &str {
+      &self.0
+    }
+  }
+
+  let name = Name("David".to_string());
+  let greeting: Rc<str> = Rc::from("Hello");
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(Str(&greeting));
+    builder.append(' ');
+    builder.append(Str(&name));
+  })
+  .unwrap();
+  assert_eq!(text, "Hello David");
+
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(Str(&name));
+  })
+  .unwrap();
+  assert_eq!(bytes, b"David");
+}
+
+#[test]
+fn append_nested_refs() {
+  let parts = ["a", "b"];
+  let owned = "c".to_string();
+  let owned_refs = [&owned];
+  let chars = [&'d'];
+  let text = StringBuilder::<String>::build(|builder| {
+    for part in parts.iter() {
+      builder.append(part);
+    }
+    for owned in owned_refs.iter() {
+      builder.append(owned);
+    }
+    for c in chars.iter() {
+      builder.append(c);
+    }
+  })
+  .unwrap();
+  assert_eq!(text, "abcd");
+
+  let cow = StringBuilder::build_cow(|builder| {
+    builder.append(parts.first().unwrap());
+  })
+  .unwrap();
+  assert!(matches!(cow, std::borrow::Cow::Borrowed("a")));
+
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    for part in parts.iter() {
+      builder.append(part);
+    }
+  })
+  .unwrap();
+  assert_eq!(bytes, b"ab");
+}
+
+#[test]
+fn append_char_slice() {
+  let chars = vec!['h', 'é', '😀'];
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(chars.as_slice());
+    builder.append(&chars[..1]);
+  })
+  .unwrap();
+  assert_eq!(text, "hé😀h");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_title_case() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_title_case("hello wORLD  ßtraße\tésta ǆUNGLA ǈ");
+  })
+  .unwrap();
+  assert_eq!(text, "Hello World  SStraße\tÉsta ǅungla ǈ");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_trimmed() {
+  let input = "  hello \n";
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_trimmed(input);
+    builder.append('|');
+    builder.append_trimmed_start(input);
+    builder.append('|');
+    builder.append_trimmed_end(input);
+  })
+  .unwrap();
+  assert_eq!(text, "hello|hello \n|  hello");
+  assert_eq!(text.capacity(), text.len());
+
+  let cow = StringBuilder::build_cow(|builder| {
+    builder.append_trimmed(input);
+  })
+  .unwrap();
+  assert!(matches!(cow, std::borrow::Cow::Borrowed("hello")));
+}
+
+#[test]
+fn append_collapse_whitespace() {
+  let cases = [
+    ("a  b\t\n c d", "a b c d"),
+    ("  leading", " leading"),
+    ("trailing \r\n", "trailing "),
+    (" ", " "),
+    ("", ""),
+    ("none", "none"),
+  ];
+  for (input, expected) in cases {
+    let text = StringBuilder::<String>::build(|builder| {
+      builder.append_collapse_whitespace(input);
+    })
+    .unwrap();
+    assert_eq!(text, expected);
+    assert_eq!(text.capacity(), text.len());
+  }
+}
+
+#[test]
+fn append_char_range() {
+  let value = "héllo😀!";
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_char_range(value, 1..3);
+    builder.append('|');
+    builder.append_char_range(value, 4..=5);
+    builder.append('|');
+    builder.append_char_range(value, ..2);
+    builder.append('|');
+    builder.append_char_range(value, 5..100);
+    builder.append('|');
+    builder.append_char_range(value, 10..);
+  })
+  .unwrap();
+  assert_eq!(text, "él|o😀|hé|😀!|");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[cfg(feature = "unicode-segmentation")]
+#[test]
+fn append_reversed() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_reversed("ab🇨🇦e\u{301}");
+  })
+  .unwrap();
+  assert_eq!(text, "e\u{301}🇨🇦ba");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_float_engineering() {
+  let cases = [
+    (12345.0, 3, "12.3e3"),
+    (0.00123, 3, "1.23e-3"),
+    (-0.000456, 2, "-460e-6"),
+    (12345.0, 1, "10e3"),
+    (999.9, 3, "1.00e3"),
+    (1.0, 4, "1.000e0"),
+    (0.0, 2, "0.0e0"),
+    (f64::INFINITY, 3, "inf"),
+  ];
+  for (value, sig_figs, expected) in cases {
+    let text = StringBuilder::<String>::build(|builder| {
+      builder.append_float_engineering(value, sig_figs);
+    })
+    .unwrap();
+    assert_eq!(text, expected);
+    assert_eq!(text.capacity(), text.len());
+  }
+}
+
+#[test]
+fn append_percent() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_percent(0.425, 1);
+    builder.append(' ');
+    builder.append_percent(1.0, 0);
+    builder.append(' ');
+    builder.append_percent(-0.00126, 2);
+  })
+  .unwrap();
+  assert_eq!(text, "42.5% 100% -0.13%");
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_fixed_point() {
+  let cases = [
+    (123456, 2, None, "1234.56"),
+    (-123456, 2, Some(','), "-1,234.56"),
+    (5, 3, None, "0.005"),
+    (-5, 1, None, "-0.5"),
+    (0, 2, None, "0.00"),
+    (1234567, 0, Some('_'), "1_234_567"),
+    (100, 0, Some(','), "100"),
+    (i64::MIN, 4, Some(' '), "-922 337 203 685 477.5808"),
+    (7, 25, None, "0.0000000000000000000000007"),
+  ];
+  for (value, scale, separator, expected) in cases {
+    let text = StringBuilder::<String>::build(|builder| match separator {
+      Some(separator) => {
+        builder.append_fixed_point_grouped(value, scale, separator)
+      }
+      None => builder.append_fixed_point(value, scale),
+    })
+    .unwrap();
+    assert_eq!(text, expected);
+    assert_eq!(text.capacity(), text.len());
+  }
+}
+
+#[test]
+fn int_digit_boundaries_capacity() {
+  let mut expected = String::new();
+  let text = StringBuilder::<String>::build(|builder| {
+    let mut value = 1u128;
+    for _ in 0..39 {
+      builder.append(value - 1);
+      builder.append(value);
+      value = value.saturating_mul(10);
+    }
+    builder.append(u128::MAX);
+    builder.append(u8::MAX);
+    builder.append(i8::MIN);
+    builder.append(i16::MIN);
+    builder.append(u64::MAX);
+  })
+  .unwrap();
+  let mut value = 1u128;
+  for _ in 0..39 {
+    expected.push_str(&(value - 1).to_string());
+    expected.push_str(&value.to_string());
+    value = value.saturating_mul(10);
+  }
+  expected.push_str(&u128::MAX.to_string());
+  expected.push_str(&u8::MAX.to_string());
+  expected.push_str(&i8::MIN.to_string());
+  expected.push_str(&i16::MIN.to_string());
+  expected.push_str(&u64::MAX.to_string());
+  assert_eq!(text, expected);
+  assert_eq!(text.capacity(), text.len());
+}
+
+#[test]
+fn append_ints() {
+  let values = (0..1000u64).map(|i| i * 7919).collect::<Vec<_>>();
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_ints(&values, ", ");
+  })
+  .unwrap();
+  let expected = values
+    .iter()
+    .map(|value| value.to_string())
+    .collect::<Vec<_>>()
+    .join(", ");
+  assert_eq!(text, expected);
+  assert_eq!(text.capacity(), text.len());
+
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_ints(&[-1i32, 20, -300], "");
+    builder.append('|');
+    builder.append_ints(&[] as &[u8], ",");
+  })
+  .unwrap();
+  assert_eq!(text, "-120-300|");
+  assert_eq!(text.capacity(), text.len());
+
+  let display = format!("{}", capacity_builder::DisplayOf(&Ints));
+  assert_eq!(display, "1 2 3");
+
+  struct Ints;
+  impl<'a> StringAppendable<'a> for &'a Ints {
+    fn append_to_builder<TString: StringType>(
+      self,
+      builder: &mut StringBuilder<'a, TString>,
+    ) {
+      builder.append_ints(&[1u16, 2, 3], " ");
+    }
+  }
+}
+
+#[test]
+fn small_string() {
+  let text = StringBuilder::<SmallString<16>>::build(|builder| {
+    builder.append("id-");
+    builder.append(12345);
+  })
+  .unwrap();
+  assert_eq!(text, "id-12345");
+  assert!(text.is_inline());
+
+  let text = StringBuilder::<SmallString<4>>::build(|builder| {
+    builder.append("Hello ");
+    builder.append(&text);
+  })
+  .unwrap();
+  assert_eq!(text, "Hello id-12345");
+  assert!(!text.is_inline());
+  assert_eq!(text.into_string(), "Hello id-12345");
+
+  let text = StringBuilder::<SmallString<8>>::build(|builder| {
+    builder.append("b");
+    builder.prepend("a");
+    builder.append("c");
+  })
+  .unwrap();
+  assert_eq!(text, "abc");
+  assert!(text.is_inline());
+}
+
+#[test]
+fn bytes_build_in_place() {
+  let mut requested = None;
+  let buffer = BytesBuilder::build_in_place(
+    |size| {
+      requested = Some(size);
+      Ok(vec![0xFF; size + 2])
+    },
+    |builder| {
+      builder.append("abc");
+      builder.append_le(2u32);
+    },
+  )
+  .unwrap();
+  assert_eq!(requested, Some(7));
+  assert_eq!(buffer, [b'a', b'b', b'c', 2, 0, 0, 0, 0xFF, 0xFF]);
+
+  let err = BytesBuilder::build_in_place(
+    |_| Ok([0u8; 2]),
+    |builder| builder.append("abc"),
+  )
+  .unwrap_err();
+  assert!(matches!(
+    err,
+    capacity_builder::Error::CapacityExceeded { size: 3, max: 2 }
+  ));
+
+  let err = BytesBuilder::build_in_place(
+    |_| Err::<Vec<u8>, _>(std::io::Error::other("mapping failed")),
+    |builder| builder.append("abc"),
+  )
+  .unwrap_err();
+  assert!(matches!(err, capacity_builder::Error::Io(_)));
+}
+
+#[test]
+fn build_into_raw() {
+  use capacity_builder::slice::BuildRawResult;
+
+  let build = |builder: &mut StringBuilder| {
+    builder.append("value: ");
+    builder.append(42);
+  };
+  let result =
+    unsafe { StringBuilder::build_into_raw(std::ptr::null_mut(), 0, build) };
+  assert_eq!(
+    result,
+    BuildRawResult {
+      needed: 9,
+      written: 0
+    }
+  );
+  let mut buffer = vec![0u8; result.needed + 1];
+  let result = unsafe {
+    StringBuilder::build_into_raw(buffer.as_mut_ptr(), buffer.len(), build)
+  };
+  assert_eq!(
+    result,
+    BuildRawResult {
+      needed: 9,
+      written: 9
+    }
+  );
+  assert_eq!(&buffer[..result.written], b"value: 42");
+
+  let build = |builder: &mut BytesBuilder<_>| {
+    builder.append_be(7u16);
+  };
+  let mut buffer = [0u8; 1];
+  let result =
+    unsafe { BytesBuilder::build_into_raw(buffer.as_mut_ptr(), 1, build) };
+  assert_eq!(
+    result,
+    BuildRawResult {
+      needed: 2,
+      written: 0
+    }
+  );
+  let mut buffer = [0u8; 2];
+  let result =
+    unsafe { BytesBuilder::build_into_raw(buffer.as_mut_ptr(), 2, build) };
+  assert_eq!(result.written, 2);
+  assert_eq!(buffer, [0, 7]);
+}
+
+#[test]
+fn bytes_append_raw() {
+  let data = b"foreign data".to_vec();
+  let (ptr, len) = (data.as_ptr(), data.len());
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append("[");
+    unsafe {
+      builder.append_raw(ptr, len);
+      builder.append_raw(std::ptr::null(), 0);
+    }
+    builder.append("]");
+  })
+  .unwrap();
+  assert_eq!(bytes, b"[foreign data]");
+  assert_eq!(bytes.capacity(), bytes.len());
+}
+
+#[test]
+fn bytes_vec_spare_capacity() {
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    for i in 0..300u16 {
+      builder.append(i as u8);
+      builder.append_be(i);
+    }
+  })
+  .unwrap();
+  let expected = (0..300u16)
+    .flat_map(|i| [i as u8, (i >> 8) as u8, i as u8])
+    .collect::<Vec<_>>();
+  assert_eq!(bytes, expected);
+  assert_eq!(bytes.capacity(), bytes.len());
+
+  // writing exactly up to the capacity doesn't grow the vec
+  let mut bytes = Vec::with_capacity(3);
+  BytesTypeMut::push(&mut bytes, 1);
+  BytesTypeMut::extend_from_slice(&mut bytes, &[2, 3]);
+  assert_eq!(bytes, [1, 2, 3]);
+  assert_eq!(bytes.capacity(), 3);
+
+  // writing past the capacity still grows the vec
+  let mut bytes = Vec::with_capacity(1);
+  BytesTypeMut::push(&mut bytes, 1);
+  BytesTypeMut::push(&mut bytes, 2);
+  BytesTypeMut::extend_from_slice(&mut bytes, &[3, 4, 5]);
+  assert_eq!(bytes, [1, 2, 3, 4, 5]);
+
+  // including when some of the slice would fit in the spare capacity
+  let mut bytes = Vec::with_capacity(4);
+  BytesTypeMut::extend_from_slice(&mut bytes, &[1, 2]);
+  BytesTypeMut::extend_from_slice(&mut bytes, &[3, 4, 5]);
+  assert_eq!(bytes, [1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn append_cached() {
+  use std::cell::Cell;
+  use std::fmt::Write;
+
+  let calls = Cell::new(0);
+  let text = StringBuilder::<String>::build(|builder| {
+    for i in 0..3 {
+      builder.append_cached(|text| {
+        calls.set(calls.get() + 1);
+        write!(text, "[{}]", i * 11).unwrap();
+      });
+      builder.append(',');
+    }
+  })
+  .unwrap();
+  assert_eq!(text, "[0],[11],[22],");
+  assert_eq!(text.capacity(), text.len());
+  assert_eq!(calls.get(), 3);
+
+  // single pass builds write it directly
+  assert!(StringBuilder::<String>::matches("abc", |builder| {
+    builder.append('a');
+    builder.append_cached(|text| text.push('b'));
+    builder.append_cached(|text| text.push('c'));
+  }));
+}
+
+#[test]
+fn precompute_literals() {
+  #[capacity_builder::precompute]
+  fn build_text<'a>(
+    builder: &mut StringBuilder<'a>,
+    name: &'a str,
+    count: usize,
+  ) {
+    builder.append("Hello, ");
+    builder.append(name);
+    builder.append('!');
+    for _ in 0..count {
+      builder.append(" again");
+    }
+    builder.append(" 😀");
+  }
+
+  #[capacity_builder::precompute]
+  fn build_bytes(builder: &mut BytesBuilder<'_, Vec<u8>>) {
+    builder.append("ab");
+    builder.append_be(1u16);
+    builder.append('c');
+  }
+
+  let name = "world".to_string();
+  let text =
+    StringBuilder::<String>::build(|builder| build_text(builder, &name, 2))
+      .unwrap();
+  assert_eq!(text, "Hello, world! again again 😀");
+  assert_eq!(text.capacity(), text.len());
+
+  let mut state =
+    StringBuilder::<String>::build_cow(|builder| build_text(builder, &name, 0))
+      .unwrap();
+  assert_eq!(state.to_mut(), "Hello, world! 😀");
+
+  let bytes = BytesBuilder::<Vec<u8>>::build(build_bytes).unwrap();
+  assert_eq!(bytes, b"ab\0\x01c");
+  assert_eq!(bytes.capacity(), bytes.len());
+}
+
+#[test]
+fn hex_display() {
+  use std::fmt::Write;
+
+  let data = (0..=255u8).collect::<Vec<_>>();
+  let mut expected = String::new();
+  for byte in &data {
+    write!(expected, "{:02x}", byte).unwrap();
+  }
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append(HexDisplay(&data));
+  })
+  .unwrap();
+  assert_eq!(text, expected);
+  assert_eq!(text.capacity(), text.len());
+
+  let bytes = BytesBuilder::<Vec<u8>>::build(|builder| {
+    builder.append(HexDisplay(&[0xde, 0xad]));
+  })
+  .unwrap();
+  assert_eq!(bytes, b"dead");
+
+  let value = HexDisplay(&[0xab, 0x01, 0xff]);
+  assert_eq!(value.to_string(), "ab01ff");
+  assert_eq!(format!("{:x}", value), "ab01ff");
+  assert_eq!(format!("{:X}", value), "AB01FF");
+  assert_eq!(HexDisplay(&[]).to_string(), "");
+}
+
+#[test]
+fn append_hexdump() {
+  let data = b"Hello, world! This is a hexdump.\n\x00\x01\xff".to_vec();
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_hexdump(&data);
+  })
+  .unwrap();
+  assert_eq!(
+    text,
+    concat!(
+      "00000000  48 65 6c 6c 6f 2c 20 77  6f 72 6c 64 21 20 54 68  |Hello, world! Th|\n",
+      "00000010  69 73 20 69 73 20 61 20  68 65 78 64 75 6d 70 2e  |is is a hexdump.|\n",
+      "00000020  0a 00 01 ff                                       |....|\n",
+      "00000024\n",
+    )
+  );
+  assert_eq!(text.capacity(), text.len());
+
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_hexdump(&[]);
+  })
+  .unwrap();
+  assert_eq!(text, "");
+
+  let data = (0..=255u8).cycle().take(1000).collect::<Vec<_>>();
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append_hexdump(&data);
+  })
+  .unwrap();
+  assert_eq!(text.lines().count(), 64);
+  assert!(text.ends_with("000003e0  e0 e1 e2 e3 e4 e5 e6 e7                           |........|\n000003e8\n"));
+  assert_eq!(text.capacity(), text.len());
+}
+
+fn build_with_edits(builder: &mut StringBuilder<String>) {
+  builder.append("items: ");
+  for item in ["a", "bé", "c"] {
+    builder.append(item);
+    builder.append(", ");
+  }
+  builder.pop_last(2);
+  let checkpoint = builder.checkpoint();
+  builder.append(" (rolled back)");
+  builder.rollback(checkpoint);
+  builder.append('!');
+}
+
+#[test]
+fn sink_matches_with_edits() {
+  assert!(StringBuilder::matches("items: a, bé, c!", build_with_edits));
+  assert!(!StringBuilder::matches(
+    "items: a, bé, c, !",
+    build_with_edits
+  ));
+  assert!(StringBuilder::starts_with(
+    "items: a, bé, c!",
+    build_with_edits
+  ));
+  assert!(!StringBuilder::starts_with(
+    "items: a, bé, c (",
+    build_with_edits
+  ));
+  assert!(StringBuilder::ends_with("bé, c!", build_with_edits));
+  assert!(!StringBuilder::ends_with("back)!", build_with_edits));
+}
+
+#[test]
+fn sink_matches_runs_once_without_edits() {
+  let runs = std::cell::Cell::new(0);
+  assert!(StringBuilder::matches("a1", |builder| {
+    runs.set(runs.get() + 1);
+    builder.append("a");
+    builder.append(1);
+  }));
+  assert!(StringBuilder::ends_with("1", |builder| {
+    runs.set(runs.get() + 1);
+    builder.append("a");
+    builder.append(1);
+  }));
+  assert_eq!(runs.get(), 2);
+
+  assert!(StringBuilder::matches("items: a, bé, c!", |builder| {
+    runs.set(runs.get() + 1);
+    build_with_edits(builder);
+  }));
+  assert_eq!(runs.get(), 4);
+}
+
+#[test]
+#[should_panic(expected = "truncating is not supported when hashing")]
+fn sink_hash_into_with_edits() {
+  let mut hasher = std::collections::hash_map::DefaultHasher::new();
+  StringBuilder::hash_into(&mut hasher, build_with_edits);
+}
+
+#[test]
+fn sink_build_utf16_with_edits() {
+  let units = StringBuilder::<String>::build_utf16(build_with_edits).unwrap();
+  assert_eq!(String::from_utf16(&units).unwrap(), "items: a, bé, c!");
+  assert_eq!(units.capacity(), units.len());
+}
+
+#[test]
+fn sink_build_into_raw_with_edits() {
+  let result = unsafe {
+    StringBuilder::build_into_raw(std::ptr::null_mut(), 0, build_with_edits)
+  };
+  // the peak length before rolling back
+  assert_eq!(result.needed, 30);
+  let mut buffer = vec![0u8; result.needed];
+  let result = unsafe {
+    StringBuilder::build_into_raw(
+      buffer.as_mut_ptr(),
+      buffer.len(),
+      build_with_edits,
+    )
+  };
+  assert_eq!(&buffer[..result.written], "items: a, bé, c!".as_bytes());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn sink_build_par_with_edits() {
+  let text = StringBuilder::<String>::build_par(&[1, 2], |builder, item| {
+    build_with_edits(builder);
+    builder.append(*item);
+  })
+  .unwrap();
+  assert_eq!(text, "items: a, bé, c!1items: a, bé, c!2");
+}
+
+fn build_with_inserts<TString: StringType>(
+  builder: &mut StringBuilder<'_, TString>,
+) {
+  builder.append("# Report\n");
+  let summary = builder.mark();
+  for item in ["- é\n", "- 22\n"] {
+    builder.append(item);
+  }
+  builder.insert_at(summary, "items: 2\n");
+  builder.prepend("<report>\n");
+  builder.append("end");
+}
+
+const INSERTS_TEXT: &str = "<report>\n# Report\nitems: 2\n- é\n- 22\nend";
+
+#[test]
+fn sink_matches_with_inserts() {
+  assert!(StringBuilder::matches(INSERTS_TEXT, build_with_inserts));
+  assert!(StringBuilder::starts_with(
+    "<report>\n#",
+    build_with_inserts
+  ));
+  assert!(StringBuilder::ends_with("- 22\nend", build_with_inserts));
+  assert!(!StringBuilder::starts_with("# Report", build_with_inserts));
+}
+
+#[test]
+#[should_panic(expected = "inserting is not supported when hashing")]
+fn sink_hash_into_with_inserts() {
+  let mut hasher = std::collections::hash_map::DefaultHasher::new();
+  StringBuilder::hash_into(&mut hasher, build_with_inserts);
+}
+
+#[test]
+fn sink_build_utf16_with_inserts() {
+  let units = StringBuilder::<String>::build_utf16(build_with_inserts).unwrap();
+  assert_eq!(String::from_utf16(&units).unwrap(), INSERTS_TEXT);
+}
+
+#[test]
+fn sink_build_into_raw_with_inserts() {
+  let mut buffer = [0u8; 64];
+  let result = unsafe {
+    StringBuilder::build_into_raw(
+      buffer.as_mut_ptr(),
+      buffer.len(),
+      build_with_inserts,
+    )
+  };
+  assert_eq!(&buffer[..result.written], INSERTS_TEXT.as_bytes());
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn sink_build_par_with_inserts() {
+  let text = StringBuilder::<String>::build_par(&[(), ()], |builder, _| {
+    build_with_inserts(builder);
+  })
+  .unwrap();
+  assert_eq!(text, INSERTS_TEXT.repeat(2));
+}
+
+struct ReportDisplay;
+
+impl std::fmt::Display for ReportDisplay {
+  fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    StringBuilder::fmt_with_edits(f, |builder| {
+      build_with_inserts(builder);
+      builder.append(", ");
+      let checkpoint = builder.checkpoint();
+      builder.append("rolled back");
+      builder.rollback(checkpoint);
+      builder.pop_last(2);
+    })
+  }
+}
+
+#[test]
+fn fmt_with_edits() {
+  assert_eq!(ReportDisplay.to_string(), INSERTS_TEXT);
+  assert_eq!(format!("{ReportDisplay:>1}"), INSERTS_TEXT);
+}
+
+#[test]
+#[should_panic(expected = "use fmt_with_edits")]
+fn fmt_with_inserts_panics() {
+  struct Inserts;
+
+  impl std::fmt::Display for Inserts {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+      StringBuilder::fmt(f, build_with_inserts)
+    }
+  }
+
+  let _ = Inserts.to_string();
+}
+
+#[test]
+fn segments_with_inserts() {
+  let segments = StringBuilder::build_segments(build_with_inserts);
+  assert_eq!(segments.iter().collect::<String>(), INSERTS_TEXT);
+  assert_eq!(segments.len(), INSERTS_TEXT.len());
+
+  let segments = StringBuilder::build_segments(|builder| {
+    let start = builder.mark();
+    builder.append("ab");
+    builder.append(1);
+    builder.append("cd");
+    builder.append(23);
+    builder.pop_last(1);
+    builder.insert_at(start, 4);
+    builder.append(5);
+  });
+  assert_eq!(segments.iter().collect::<String>(), "4ab1cd25");
+}
+
+#[test]
+fn display_with_edits() {
+  #[derive(CapacityDisplay)]
+  #[capacity_display(edits, debug)]
+  struct Report;
+
+  impl<'a> StringAppendable<'a> for &'a Report {
+    fn append_to_builder<TString: StringType>(
+      self,
+      builder: &mut StringBuilder<'a, TString>,
+    ) {
+      build_with_inserts(builder);
+      builder.append(", ");
+      builder.pop_last(2);
+    }
+  }
+
+  assert_eq!(format!("{}", Report), INSERTS_TEXT);
+  assert_eq!(Report.to_string(), INSERTS_TEXT);
+  assert_eq!(format!("{:?}", Report), INSERTS_TEXT);
+}
+
+#[test]
+fn insert_at_after_prepend_in_sinks() {
+  let build = |builder: &mut StringBuilder<'_, String>| {
+    let start = builder.mark();
+    builder.append("b");
+    builder.prepend("a");
+    builder.insert_at(start, "0");
+    builder.append("c");
+  };
+  let text = StringBuilder::<String>::build(build).unwrap();
+  assert_eq!(text, "0abc");
+  assert!(StringBuilder::matches(&text, build));
+  let units = StringBuilder::<String>::build_utf16(build).unwrap();
+  assert_eq!(String::from_utf16(&units).unwrap(), text);
+  let segments = StringBuilder::build_segments(build);
+  assert_eq!(segments.iter().collect::<String>(), text);
+}
+
+#[test]
+fn bytes_type_without_slices_from() {
+  struct Counted(usize);
+
+  impl BytesType for Counted {
+    type MutType = CountedMut;
+
+    fn with_capacity(
+      _size: usize,
+    ) -> Result<Self::MutType, std::collections::TryReserveError> {
+      Ok(CountedMut(0))
+    }
+
+    fn from_mut(inner: Self::MutType) -> Self {
+      Counted(inner.0)
+    }
+  }
+
+  struct CountedMut(usize);
+
+  impl BytesTypeMut for CountedMut {
+    fn push(&mut self, _c: u8) {
+      self.0 += 1;
+    }
+
+    fn extend_from_slice(&mut self, bytes: &[u8]) {
+      self.0 += bytes.len();
+    }
+
+    fn len(&self) -> usize {
+      self.0
+    }
+  }
+
+  let counted = BytesBuilder::<Counted>::build(|builder| {
+    builder.append("abc");
+    builder.append_be(1u32);
+  })
+  .unwrap();
+  assert_eq!(counted.0, 7);
+}
+
+#[cfg(feature = "unsafe-fast")]
+#[test]
+fn unsafe_fast_string_appends() {
+  let text = StringBuilder::<String>::build(|builder| {
+    builder.append('a');
+    builder.append('é');
+    builder.append('😀');
+    builder.append("bc");
+    builder.append("日本");
+  })
+  .unwrap();
+  assert_eq!(text, "aé😀bc日本");
+  assert_eq!(text.capacity(), text.len());
+
+  // writing exactly up to the capacity doesn't grow the string
+  let mut text = String::with_capacity(6);
+  StringTypeMut::push(&mut text, 'a');
+  StringTypeMut::push(&mut text, 'é');
+  StringTypeMut::push_str(&mut text, "日");
+  assert_eq!(text, "aé日");
+  assert_eq!(text.capacity(), 6);
+
+  // writing past the capacity still grows the string
+  StringTypeMut::push(&mut text, 'b');
+  StringTypeMut::push(&mut text, 'é');
+  assert_eq!(text, "aé日bé");
+  let mut text = String::with_capacity(4);
+  StringTypeMut::push_str(&mut text, "ab");
+  StringTypeMut::push_str(&mut text, "cdé");
+  StringTypeMut::push(&mut text, '😀');
+  assert_eq!(text, "abcdé😀");
+}